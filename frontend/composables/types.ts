@@ -24,16 +24,60 @@
  *
  */
 
+export interface ImageVariant {
+    webp: string;
+    jpeg: string;
+    width: number;
+    height: number;
+}
+
+export interface ImageVariants {
+    thumbnail: ImageVariant;
+    card: ImageVariant;
+    full: ImageVariant;
+}
+
 export interface News {
     id: string;
     title: string;
+    language: string;
     shown: boolean;
+    format: "html" | "markdown";
+    source?: string;
     content?: string;
+    publishAt?: Date;
+    unpublishAt?: Date;
+    pinned: boolean;
+    priority: number;
+    images?: ImageVariants;
+    revision: number;
+    author?: string;
     createdAt: Date;
+    updatedAt?: Date;
 }
 
 export interface SchematicEntry {
     id: string;
     name: string;
     owner: string;
+    tags: string[];
+    size: number;
+    width: number;
+    height: number;
+    length: number;
+    blocks: number;
+    createdAt: string;
+}
+
+export interface Event {
+    id: string;
+    name: string;
+    description: string;
+    start: Date;
+    end: Date;
+    status: "draft" | "published" | "running" | "finished" | "cancelled";
+    capacity?: number;
+    checkIn?: number;
+    images?: ImageVariants;
+    createdAt: Date;
 }
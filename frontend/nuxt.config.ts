@@ -8,7 +8,12 @@ export default defineNuxtConfig({
             charset: "utf-8",
             viewport: "width=device-width, initial-scale=1",
             meta: [{name: "format-detection", content: "telephone=no"}],
-            link: [{rel: "icon", type: "image/x-icon", href: "/favicon.ico"}],
+            link: [
+                {rel: "icon", type: "image/x-icon", href: "/favicon.ico"},
+                {rel: "alternate", type: "application/rss+xml", title: "News", href: "/api/news/feed.rss"},
+                {rel: "alternate", type: "application/atom+xml", title: "News", href: "/api/news/feed.atom"},
+                {rel: "alternate", type: "application/rss+xml", title: "Events", href: "/api/event/feed.rss"},
+            ],
             style: [],
             script: [],
         },
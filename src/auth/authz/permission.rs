@@ -87,7 +87,18 @@ permissions!(
     (EVENT_FIGHT_UPDATE, "event.fight.update"),
     (EVENT_FIGHT_DELETE, "event.fight.delete"),
     // --------------------------------
-    (ACCOUNT_PERMISSION_GET, "account.permission.get")
+    (SEASON_CREATE, "season.create"),
+    (SEASON_UPDATE, "season.update"),
+    // --------------------------------
+    (CLAN_DELETE, "clan.delete"),
+    // --------------------------------
+    (AUDIT_READ, "audit.read"),
+    // --------------------------------
+    (ACCOUNT_PERMISSION_GET, "account.permission.get"),
+    // --------------------------------
+    (MACHINE_CLIENT_CREATE, "machine.client.create"),
+    // --------------------------------
+    (SCHEMATIC_QUOTA_UPDATE, "schematic.quota.update")
 );
 
 pub async fn init_permissions(connection: &DatabaseConnection) -> Result<()> {
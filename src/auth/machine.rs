@@ -0,0 +1,114 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::{Session, SessionType, ALPHABET};
+use crate::prelude::*;
+use argon2::password_hash::SaltString;
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use chacha20poly1305::aead::OsRng;
+use chrono::{DateTime, Utc};
+
+/// The length of generated client secrets
+const SECRET_LENGTH: usize = 48;
+
+/// An API-Client like a game server plugin, authenticated by its id and a secret
+#[derive(Getters, Deserialize, Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct MachineClient {
+    id: Id,
+    /// a name describing the client
+    name: String,
+    /// the argon2id hash of the secret
+    #[serde(skip_serializing)]
+    secret: String,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// A newly created client together with its secret, which can't be obtained later on
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineCredentials {
+    client_id: String,
+    name: String,
+    secret: String,
+}
+
+impl MachineClient {
+    /// Creates a new client with a random secret
+    #[instrument(skip(connection))]
+    pub async fn create(name: &str, connection: &DatabaseConnection) -> Result<MachineCredentials> {
+        if name.trim().is_empty() {
+            return Err(ApplicationError::BadRequest(
+                "the name can't be empty".to_owned(),
+            ));
+        }
+
+        let secret = nanoid::nanoid!(SECRET_LENGTH, &ALPHABET);
+        let salt = SaltString::generate(&mut OsRng);
+        let hash = Argon2::default()
+            .hash_password(secret.as_bytes(), &salt)?
+            .to_string();
+
+        let client: MachineClient = sql_span!(
+            connection
+                .create("machine_client")
+                .content(&serde_json::json!({
+                    "name": name.trim(),
+                    "secret": hash
+                }))
+                .await?
+        );
+        Ok(MachineCredentials {
+            client_id: client.id.to_string(),
+            name: client.name,
+            secret,
+        })
+    }
+
+    /// Fetch a client by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        let client: Option<MachineClient> = sql_span!(
+            connection
+                .select(&Id::try_from(("machine_client", id))?)
+                .await?
+        );
+
+        Ok(client)
+    }
+
+    /// Verifies the secret and starts a new session for the client
+    #[instrument(skip_all)]
+    pub async fn login(&self, secret: &str, connection: &DatabaseConnection) -> Result<Session> {
+        Argon2::default()
+            .verify_password(secret.as_bytes(), &PasswordHash::new(self.secret.as_str())?)
+            .map_err(|_| ApplicationError::Unauthorized)?;
+
+        Session::init(SessionType::Machine(self.id.clone()), connection).await
+    }
+}
@@ -34,6 +34,7 @@ use chacha20poly1305::{AeadCore, KeyInit, XChaCha20Poly1305, XNonce};
 use totp_rs::{Algorithm, Secret, TOTP};
 
 pub mod authz;
+pub mod machine;
 pub mod session;
 
 #[async_trait]
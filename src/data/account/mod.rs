@@ -74,6 +74,18 @@ impl Account {
         Ok(account)
     }
 
+    /// Get the account linked with the given minecraft uuid
+    #[instrument(skip(connection))]
+    pub async fn from_uuid(uuid: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        let account = sql_span!(connection
+            .query("SELECT * FROM account WHERE uuid = $uuid")
+            .bind(("uuid", uuid))
+            .await?
+            .take::<Option<Account>>(0)?);
+
+        Ok(account)
+    }
+
     /// Get an instance of an account by the given id. This operation will fail if the id
     /// cant be associated with an account.
     #[instrument(skip(connection))]
@@ -0,0 +1,298 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::asset::{Asset, NewAsset};
+use crate::prelude::*;
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, ImageOutputFormat};
+use std::io::Cursor;
+
+/// A resized variant of an uploaded image
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ImageVariant {
+    /// the url of the webp encoded variant
+    pub webp: String,
+    /// the url of the jpeg encoded variant for clients without webp support
+    pub jpeg: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// All variants of an uploaded image. The original upload itself is not stored.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct ImageVariants {
+    /// cropped to 16:9, at most 320x180
+    pub thumbnail: ImageVariant,
+    /// cropped to 16:9, at most 800x450
+    pub card: ImageVariant,
+    /// the uncropped image, at most 1920x1080
+    pub full: ImageVariant,
+}
+
+/// name, bounds and whether the image is cropped to the aspect ratio of the bounds
+const VARIANTS: [(&str, (u32, u32), bool); 3] = [
+    ("thumbnail", (320, 180), true),
+    ("card", (800, 450), true),
+    ("full", (1920, 1080), false),
+];
+
+/// The quality of the lossy webp variants, matching the quality of the jpeg variants
+const WEBP_QUALITY: f32 = 85.0;
+
+/// The encoded files of a single variant
+struct Encoded {
+    webp: Vec<u8>,
+    jpeg: Vec<u8>,
+    width: u32,
+    height: u32,
+}
+
+fn invalid() -> ApplicationError {
+    ApplicationError::BadRequest("invalid image data".to_owned())
+}
+
+/// Decodes the given image, rejecting images exceeding the configured pixel limit before decoding
+fn decode(data: &[u8]) -> Result<DynamicImage> {
+    let reader = || {
+        ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|_| invalid())
+    };
+
+    let (width, height) = reader()?.into_dimensions().map_err(|_| invalid())?;
+    if width as u64 * height as u64 > *IMAGE_PIXEL_LIMIT {
+        return Err(ApplicationError::BadRequest(format!(
+            "image exceeds the maximum of {} pixels",
+            *IMAGE_PIXEL_LIMIT
+        )));
+    }
+
+    let image = reader()?.decode().map_err(|_| invalid())?;
+    Ok(orient(image, orientation(data)))
+}
+
+/// Reads the EXIF orientation of the image. Images without EXIF data are upright.
+fn orientation(data: &[u8]) -> u32 {
+    exif::Reader::new()
+        .read_from_container(&mut Cursor::new(data))
+        .ok()
+        .and_then(|exif| {
+            exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+                .and_then(|field| field.value.get_uint(0))
+        })
+        .unwrap_or(1)
+}
+
+/// Rotates and flips the image as described by the EXIF orientation, because the metadata is
+/// dropped when the variants are encoded.
+fn orient(image: DynamicImage, orientation: u32) -> DynamicImage {
+    match orientation {
+        2 => image.fliph(),
+        3 => image.rotate180(),
+        4 => image.flipv(),
+        5 => image.rotate90().fliph(),
+        6 => image.rotate90(),
+        7 => image.rotate270().fliph(),
+        8 => image.rotate270(),
+        _ => image,
+    }
+}
+
+/// Fits the image into the bounds without upscaling it. Cropped variants are cut to the aspect
+/// ratio of the bounds around the center first.
+fn resize(image: &DynamicImage, (max_width, max_height): (u32, u32), crop: bool) -> DynamicImage {
+    let image = if crop {
+        let (width, height) = (image.width() as u64, image.height() as u64);
+        let (crop_width, crop_height) = if width * max_height as u64 > height * max_width as u64 {
+            (height * max_width as u64 / max_height as u64, height)
+        } else {
+            (width, width * max_height as u64 / max_width as u64)
+        };
+
+        image.crop_imm(
+            ((width - crop_width) / 2) as u32,
+            ((height - crop_height) / 2) as u32,
+            crop_width.max(1) as u32,
+            crop_height.max(1) as u32,
+        )
+    } else {
+        image.clone()
+    };
+
+    if image.width() > max_width || image.height() > max_height {
+        image.resize(max_width, max_height, FilterType::Lanczos3)
+    } else {
+        image
+    }
+}
+
+/// Encodes the image as lossy webp. The encoder of the image crate only supports lossless webp,
+/// which ends up larger than the jpeg.
+fn encode_webp(image: &DynamicImage) -> Result<Vec<u8>> {
+    let rgba = image.to_rgba8();
+    let encoded = webp::Encoder::from_rgba(rgba.as_raw(), rgba.width(), rgba.height())
+        .encode_simple(false, WEBP_QUALITY)
+        .map_err(|_| ApplicationError::InternalServerError)?;
+
+    Ok(encoded.to_vec())
+}
+
+fn encode(image: &DynamicImage, format: ImageOutputFormat) -> Result<Vec<u8>> {
+    let mut encoded = Cursor::new(Vec::new());
+    image
+        .write_to(&mut encoded, format)
+        .map_err(|_| ApplicationError::InternalServerError)?;
+
+    Ok(encoded.into_inner())
+}
+
+/// Decodes the image and encodes all variants. Only the decoded and oriented pixels are encoded
+/// again, which drops all metadata (e.g. EXIF) of the upload.
+fn process(data: &[u8]) -> Result<Vec<Encoded>> {
+    let image = decode(data)?;
+
+    VARIANTS
+        .iter()
+        .map(|(_, bounds, crop)| {
+            let variant = resize(&image, *bounds, *crop);
+            Ok(Encoded {
+                webp: encode_webp(&variant)?,
+                jpeg: encode(
+                    &DynamicImage::ImageRgb8(variant.to_rgb8()),
+                    ImageOutputFormat::Jpeg(85),
+                )?,
+                width: variant.width(),
+                height: variant.height(),
+            })
+        })
+        .collect()
+}
+
+impl ImageVariants {
+    /// Processes the uploaded image and stores its variants as assets of the owner. Previously
+    /// stored variants with the same name are replaced.
+    #[instrument(skip(data, connection))]
+    pub async fn store(
+        owner: &Id,
+        name: &str,
+        data: Vec<u8>,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        // resizing and encoding is cpu bound
+        let encoded = tokio::task::spawn_blocking(move || process(data.as_slice()))
+            .await
+            .map_err(|_| ApplicationError::InternalServerError)??;
+
+        let mut assets = vec![];
+        for ((variant, _, _), encoded) in VARIANTS.iter().zip(&encoded) {
+            assets.push(NewAsset {
+                name: format!("{name}.{variant}.webp"),
+                mime: "image/webp",
+                data: encoded.webp.as_slice(),
+            });
+            assets.push(NewAsset {
+                name: format!("{name}.{variant}.jpeg"),
+                mime: "image/jpeg",
+                data: encoded.jpeg.as_slice(),
+            });
+        }
+
+        // store all variants at once, so the record never serves a mix of old and new variants
+        let stored = Asset::store_all(owner, assets, connection).await?;
+        let variants = stored
+            .chunks(2)
+            .zip(&encoded)
+            .map(|(assets, encoded)| ImageVariant {
+                webp: assets[0].url(),
+                jpeg: assets[1].url(),
+                width: encoded.width,
+                height: encoded.height,
+            })
+            .collect::<Vec<ImageVariant>>();
+
+        let mut variants = variants.into_iter();
+        let mut next = || variants.next().ok_or(ApplicationError::InternalServerError);
+        Ok(Self {
+            thumbnail: next()?,
+            card: next()?,
+            full: next()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::asset::image::{orient, process, resize};
+    use image::{DynamicImage, ImageOutputFormat, RgbImage};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_resize() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(1000, 1000));
+
+        let cropped = resize(&image, (320, 180), true);
+        assert_eq!((320, 180), (cropped.width(), cropped.height()));
+
+        let full = resize(&image, (1920, 1080), false);
+        assert_eq!((1000, 1000), (full.width(), full.height()));
+
+        let small = DynamicImage::ImageRgb8(RgbImage::new(100, 100));
+        let cropped = resize(&small, (320, 180), true);
+        assert_eq!((100, 56), (cropped.width(), cropped.height()));
+    }
+
+    #[test]
+    fn test_process() {
+        let mut data = Cursor::new(Vec::new());
+        DynamicImage::ImageRgb8(RgbImage::new(400, 300))
+            .write_to(&mut data, ImageOutputFormat::Png)
+            .unwrap();
+
+        let variants = process(data.get_ref().as_slice()).unwrap();
+        assert_eq!(3, variants.len());
+        assert_eq!((320, 180), (variants[0].width, variants[0].height));
+        assert_eq!((400, 300), (variants[2].width, variants[2].height));
+        assert!(image::load_from_memory(variants[0].webp.as_slice()).is_ok());
+
+        assert!(process(b"not an image").is_err());
+    }
+
+    #[test]
+    fn test_orient() {
+        let image = DynamicImage::ImageRgb8(RgbImage::new(400, 300));
+
+        for (orientation, size) in [
+            (1, (400, 300)),
+            (3, (400, 300)),
+            (6, (300, 400)),
+            (8, (300, 400)),
+        ] {
+            let oriented = orient(image.clone(), orientation);
+            assert_eq!(size, (oriented.width(), oriented.height()));
+        }
+    }
+}
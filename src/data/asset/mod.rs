@@ -0,0 +1,240 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+pub mod image;
+
+/// A file owned by another record (e.g. the image of a news), which is stored below the configured
+/// asset root and served at `/asset/:asset_id`
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Getters)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct Asset {
+    id: Id,
+    /// the record owning the asset
+    owner: Id,
+    /// the purpose of the asset, unique per owner (e.g. `image`)
+    name: String,
+    /// the mime type the asset is served with
+    mime: String,
+    /// the size in bytes
+    size: u64,
+    /// the hex encoded sha256 hash of the content
+    hash: String,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// The content of an asset to be stored
+pub struct NewAsset<'a> {
+    /// the purpose of the asset, unique per owner
+    pub name: String,
+    pub mime: &'a str,
+    pub data: &'a [u8],
+}
+
+impl Asset {
+    /// Stores the given content as asset of the owner. An already existing asset with the same
+    /// name is replaced, which also changes the url, so assets can be cached indefinitely.
+    #[instrument(skip(data, connection))]
+    pub async fn store(
+        owner: &Id,
+        name: &str,
+        mime: &str,
+        data: &[u8],
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let asset = NewAsset {
+            name: name.to_owned(),
+            mime,
+            data,
+        };
+        Self::store_all(owner, vec![asset], connection)
+            .await?
+            .pop()
+            .ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Stores all given assets of the owner in one transaction like [`Asset::store`], so either
+    /// all or none of the existing assets with the same names are replaced.
+    #[instrument(skip_all)]
+    pub async fn store_all(
+        owner: &Id,
+        assets: Vec<NewAsset<'_>>,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<Self>> {
+        // write the files first, so records never point to missing files
+        tokio::fs::create_dir_all(ASSET_ROOT.as_path()).await?;
+        let mut paths = vec![];
+        let mut query = connection.query("BEGIN TRANSACTION");
+        let mut previous = vec![];
+        for (index, asset) in assets.iter().enumerate() {
+            let id = nanoid::nanoid!(32, &ALPHABET);
+            let hash = openssl::sha::sha256(asset.data)
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+
+            let path = Self::file(id.as_str());
+            if let Err(error) = tokio::fs::write(&path, asset.data).await {
+                Self::remove_files(&paths).await;
+                return Err(error.into());
+            }
+            paths.push(path);
+
+            // replace the previous record in the same transaction, as the name is unique per owner
+            previous.extend(Self::from_owner(owner, asset.name.as_str(), connection).await?);
+            query = query
+                .query(format!(
+                    "DELETE asset WHERE owner = $owner AND name = $name{index}"
+                ))
+                .query(format!(
+                    "CREATE $asset{index} SET owner = $owner, name = $name{index}, \
+                    mime = $mime{index}, size = $size{index}, hash = $hash{index}"
+                ))
+                .bind((
+                    format!("asset{index}"),
+                    Id::new(("asset", id.as_str())).to_thing(),
+                ))
+                .bind((format!("name{index}"), asset.name.as_str()))
+                .bind((format!("mime{index}"), asset.mime))
+                .bind((format!("size{index}"), asset.data.len() as u64))
+                .bind((format!("hash{index}"), hash));
+        }
+
+        let created = sql_span!(query
+            .query("COMMIT TRANSACTION")
+            .bind(("owner", owner.to_thing()))
+            .await
+            .and_then(|response| response.check())
+            .and_then(|mut response| {
+                (0..assets.len())
+                    .map(|index| response.take::<Option<Asset>>(index * 2 + 1))
+                    .collect::<std::result::Result<Option<Vec<Asset>>, _>>()
+            }));
+        let stored = match created {
+            Ok(Some(stored)) => stored,
+            result => {
+                Self::remove_files(&paths).await;
+                return Err(result
+                    .err()
+                    .map_or(ApplicationError::InternalServerError, Into::into));
+            }
+        };
+
+        for previous in previous {
+            match tokio::fs::remove_file(previous.path()).await {
+                Err(error) if error.kind() != std::io::ErrorKind::NotFound => {
+                    warn!("failed to remove the replaced asset file: {error}")
+                }
+                _ => {}
+            }
+        }
+        Ok(stored)
+    }
+
+    /// Removes the files of assets whose records could not be created
+    async fn remove_files(paths: &[PathBuf]) {
+        for path in paths {
+            let _ = tokio::fs::remove_file(path).await;
+        }
+    }
+
+    /// Fetch an asset by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        let asset: Option<Asset> =
+            sql_span!(connection.select(&Id::try_from(("asset", id))?).await?);
+
+        Ok(asset)
+    }
+
+    /// Fetch the asset of the owner with the given name
+    #[instrument(skip(connection))]
+    pub async fn from_owner(
+        owner: &Id,
+        name: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<Option<Self>> {
+        let asset = sql_span!(connection
+            .query("SELECT * FROM asset WHERE owner = $owner AND name = $name")
+            .bind(("owner", owner.to_thing()))
+            .bind(("name", name))
+            .await?
+            .take::<Option<Asset>>(0)?);
+
+        Ok(asset)
+    }
+
+    /// The location of the file of the given asset id
+    fn file(id: &str) -> PathBuf {
+        ASSET_ROOT.join(id)
+    }
+
+    /// The location of the stored file
+    pub fn path(&self) -> PathBuf {
+        Self::file(self.id.id.as_str())
+    }
+
+    /// The relative url the asset is served at
+    pub fn url(&self) -> String {
+        format!("/asset/{}", self.id.to_string())
+    }
+
+    /// Deletes the asset including its file
+    #[instrument(skip_all)]
+    pub async fn delete(self, connection: &DatabaseConnection) -> Result<()> {
+        sql_span!(connection
+            .query("DELETE $asset")
+            .bind(("asset", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        match tokio::fs::remove_file(self.path()).await {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Deletes all assets of the given owner. Has to be called when the owner gets deleted.
+    #[instrument(skip(connection))]
+    pub async fn delete_owned(owner: &Id, connection: &DatabaseConnection) -> Result<()> {
+        let assets = sql_span!(connection
+            .query("SELECT * FROM asset WHERE owner = $owner")
+            .bind(("owner", owner.to_thing()))
+            .await?
+            .take::<Vec<Asset>>(0)?);
+
+        for asset in assets {
+            asset.delete(connection).await?;
+        }
+        Ok(())
+    }
+}
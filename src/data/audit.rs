@@ -0,0 +1,296 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::{Session, SessionType};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// Fields whose values never end up in the audit log
+const REDACTED: [&str; 5] = ["password", "secret", "nonce", "token", "refreshToken"];
+
+/// A changed field of the audited record. The values are JSON encoded, absent if the field
+/// didn't exist before or after the change.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct AuditChange {
+    pub field: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// A privileged or otherwise mutating action, recorded after it succeeded
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct AuditEntry {
+    id: Id,
+    /// the account or machine client which performed the action
+    actor: Option<Id>,
+    /// the session the action was performed with
+    session: Option<Id>,
+    /// e.g. `event.update`, or the method and path of the request
+    action: String,
+    /// the record the action was performed on
+    target: Option<Id>,
+    #[serde(default)]
+    changes: Vec<AuditChange>,
+    /// the address of the client, forwarded addresses are only taken from trusted proxies
+    address: Option<String>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// Compares the top level fields of both records. Redacted fields are left out.
+pub fn diff(before: &Value, after: &Value) -> Vec<AuditChange> {
+    let empty = serde_json::Map::new();
+    let (before, after) = (
+        before.as_object().unwrap_or(&empty),
+        after.as_object().unwrap_or(&empty),
+    );
+
+    before
+        .keys()
+        .chain(after.keys())
+        .collect::<BTreeSet<&String>>()
+        .into_iter()
+        .filter(|field| !REDACTED.contains(&field.as_str()))
+        .filter(|field| before.get(*field) != after.get(*field))
+        .map(|field| AuditChange {
+            field: field.clone(),
+            before: before.get(field).map(Value::to_string),
+            after: after.get(field).map(Value::to_string),
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default)]
+struct AuditDetails {
+    actor: Option<Id>,
+    session: Option<Id>,
+    action: Option<String>,
+    target: Option<Id>,
+    changes: Vec<AuditChange>,
+}
+
+/// The audit record of the current request. The audit middleware inserts it into every request
+/// and writes it once a mutating request succeeded. The session middleware fills in the actor,
+/// handlers may describe the action in more detail.
+#[derive(Debug, Clone, Default)]
+pub struct Audit(Arc<Mutex<AuditDetails>>);
+
+impl Audit {
+    fn update(&self, update: impl FnOnce(&mut AuditDetails)) {
+        if let Ok(mut details) = self.0.lock() {
+            update(&mut details);
+        }
+    }
+
+    /// Attributes the action to the target of the session
+    pub fn session(&self, session: &Session) {
+        self.update(|details| {
+            details.session = Some(session.id.clone());
+            details.actor = Some(match session.target() {
+                SessionType::Human(id) | SessionType::Machine(id) => id.clone(),
+            });
+        });
+    }
+
+    /// Names the action, the method and path of the request are used otherwise
+    pub fn action(&self, action: &str) {
+        self.update(|details| details.action = Some(action.to_owned()));
+    }
+
+    /// Sets the record the action was performed on
+    pub fn target(&self, target: &Id) {
+        self.update(|details| details.target = Some(target.clone()));
+    }
+
+    /// Records the changed fields between both states of the target, a missing state stands for
+    /// the creation or deletion of the target
+    pub fn changes<T: Serialize>(&self, before: Option<&T>, after: Option<&T>) {
+        let value = |state: Option<&T>| {
+            state
+                .and_then(|state| serde_json::to_value(state).ok())
+                .unwrap_or(Value::Null)
+        };
+        let changes = diff(&value(before), &value(after));
+        self.update(|details| details.changes = changes);
+    }
+
+    /// Describes the action on the given record in one go
+    pub fn record<T: Serialize>(
+        &self,
+        action: &str,
+        target: &Id,
+        before: Option<&T>,
+        after: Option<&T>,
+    ) {
+        self.action(action);
+        self.target(target);
+        self.changes(before, after);
+    }
+
+    /// Writes the audit entry
+    #[instrument(skip(self, connection))]
+    pub async fn write(
+        &self,
+        action: String,
+        address: Option<String>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let details = self
+            .0
+            .lock()
+            .map_err(|_| ApplicationError::InternalServerError)?
+            .clone();
+
+        sql_span!(connection
+            .query(
+                "CREATE audit SET actor = $actor, session = $session, action = $action, \
+                target = $target, changes = $changes, address = $address"
+            )
+            .bind(("actor", details.actor.as_ref().map(Id::to_thing)))
+            .bind(("session", details.session.as_ref().map(Id::to_thing)))
+            .bind(("action", details.action.unwrap_or(action)))
+            .bind(("target", details.target.as_ref().map(Id::to_thing)))
+            .bind(("changes", details.changes))
+            .bind(("address", address))
+            .await?
+            .check()?);
+        Ok(())
+    }
+}
+
+/// The filters of the audit log. All given filters have to match.
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditSearch {
+    /// the id of the acting account or machine client, e.g. `account:abc`
+    pub actor: Option<Id>,
+    /// the id of the target record, e.g. `event:abc`
+    pub target: Option<Id>,
+    /// only entries recorded at or after the given time
+    pub from: Option<DateTime<Utc>>,
+    /// only entries recorded before the given time
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditSearch {
+    /// Builds the query selecting the matching entries, the latest first. User input is only
+    /// passed as bindings.
+    pub fn query(&self) -> (String, Vec<(&'static str, Value)>) {
+        let mut conditions = vec![];
+        let mut bindings = vec![];
+
+        // record links can't be bound as json, so they are built from their parts
+        if let Some(actor) = &self.actor {
+            conditions.push("actor = type::thing($actor_table, $actor)");
+            bindings.push(("actor_table", Value::from(actor.table.as_str())));
+            bindings.push(("actor", Value::from(actor.id.as_str())));
+        }
+        if let Some(target) = &self.target {
+            conditions.push("target = type::thing($target_table, $target)");
+            bindings.push(("target_table", Value::from(target.table.as_str())));
+            bindings.push(("target", Value::from(target.id.as_str())));
+        }
+        if let Some(from) = self.from {
+            conditions.push("created_at >= <datetime> $from");
+            bindings.push(("from", Value::from(from.to_rfc3339())));
+        }
+        if let Some(to) = self.to {
+            conditions.push("created_at < <datetime> $to");
+            bindings.push(("to", Value::from(to.to_rfc3339())));
+        }
+
+        let filter = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {} ", conditions.join(" AND ")),
+        };
+        (
+            format!("SELECT * FROM audit {filter}ORDER BY created_at DESC"),
+            bindings,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let before = json!({ "name": "old", "start": 1, "password": "a", "removed": true });
+        let after = json!({ "name": "new", "start": 1, "password": "b", "added": [1] });
+
+        assert_eq!(
+            vec![
+                AuditChange {
+                    field: "added".to_owned(),
+                    before: None,
+                    after: Some("[1]".to_owned()),
+                },
+                AuditChange {
+                    field: "name".to_owned(),
+                    before: Some("\"old\"".to_owned()),
+                    after: Some("\"new\"".to_owned()),
+                },
+                AuditChange {
+                    field: "removed".to_owned(),
+                    before: Some("true".to_owned()),
+                    after: None,
+                },
+            ],
+            diff(&before, &after)
+        );
+        assert_eq!(3, diff(&Value::Null, &after).len());
+    }
+
+    #[test]
+    fn test_query() {
+        let (query, bindings) = AuditSearch::default().query();
+        assert_eq!("SELECT * FROM audit ORDER BY created_at DESC", query);
+        assert!(bindings.is_empty());
+
+        let search = AuditSearch {
+            actor: Some(Id::new(("account", "abc"))),
+            target: Some(Id::new(("event", "def"))),
+            from: Some(Utc::now()),
+            to: None,
+        };
+        let (query, bindings) = search.query();
+        assert_eq!(
+            "SELECT * FROM audit WHERE actor = type::thing($actor_table, $actor) \
+            AND target = type::thing($target_table, $target) AND created_at >= <datetime> $from \
+            ORDER BY created_at DESC",
+            query
+        );
+        assert!(bindings.contains(&("target", Value::from("def"))));
+        assert_eq!(5, bindings.len());
+    }
+}
@@ -24,40 +24,53 @@
  *
  */
 
+use crate::data::clan::Clan;
 use crate::prelude::*;
 use chrono::{DateTime, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Getters, Setters)]
-#[get = "pub"]
+/// Records a membership change, expects the bindings $clan, $account, $actor and $action
+pub(crate) const HISTORY: &str =
+    "CREATE clan_history SET clan = $clan, account = $account, actor = $actor, action = $action";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClanAction {
+    /// the account founded the clan
+    Created,
+    Joined,
+    Left,
+    /// the account was removed by another member
+    Kicked,
+    Promoted,
+    Demoted,
+    /// the account became the owner of the clan
+    Transferred,
+}
+
+/// A change of the members of a clan
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
-pub struct News {
+#[get = "pub"]
+pub struct ClanHistory {
     id: Id,
-    title: String,
-    /// the content (supports html -> with automatic xss cleanup)
-    content: Option<String>,
-    shown: bool,
+    clan: Id,
+    /// the account whose membership changed
+    account: Id,
+    /// the account which made the change
+    actor: Id,
+    action: ClanAction,
     #[serde(alias = "created_at")]
     created_at: DateTime<Utc>,
 }
 
-impl News {
+impl Clan {
+    /// Lists the membership changes of the clan, the latest first
     #[instrument(skip_all)]
-    pub async fn new(
-        title: &str,
-        content: Option<&str>,
-        connection: &DatabaseConnection,
-    ) -> Result<Self> {
-        // cleanup the content
-        let content = content.and_then(|content| Some(ammonia::clean(content)));
-
-        // save into the database
-        let news: News = sql_span!(
-            connection
-                .create("news")
-                .content(&serde_json::json!({ "content": content, "title": title }))
-                .await?
-        );
-
-        Ok(news)
+    pub async fn history(&self, connection: &DatabaseConnection) -> Result<Vec<ClanHistory>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM clan_history WHERE clan = $clan ORDER BY created_at DESC")
+            .bind(("clan", self.id.to_thing()))
+            .await?
+            .take::<Vec<ClanHistory>>(0)?))
     }
 }
@@ -0,0 +1,432 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::data::account::Account;
+use crate::data::clan::history::{ClanAction, HISTORY};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::ops::RangeInclusive;
+
+pub mod history;
+pub mod request;
+
+const NAME_LENGTH: RangeInclusive<usize> = 3..=32;
+const TAG_LENGTH: RangeInclusive<usize> = 2..=6;
+
+/// The rank of a member within the clan, ordered from the lowest to the highest
+#[derive(
+    Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum ClanRole {
+    Member,
+    /// manages invitations, join requests and members
+    Officer,
+    /// manages the officers, there is exactly one owner
+    Owner,
+}
+
+/// A persistent group of players, which can join events as a team
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct Clan {
+    id: Id,
+    name: String,
+    /// a short uppercase version of the name, e.g. shown in front of player names
+    tag: String,
+    #[serde(default)]
+    description: String,
+    /// the account owning the clan
+    owner: Id,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// The membership of an account in a clan. Accounts are member of at most one clan.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct ClanMember {
+    id: Id,
+    clan: Id,
+    account: Id,
+    role: ClanRole,
+    #[serde(alias = "joined_at")]
+    joined_at: DateTime<Utc>,
+}
+
+/// Validates and normalises the name and the tag of a clan
+fn normalize(name: &str, tag: &str) -> Result<(String, String)> {
+    let name = name.trim();
+    if !NAME_LENGTH.contains(&name.chars().count()) {
+        return Err(ApplicationError::BadRequest(format!(
+            "the name has to be between {} and {} characters long",
+            NAME_LENGTH.start(),
+            NAME_LENGTH.end()
+        )));
+    }
+
+    let tag = tag.trim().to_uppercase();
+    if !TAG_LENGTH.contains(&tag.len()) || !tag.chars().all(|char| char.is_ascii_alphanumeric()) {
+        return Err(ApplicationError::BadRequest(format!(
+            "the tag has to consist of {} to {} letters or digits",
+            TAG_LENGTH.start(),
+            TAG_LENGTH.end()
+        )));
+    }
+
+    Ok((name.to_owned(), tag))
+}
+
+impl Clan {
+    /// Creates a new clan owned by the given account, which mustn't be member of a clan yet
+    #[instrument(skip(owner, connection))]
+    pub async fn create(
+        name: &str,
+        tag: &str,
+        description: &str,
+        owner: &Account,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let (name, tag) = normalize(name, tag)?;
+        if ClanMember::of_account(owner.id(), connection)
+            .await?
+            .is_some()
+        {
+            return Err(ApplicationError::Conflict(
+                "already member of a clan".to_owned(),
+            ));
+        }
+        let taken = sql_span!(connection
+            .query(
+                "SELECT * FROM clan WHERE string::lowercase(name) = string::lowercase($name) \
+                OR tag = $tag"
+            )
+            .bind(("name", name.as_str()))
+            .bind(("tag", tag.as_str()))
+            .await?
+            .take::<Vec<Clan>>(0)?);
+        if !taken.is_empty() {
+            return Err(ApplicationError::Conflict(
+                "the name or the tag is already taken".to_owned(),
+            ));
+        }
+
+        let id = Id::new(("clan", nanoid::nanoid!(20, &ALPHABET).as_str()));
+        let clan = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query(
+                "CREATE $clan SET name = $name, tag = $tag, description = $description, \
+                owner = $account"
+            )
+            .query("CREATE clan_member SET clan = $clan, account = $account, role = 'owner'")
+            .query(HISTORY)
+            .query("COMMIT TRANSACTION")
+            .bind(("clan", id.to_thing()))
+            .bind(("name", name))
+            .bind(("tag", tag))
+            .bind(("description", description.trim()))
+            .bind(("account", owner.id().to_thing()))
+            .bind(("actor", owner.id().to_thing()))
+            .bind(("action", ClanAction::Created))
+            .await?
+            .check()?
+            .take::<Option<Clan>>(0)?);
+
+        clan.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch a clan by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let clan: Option<Clan> = sql_span!(connection.select(&Id::try_from(("clan", id))?).await?);
+
+        clan.ok_or(ApplicationError::BadRequest("clan not found".to_owned()))
+    }
+
+    /// Select a page of all clans, ordered by their name
+    #[instrument(skip(connection))]
+    pub async fn all(
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<Self>> {
+        request
+            .execute::<Clan, &str>("SELECT * FROM clan ORDER BY name ASC", None, connection)
+            .await
+    }
+
+    /// Deletes the clan with its members, pending requests and history. Teams the clan formed
+    /// for events are kept.
+    #[instrument(skip_all)]
+    pub async fn delete(&self, connection: &DatabaseConnection) -> Result<()> {
+        sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("DELETE clan_member WHERE clan = $clan")
+            .query("DELETE clan_request WHERE clan = $clan")
+            .query("DELETE clan_history WHERE clan = $clan")
+            .query("DELETE $clan")
+            .query("COMMIT TRANSACTION")
+            .bind(("clan", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+
+    /// Fetch the membership of the account in the clan
+    #[instrument(skip_all)]
+    pub async fn member(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<Option<ClanMember>> {
+        Ok(ClanMember::of_account(account, connection)
+            .await?
+            .filter(|member| member.clan == self.id))
+    }
+
+    /// Lists the members of the clan, the earliest first
+    #[instrument(skip_all)]
+    pub async fn members(&self, connection: &DatabaseConnection) -> Result<Vec<ClanMember>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM clan_member WHERE clan = $clan ORDER BY joined_at ASC")
+            .bind(("clan", self.id.to_thing()))
+            .await?
+            .take::<Vec<ClanMember>>(0)?))
+    }
+
+    /// Fetch the membership of the account and make sure it has at least the given role
+    #[instrument(skip_all)]
+    pub async fn authorize(
+        &self,
+        account: &Id,
+        role: ClanRole,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanMember> {
+        match self.member(account, connection).await? {
+            Some(member) if member.role >= role => Ok(member),
+            _ => Err(ApplicationError::Forbidden(
+                "requires a higher role within the clan".to_owned(),
+            )),
+        }
+    }
+
+    /// Adds the account to the clan, its pending invitations and join requests are dropped
+    async fn join(
+        &self,
+        account: &Id,
+        actor: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanMember> {
+        if ClanMember::of_account(account, connection).await?.is_some() {
+            return Err(ApplicationError::Conflict(
+                "already member of a clan".to_owned(),
+            ));
+        }
+
+        let member = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("CREATE clan_member SET clan = $clan, account = $account, role = 'member'")
+            .query("DELETE clan_request WHERE account = $account")
+            .query(HISTORY)
+            .query("COMMIT TRANSACTION")
+            .bind(("clan", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .bind(("actor", actor.to_thing()))
+            .bind(("action", ClanAction::Joined))
+            .await?
+            .check()?
+            .take::<Option<ClanMember>>(0)?);
+
+        member.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Removes the member from the clan. The owner has to transfer the ownership first.
+    #[instrument(skip_all)]
+    pub async fn leave(&self, member: &ClanMember, connection: &DatabaseConnection) -> Result<()> {
+        if member.role == ClanRole::Owner {
+            return Err(ApplicationError::BadRequest(
+                "the owner has to transfer the ownership before leaving".to_owned(),
+            ));
+        }
+
+        self.remove(member, &member.account, ClanAction::Left, connection)
+            .await
+    }
+
+    /// Removes the member from the clan on behalf of a member with a higher role
+    #[instrument(skip_all)]
+    pub async fn kick(
+        &self,
+        actor: &ClanMember,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let member = self.target(account, connection).await?;
+        if actor.role < ClanRole::Officer || actor.role <= member.role {
+            return Err(ApplicationError::Forbidden(
+                "members can only be removed by a higher role".to_owned(),
+            ));
+        }
+
+        self.remove(&member, &actor.account, ClanAction::Kicked, connection)
+            .await
+    }
+
+    async fn remove(
+        &self,
+        member: &ClanMember,
+        actor: &Id,
+        action: ClanAction,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("DELETE $member")
+            .query(HISTORY)
+            .query("COMMIT TRANSACTION")
+            .bind(("member", member.id.to_thing()))
+            .bind(("clan", self.id.to_thing()))
+            .bind(("account", member.account.to_thing()))
+            .bind(("actor", actor.to_thing()))
+            .bind(("action", action))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+
+    /// Changes the role of a member, only the owner manages roles. Making another member the
+    /// owner transfers the ownership, the previous owner stays as an officer.
+    #[instrument(skip_all)]
+    pub async fn set_role(
+        &mut self,
+        actor: &ClanMember,
+        account: &Id,
+        role: ClanRole,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanMember> {
+        if actor.role != ClanRole::Owner {
+            return Err(ApplicationError::Forbidden(
+                "roles are managed by the owner".to_owned(),
+            ));
+        }
+        let member = self.target(account, connection).await?;
+        if member.id == actor.id {
+            return Err(ApplicationError::BadRequest(
+                "the owner can't change their own role".to_owned(),
+            ));
+        }
+        if member.role == role {
+            return Ok(member);
+        }
+
+        let action = match role {
+            ClanRole::Owner => ClanAction::Transferred,
+            role if role > member.role => ClanAction::Promoted,
+            _ => ClanAction::Demoted,
+        };
+        let mut query = connection.query("BEGIN TRANSACTION");
+        if role == ClanRole::Owner {
+            query = query
+                .query("UPDATE $owner SET role = 'officer'")
+                .query("UPDATE $clan SET owner = $account")
+                .bind(("owner", actor.id.to_thing()));
+        }
+        let mut response = sql_span!(query
+            .query("UPDATE $member SET role = $role")
+            .query(HISTORY)
+            .query("COMMIT TRANSACTION")
+            .bind(("member", member.id.to_thing()))
+            .bind(("role", role))
+            .bind(("clan", self.id.to_thing()))
+            .bind(("account", member.account.to_thing()))
+            .bind(("actor", actor.account.to_thing()))
+            .bind(("action", action))
+            .await?
+            .check()?);
+
+        let updated = match role {
+            ClanRole::Owner => {
+                self.owner = member.account.clone();
+                response.take::<Option<ClanMember>>(2)?
+            }
+            _ => response.take::<Option<ClanMember>>(0)?,
+        };
+        updated.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch the membership of the account, which has to be a member of the clan
+    async fn target(&self, account: &Id, connection: &DatabaseConnection) -> Result<ClanMember> {
+        self.member(account, connection)
+            .await?
+            .ok_or(ApplicationError::BadRequest(
+                "the account isn't a member of the clan".to_owned(),
+            ))
+    }
+}
+
+impl ClanMember {
+    /// Fetch the clan membership of the account
+    #[instrument(skip(connection))]
+    pub async fn of_account(account: &Id, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM clan_member WHERE account = $account")
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Option<ClanMember>>(0)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::clan::{normalize, ClanRole};
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(
+            ("Iron Legion".to_owned(), "IRON".to_owned()),
+            normalize(" Iron Legion ", " iron ").unwrap()
+        );
+        assert!(normalize("ab", "IRON").is_err());
+        assert!(normalize(&"a".repeat(33), "IRON").is_err());
+        assert!(normalize("Iron Legion", "I").is_err());
+        assert!(normalize("Iron Legion", "IRONLEG").is_err());
+        assert!(normalize("Iron Legion", "IR-N").is_err());
+    }
+
+    #[test]
+    fn test_role() {
+        assert!(ClanRole::Owner > ClanRole::Officer);
+        assert!(ClanRole::Officer > ClanRole::Member);
+        assert_eq!(
+            serde_json::json!("officer"),
+            serde_json::to_value(ClanRole::Officer).unwrap()
+        );
+    }
+}
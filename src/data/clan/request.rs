@@ -0,0 +1,220 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::account::Account;
+use crate::data::clan::{Clan, ClanMember, ClanRole};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClanRequestKind {
+    /// a member of the clan invited the account, the account accepts
+    Invitation,
+    /// the account asked to join the clan, an officer accepts
+    Join,
+}
+
+/// A pending invitation or join request. There is at most one pending request per account
+/// and clan.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct ClanRequest {
+    id: Id,
+    clan: Id,
+    /// the account which would join the clan
+    account: Id,
+    kind: ClanRequestKind,
+    /// the account which created the request
+    author: Id,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+impl Clan {
+    /// Invites the account to the clan on behalf of an officer
+    #[instrument(skip_all)]
+    pub async fn invite(
+        &self,
+        actor: &ClanMember,
+        account: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanRequest> {
+        if *actor.role() < ClanRole::Officer {
+            return Err(ApplicationError::Forbidden(
+                "invitations are sent by officers".to_owned(),
+            ));
+        }
+        let account = Account::from_id(account, connection)
+            .await?
+            .ok_or(ApplicationError::BadRequest("account not found".to_owned()))?;
+
+        self.open_request(
+            account.id(),
+            ClanRequestKind::Invitation,
+            actor.account(),
+            connection,
+        )
+        .await
+    }
+
+    /// Asks to join the clan on behalf of the account
+    #[instrument(skip_all)]
+    pub async fn request_join(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanRequest> {
+        self.open_request(account, ClanRequestKind::Join, account, connection)
+            .await
+    }
+
+    async fn open_request(
+        &self,
+        account: &Id,
+        kind: ClanRequestKind,
+        author: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<ClanRequest> {
+        if ClanMember::of_account(account, connection).await?.is_some() {
+            return Err(ApplicationError::Conflict(
+                "the account is already member of a clan".to_owned(),
+            ));
+        }
+        let pending = sql_span!(connection
+            .query("SELECT * FROM clan_request WHERE clan = $clan AND account = $account")
+            .bind(("clan", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Option<ClanRequest>>(0)?);
+        if pending.is_some() {
+            return Err(ApplicationError::Conflict(
+                "there is already a pending request for the account".to_owned(),
+            ));
+        }
+
+        let request = sql_span!(connection
+            .query(
+                "CREATE clan_request SET clan = $clan, account = $account, kind = $kind, \
+                author = $author"
+            )
+            .bind(("clan", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .bind(("kind", kind))
+            .bind(("author", author.to_thing()))
+            .await?
+            .take::<Option<ClanRequest>>(0)?);
+        request.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Lists the pending invitations and join requests of the clan, the earliest first
+    #[instrument(skip_all)]
+    pub async fn requests(&self, connection: &DatabaseConnection) -> Result<Vec<ClanRequest>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM clan_request WHERE clan = $clan ORDER BY created_at ASC")
+            .bind(("clan", self.id.to_thing()))
+            .await?
+            .take::<Vec<ClanRequest>>(0)?))
+    }
+}
+
+async fn is_officer(clan: &Clan, account: &Id, connection: &DatabaseConnection) -> Result<bool> {
+    Ok(clan
+        .member(account, connection)
+        .await?
+        .is_some_and(|member| *member.role() >= ClanRole::Officer))
+}
+
+impl ClanRequest {
+    /// Fetch a pending request by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let request: Option<ClanRequest> = sql_span!(
+            connection
+                .select(&Id::try_from(("clan_request", id))?)
+                .await?
+        );
+
+        request.ok_or(ApplicationError::BadRequest("request not found".to_owned()))
+    }
+
+    /// Lists the pending invitations and join requests of the account, the earliest first
+    #[instrument(skip(connection))]
+    pub async fn of_account(account: &Id, connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM clan_request WHERE account = $account ORDER BY created_at ASC")
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Vec<ClanRequest>>(0)?))
+    }
+
+    /// Whether the account may accept the request: invitations are accepted by the invited
+    /// account, join requests by an officer of the clan
+    async fn may_accept(
+        &self,
+        clan: &Clan,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<bool> {
+        Ok(match self.kind {
+            ClanRequestKind::Invitation => self.account == *account,
+            ClanRequestKind::Join => is_officer(clan, account, connection).await?,
+        })
+    }
+
+    /// Accepts the request, which adds the account to the clan
+    #[instrument(skip_all)]
+    pub async fn accept(&self, actor: &Id, connection: &DatabaseConnection) -> Result<ClanMember> {
+        let clan = Clan::from_id(self.clan.to_string().as_str(), connection).await?;
+        if !self.may_accept(&clan, actor, connection).await? {
+            return Err(ApplicationError::Forbidden(
+                "the request can't be accepted by the account".to_owned(),
+            ));
+        }
+
+        clan.join(&self.account, actor, connection).await
+    }
+
+    /// Declines or withdraws the request, which is up to the account and the officers of the
+    /// clan regardless of the kind
+    #[instrument(skip_all)]
+    pub async fn decline(&self, actor: &Id, connection: &DatabaseConnection) -> Result<()> {
+        let clan = Clan::from_id(self.clan.to_string().as_str(), connection).await?;
+        if self.account != *actor && !is_officer(&clan, actor, connection).await? {
+            return Err(ApplicationError::Forbidden(
+                "the request can't be declined by the account".to_owned(),
+            ));
+        }
+
+        sql_span!(connection
+            .query("DELETE $request")
+            .bind(("request", self.id.to_thing()))
+            .await?
+            .check()?);
+        Ok(())
+    }
+}
@@ -0,0 +1,246 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::asset::image::ImageVariants;
+use crate::data::asset::Asset;
+use crate::data::event::Event;
+use crate::data::schematic::format::Structure;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// The maximum amount of attachments per event
+const ATTACHMENT_LIMIT: usize = 10;
+
+/// A file attached to an event, e.g. the rules or the schematic of the arena
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EventAttachment {
+    /// the file name, unique per event
+    pub name: String,
+    /// the mime type the file is served with
+    pub mime: String,
+    /// the size in bytes
+    pub size: u64,
+    /// the url of the stored asset
+    pub url: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Fails if the file name is empty, too long or contains path separators
+fn validate_name(name: &str) -> Result<()> {
+    if !(1..=64).contains(&name.chars().count())
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_control)
+    {
+        return Err(ApplicationError::BadRequest(
+            "the file name has to be between 1 and 64 characters without path separators"
+                .to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Detects the mime type of an attachment by its content. Only pdf documents and schematics are
+/// accepted, schematics have to be readable.
+fn detect(data: &[u8]) -> Result<&'static str> {
+    if data.starts_with(b"%PDF-") {
+        return Ok("application/pdf");
+    }
+
+    match Structure::read(data) {
+        Ok((_, format)) => Ok(format.content_type()),
+        Err(_) => Err(ApplicationError::BadRequest(
+            "attachments have to be pdf documents or schematics".to_owned(),
+        )),
+    }
+}
+
+impl Event {
+    /// Processes the uploaded cover image and stores its variants, replacing the previous ones
+    #[instrument(skip(self, data, connection))]
+    pub async fn upload_image(
+        &mut self,
+        data: Vec<u8>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let images = ImageVariants::store(&self.id, "image", data, connection).await?;
+        self.set_images(Some(images), connection).await
+    }
+
+    /// Removes the cover image including its variants
+    #[instrument(skip_all)]
+    pub async fn remove_image(&mut self, connection: &DatabaseConnection) -> Result<()> {
+        if self.images.is_none() {
+            return Err(ApplicationError::BadRequest(
+                "the event has no image".to_owned(),
+            ));
+        }
+
+        let assets = sql_span!(connection
+            .query(
+                "SELECT * FROM asset WHERE owner = $event AND string::startsWith(name, 'image.')"
+            )
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<Asset>>(0)?);
+        for asset in assets {
+            asset.delete(connection).await?;
+        }
+        self.set_images(None, connection).await
+    }
+
+    async fn set_images(
+        &mut self,
+        images: Option<ImageVariants>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let query = match images {
+            Some(_) => "UPDATE $event SET images = $images",
+            None => "UPDATE $event SET images = NONE",
+        };
+        let event = sql_span!(connection
+            .query(query)
+            .bind(("event", self.id.to_thing()))
+            .bind(("images", images))
+            .await?
+            .take::<Option<Event>>(0)?);
+        *self = event.ok_or(ApplicationError::BadRequest("event not found".to_owned()))?;
+        Ok(())
+    }
+
+    /// Stores the uploaded file as attachment, an attachment with the same name is replaced
+    #[instrument(skip(self, data, connection))]
+    pub async fn attach(
+        &mut self,
+        name: &str,
+        data: Vec<u8>,
+        connection: &DatabaseConnection,
+    ) -> Result<EventAttachment> {
+        validate_name(name)?;
+        let mut attachments = self.attachments.clone();
+        attachments.retain(|attachment| attachment.name != name);
+        if attachments.len() >= ATTACHMENT_LIMIT {
+            return Err(ApplicationError::BadRequest(format!(
+                "events can't have more than {ATTACHMENT_LIMIT} attachments"
+            )));
+        }
+        // reading schematics is cpu bound
+        let (data, mime) = tokio::task::spawn_blocking(move || {
+            let mime = detect(data.as_slice());
+            mime.map(|mime| (data, mime))
+        })
+        .await
+        .map_err(|_| ApplicationError::InternalServerError)??;
+
+        let asset = Asset::store(
+            &self.id,
+            format!("attachment.{name}").as_str(),
+            mime,
+            data.as_slice(),
+            connection,
+        )
+        .await?;
+        let attachment = EventAttachment {
+            name: name.to_owned(),
+            mime: mime.to_owned(),
+            size: *asset.size(),
+            url: asset.url(),
+            created_at: *asset.created_at(),
+        };
+        attachments.push(attachment.clone());
+
+        self.set_attachments(attachments, connection).await?;
+        Ok(attachment)
+    }
+
+    /// Removes the attachment with the given name including its file
+    #[instrument(skip(self, connection))]
+    pub async fn detach(&mut self, name: &str, connection: &DatabaseConnection) -> Result<()> {
+        let mut attachments = self.attachments.clone();
+        attachments.retain(|attachment| attachment.name != name);
+        if attachments.len() == self.attachments.len() {
+            return Err(ApplicationError::BadRequest(
+                "attachment not found".to_owned(),
+            ));
+        }
+
+        if let Some(asset) =
+            Asset::from_owner(&self.id, format!("attachment.{name}").as_str(), connection).await?
+        {
+            asset.delete(connection).await?;
+        }
+        self.set_attachments(attachments, connection).await
+    }
+
+    async fn set_attachments(
+        &mut self,
+        attachments: Vec<EventAttachment>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let event = sql_span!(connection
+            .query("UPDATE $event SET attachments = $attachments")
+            .bind(("event", self.id.to_thing()))
+            .bind(("attachments", attachments))
+            .await?
+            .take::<Option<Event>>(0)?);
+        *self = event.ok_or(ApplicationError::BadRequest("event not found".to_owned()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::schematic::format::SchematicFormat;
+
+    #[test]
+    fn test_validate_name() {
+        assert!(validate_name("rules.pdf").is_ok());
+        assert!(validate_name("Arena 1.schem").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("../rules.pdf").is_err());
+        assert!(validate_name("a\\b").is_err());
+        assert!(validate_name(&"a".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(
+            "application/pdf",
+            detect(b"%PDF-1.7\n%rest of the document").unwrap()
+        );
+        assert!(detect(b"MZ not a document").is_err());
+
+        let schematic = Structure::empty(1, 1, 1, 3465)
+            .unwrap()
+            .write(SchematicFormat::Schem, "arena")
+            .unwrap();
+        assert_eq!("application/x-schem", detect(schematic.as_slice()).unwrap());
+    }
+}
@@ -0,0 +1,222 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::data::event::{Event, EventStatus};
+use crate::data::feed::api_link;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// The secret token of the personal calendar feed of an account. Calendar apps can't
+/// authenticate, so the token in the url grants access to the feed until it is renewed.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize)]
+#[get = "pub"]
+pub struct CalendarToken {
+    account: Id,
+    token: String,
+}
+
+impl CalendarToken {
+    /// Fetch the token of the account, a new one is created if the account has none yet
+    #[instrument(skip_all)]
+    pub async fn of(account: &Id, connection: &DatabaseConnection) -> Result<Self> {
+        let token = sql_span!(connection
+            .query("SELECT * FROM calendar_token WHERE account = $account")
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Option<CalendarToken>>(0)?);
+
+        match token {
+            Some(token) => Ok(token),
+            None => Self::renew(account, connection).await,
+        }
+    }
+
+    /// Replaces the token of the account, which invalidates the previous feed url
+    #[instrument(skip_all)]
+    pub async fn renew(account: &Id, connection: &DatabaseConnection) -> Result<Self> {
+        let token = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("DELETE calendar_token WHERE account = $account")
+            .query("CREATE calendar_token SET account = $account, token = $token")
+            .query("COMMIT TRANSACTION")
+            .bind(("account", account.to_thing()))
+            .bind(("token", nanoid::nanoid!(48, &ALPHABET)))
+            .await?
+            .check()?
+            .take::<Option<CalendarToken>>(1)?);
+
+        token.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch the token by its value
+    #[instrument(skip_all)]
+    pub async fn from_token(token: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM calendar_token WHERE token = $token")
+            .bind(("token", token))
+            .await?
+            .take::<Option<CalendarToken>>(0)?))
+    }
+
+    /// The absolute url of the personal calendar feed
+    pub fn url(&self) -> String {
+        api_link(format!("/event/calendar/personal/{}", self.token).as_str())
+    }
+}
+
+/// Renders the events as iCalendar (RFC 5545)
+pub fn calendar(name: &str, events: &[Event]) -> String {
+    write(name, events, ROOT.as_str())
+}
+
+fn write(name: &str, events: &[Event], root: &str) -> String {
+    // the uids have to be globally unique, so they are scoped to the domain of the site
+    let domain = root
+        .split("://")
+        .last()
+        .and_then(|host| host.split('/').next())
+        .unwrap_or(root);
+
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_owned(),
+        "VERSION:2.0".to_owned(),
+        format!("PRODID:-//{}//Events//EN", escape(FEED_TITLE.as_str())),
+        "CALSCALE:GREGORIAN".to_owned(),
+        "METHOD:PUBLISH".to_owned(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for event in events {
+        lines.extend([
+            "BEGIN:VEVENT".to_owned(),
+            format!("UID:{}@{domain}", event.id.id),
+            format!("DTSTAMP:{}", timestamp(&event.created_at)),
+            format!("DTSTART:{}", timestamp(&event.start)),
+            format!("DTEND:{}", timestamp(&event.end)),
+            format!("SUMMARY:{}", escape(event.name.as_str())),
+            format!("DESCRIPTION:{}", escape(event.description.as_str())),
+            format!("URL:{root}/event/{}", event.id.id),
+            format!(
+                "STATUS:{}",
+                match event.status {
+                    EventStatus::Draft => "TENTATIVE",
+                    EventStatus::Cancelled => "CANCELLED",
+                    _ => "CONFIRMED",
+                }
+            ),
+            "END:VEVENT".to_owned(),
+        ]);
+    }
+    lines.push("END:VCALENDAR".to_owned());
+
+    lines
+        .iter()
+        .map(|line| fold(line.as_str()))
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+/// Formats the time as UTC date-time
+fn timestamp(time: &DateTime<Utc>) -> String {
+    time.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes the special characters of a text value
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace(['\r', '\n'], "\\n")
+}
+
+/// Terminates the content line and folds it into lines of at most 75 octets
+fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut length = 0;
+    for char in line.chars() {
+        if length + char.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            // the leading space counts towards the line length
+            length = 1;
+        }
+        folded.push(char);
+        length += char.len_utf8();
+    }
+    folded.push_str("\r\n");
+
+    folded
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::calendar::{escape, fold, write};
+    use crate::data::event::{Event, EventStatus};
+    use crate::prelude::Id;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!("a\\, b\\; c\\\\d\\ne", escape("a, b; c\\d\r\ne"));
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!("short\r\n", fold("short"));
+
+        let line = format!("DESCRIPTION:{}", "ä".repeat(60));
+        let folded = fold(line.as_str());
+        assert!(folded.split("\r\n").all(|line| line.len() <= 75));
+        assert_eq!(line, folded.replace("\r\n ", "").trim_end());
+    }
+
+    #[test]
+    fn test_write() {
+        let event = Event {
+            id: Id::new(("event", "abc")),
+            name: "Build, Battle".to_owned(),
+            description: "rules apply".to_owned(),
+            start: Utc.with_ymd_and_hms(2023, 4, 1, 18, 0, 0).unwrap(),
+            end: Utc.with_ymd_and_hms(2023, 4, 1, 20, 30, 0).unwrap(),
+            status: EventStatus::Cancelled,
+            capacity: None,
+            check_in: None,
+            series: None,
+            detached: false,
+            images: None,
+            attachments: vec![],
+            created_at: Utc.with_ymd_and_hms(2023, 3, 1, 12, 0, 0).unwrap(),
+        };
+        let calendar = write("Events", &[event], "https://example.com");
+
+        assert!(calendar.starts_with("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n"));
+        assert!(calendar.ends_with("END:VEVENT\r\nEND:VCALENDAR\r\n"));
+        assert!(calendar.contains("\r\nUID:abc@example.com\r\n"));
+        assert!(calendar.contains("\r\nDTSTART:20230401T180000Z\r\nDTEND:20230401T203000Z\r\n"));
+        assert!(calendar.contains("\r\nSUMMARY:Build\\, Battle\r\n"));
+        assert!(calendar.contains("\r\nURL:https://example.com/event/abc\r\nSTATUS:CANCELLED\r\n"));
+    }
+}
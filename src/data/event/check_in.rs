@@ -0,0 +1,475 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::live::LiveMessage;
+use crate::data::event::registration::{EventRegistration, RegistrationStatus};
+use crate::data::event::team::EventTeam;
+use crate::data::event::Event;
+use crate::data::notification::{notify, NotificationKind};
+use crate::prelude::*;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashSet;
+
+/// How often the check-ins of started events are closed
+const CHECK_IN_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// A registration with the account it belongs to
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct CheckInEntry {
+    #[serde(flatten)]
+    pub registration: EventRegistration,
+    pub username: Option<String>,
+    /// the uuid of the linked minecraft account
+    pub uuid: Option<String>,
+}
+
+/// The check-in progress of a team
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TeamCheckIn {
+    pub team: Id,
+    pub name: String,
+    pub members: usize,
+    pub checked_in: usize,
+}
+
+/// The overview of the check-in for the organisers
+#[derive(Debug, Clone, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckInDashboard {
+    /// the time the check-in opens, absent if the event doesn't require a check-in
+    pub opens_at: Option<DateTime<Utc>>,
+    /// the time the check-in closes, which is the start of the event
+    pub closes_at: Option<DateTime<Utc>>,
+    pub capacity: Option<u32>,
+    pub registered: usize,
+    /// the registered and waitlisted players which checked in
+    pub checked_in: usize,
+    pub waitlisted: usize,
+    pub dropped: usize,
+    pub players: Vec<CheckInEntry>,
+    pub teams: Vec<TeamCheckIn>,
+}
+
+/// Whether the registration checked in and still takes part
+fn is_checked_in(registration: &EventRegistration) -> bool {
+    registration.checked_in_at().is_some() && *registration.status() != RegistrationStatus::Dropped
+}
+
+/// Selects the waitlisted registrations moving into the free places, the earliest first. Once
+/// the check-in closed only checked in registrations move up.
+fn promotions(registrations: &[EventRegistration], capacity: Option<u32>, closed: bool) -> Vec<Id> {
+    let registered = registrations
+        .iter()
+        .filter(|registration| *registration.status() == RegistrationStatus::Registered)
+        .count();
+    let free = capacity.map_or(usize::MAX, |capacity| {
+        (capacity as usize).saturating_sub(registered)
+    });
+
+    let mut waitlisted = registrations
+        .iter()
+        .filter(|registration| *registration.status() == RegistrationStatus::Waitlisted)
+        .filter(|registration| !closed || registration.checked_in_at().is_some())
+        .collect::<Vec<&EventRegistration>>();
+    waitlisted.sort_by_key(|registration| *registration.created_at());
+
+    waitlisted
+        .into_iter()
+        .take(free)
+        .map(|registration| registration.id().clone())
+        .collect()
+}
+
+/// Summarises the registrations of the event
+fn dashboard(event: &Event, players: Vec<CheckInEntry>, teams: &[EventTeam]) -> CheckInDashboard {
+    let count = |status: RegistrationStatus| {
+        players
+            .iter()
+            .filter(|player| *player.registration.status() == status)
+            .count()
+    };
+    let checked_in = players
+        .iter()
+        .filter(|player| is_checked_in(&player.registration))
+        .filter_map(|player| player.uuid.as_ref().map(|uuid| uuid.to_lowercase()))
+        .collect::<HashSet<String>>();
+    let window = event.check_in_window();
+
+    CheckInDashboard {
+        opens_at: window.map(|(opens, _)| opens),
+        closes_at: window.map(|(_, closes)| closes),
+        capacity: event.capacity,
+        registered: count(RegistrationStatus::Registered),
+        checked_in: players
+            .iter()
+            .filter(|player| is_checked_in(&player.registration))
+            .count(),
+        waitlisted: count(RegistrationStatus::Waitlisted),
+        dropped: count(RegistrationStatus::Dropped),
+        teams: teams
+            .iter()
+            .map(|team| TeamCheckIn {
+                team: team.id().clone(),
+                name: team.name().clone(),
+                members: team.members().len(),
+                checked_in: team
+                    .members()
+                    .iter()
+                    .filter(|member| checked_in.contains(*member))
+                    .count(),
+            })
+            .collect(),
+        players,
+    }
+}
+
+impl Event {
+    /// The time the check-in opens and closes, absent if the event doesn't require a check-in
+    pub fn check_in_window(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        self.check_in
+            .map(|minutes| (self.start - Duration::minutes(minutes as i64), self.start))
+    }
+
+    /// Whether the check-in has closed, which drops the players who didn't check in
+    pub(crate) fn check_in_closed(&self) -> bool {
+        self.check_in.is_some() && Utc::now() >= self.start
+    }
+
+    /// Checks in the registered account
+    #[instrument(skip_all)]
+    pub async fn check_in_account(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<EventRegistration> {
+        let registration =
+            self.registration(account, connection)
+                .await?
+                .ok_or(ApplicationError::BadRequest(
+                    "not registered for the event".to_owned(),
+                ))?;
+
+        self.confirm(registration, connection).await
+    }
+
+    /// Checks in the registered player by the uuid of their minecraft account, e.g. when they
+    /// join the game server
+    #[instrument(skip(self, connection))]
+    pub async fn check_in_player(
+        &self,
+        uuid: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<EventRegistration> {
+        let registration = sql_span!(connection
+            .query(
+                "SELECT * FROM event_registration \
+                WHERE event = $event AND string::lowercase(account.uuid) = $uuid"
+            )
+            .bind(("event", self.id.to_thing()))
+            .bind(("uuid", uuid.trim().to_lowercase()))
+            .await?
+            .take::<Option<EventRegistration>>(0)?)
+        .ok_or(ApplicationError::BadRequest(
+            "the player isn't registered for the event".to_owned(),
+        ))?;
+
+        self.confirm(registration, connection).await
+    }
+
+    /// Marks the registration as checked in, repeated check-ins are ignored
+    async fn confirm(
+        &self,
+        registration: EventRegistration,
+        connection: &DatabaseConnection,
+    ) -> Result<EventRegistration> {
+        let Some((opens, closes)) = self.check_in_window() else {
+            return Err(ApplicationError::BadRequest(
+                "the event doesn't require a check-in".to_owned(),
+            ));
+        };
+        let now = Utc::now();
+        if now < opens {
+            return Err(ApplicationError::BadRequest(
+                "the check-in hasn't opened yet".to_owned(),
+            ));
+        }
+        if now >= closes {
+            return Err(ApplicationError::BadRequest(
+                "the check-in has already closed".to_owned(),
+            ));
+        }
+        if registration.checked_in_at().is_some() {
+            return Ok(registration);
+        }
+
+        let registration = sql_span!(connection
+            .query("UPDATE $registration SET checked_in_at = time::now()")
+            .bind(("registration", registration.id().to_thing()))
+            .await?
+            .take::<Option<EventRegistration>>(0)?);
+        registration.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Drops the registrations which didn't check in once the check-in closed and gives their
+    /// places to the waitlist. Returns the changed registrations.
+    #[instrument(skip_all)]
+    pub async fn close_check_in(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<EventRegistration>> {
+        if !self.check_in_closed() {
+            return Ok(vec![]);
+        }
+
+        let mut changed = sql_span!(connection
+            .query(
+                "UPDATE event_registration SET status = 'dropped' \
+                WHERE event = $event AND status = 'registered' AND checked_in_at IS NONE"
+            )
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<EventRegistration>>(0)?);
+        changed.extend(self.fill(connection).await?);
+        Ok(changed)
+    }
+
+    /// Moves waitlisted registrations into the free places, returns the promoted ones
+    #[instrument(skip_all)]
+    pub async fn fill(&self, connection: &DatabaseConnection) -> Result<Vec<EventRegistration>> {
+        let registrations = self.registrations(connection).await?;
+        let promoted = promotions(&registrations, self.capacity, self.check_in_closed());
+        if promoted.is_empty() {
+            return Ok(vec![]);
+        }
+
+        Ok(sql_span!(connection
+            .query("UPDATE event_registration SET status = 'registered' WHERE id INSIDE $promoted")
+            .bind((
+                "promoted",
+                promoted.iter().map(Id::to_thing).collect::<Vec<_>>()
+            ))
+            .await?
+            .take::<Vec<EventRegistration>>(0)?))
+    }
+
+    /// Collects the check-in progress of the players and teams
+    #[instrument(skip_all)]
+    pub async fn check_in_dashboard(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<CheckInDashboard> {
+        let players = sql_span!(connection
+            .query(
+                "SELECT *, account.username AS username, account.uuid AS uuid \
+                FROM event_registration WHERE event = $event ORDER BY created_at ASC"
+            )
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<CheckInEntry>>(0)?);
+        let teams = self.teams(connection).await?;
+
+        Ok(dashboard(self, players, teams.as_slice()))
+    }
+
+    /// Notifies the players whose registrations moved up from the waitlist
+    pub async fn notify_promotions(
+        &self,
+        registrations: &[EventRegistration],
+        state: &ApplicationState,
+    ) {
+        let message = format!(
+            "A place became available, you are now registered for {}",
+            self.name
+        );
+        for registration in registrations
+            .iter()
+            .filter(|registration| *registration.status() == RegistrationStatus::Registered)
+        {
+            notify(
+                state,
+                registration.account(),
+                NotificationKind::Promotion,
+                message.as_str(),
+                Some(&self.id),
+            )
+            .await;
+        }
+    }
+
+    /// Select the running or published events whose check-in has closed
+    async fn started_check_ins(connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query(
+                "SELECT * FROM event WHERE check_in IS NOT NONE AND start <= time::now() \
+                AND end > time::now() AND status INSIDE ['published', 'running']"
+            )
+            .await?
+            .take::<Vec<Event>>(0)?))
+    }
+}
+
+/// Periodically closes the check-ins of started events and pushes the changed registrations to
+/// the viewers of the events
+pub async fn close_check_ins(state: ApplicationState) {
+    let mut interval = tokio::time::interval(CHECK_IN_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let events = match Event::started_check_ins(state.connection()).await {
+            Ok(events) => events,
+            Err(error) => {
+                warn!("failed to select the started check-ins: {error}");
+                continue;
+            }
+        };
+        for event in events {
+            match event.close_check_in(state.connection()).await {
+                Ok(changed) => {
+                    event.notify_promotions(&changed, &state).await;
+                    changed.into_iter().for_each(|registration| {
+                        state
+                            .live()
+                            .publish(event.id(), LiveMessage::Registration(registration))
+                    })
+                }
+                Err(error) => warn!("failed to close the check-in of {}: {error}", event.id.id),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn registration(
+        id: &str,
+        status: RegistrationStatus,
+        checked_in: bool,
+        minutes: i64,
+    ) -> EventRegistration {
+        serde_json::from_value(json!({
+            "id": format!("event_registration:{id}"),
+            "event": "event:event",
+            "account": format!("account:{id}"),
+            "status": status,
+            "checkedInAt": checked_in.then(Utc::now),
+            "createdAt": Utc::now() + Duration::minutes(minutes),
+        }))
+        .unwrap()
+    }
+
+    fn ids(promoted: Vec<Id>) -> Vec<String> {
+        promoted.into_iter().map(|id| id.id).collect()
+    }
+
+    #[test]
+    fn test_promotions() {
+        let registrations = vec![
+            registration("a", RegistrationStatus::Registered, true, 0),
+            registration("b", RegistrationStatus::Dropped, false, 1),
+            registration("d", RegistrationStatus::Waitlisted, false, 3),
+            registration("c", RegistrationStatus::Waitlisted, true, 2),
+            registration("e", RegistrationStatus::Waitlisted, true, 4),
+        ];
+
+        assert_eq!(vec!["c"], ids(promotions(&registrations, Some(2), false)));
+        assert_eq!(
+            vec!["c", "d", "e"],
+            ids(promotions(&registrations, None, false))
+        );
+        // only checked in players move up once the check-in closed
+        assert_eq!(
+            vec!["c", "e"],
+            ids(promotions(&registrations, Some(3), true))
+        );
+        assert!(promotions(&registrations, Some(1), false).is_empty());
+    }
+
+    #[test]
+    fn test_dashboard() {
+        let event: Event = serde_json::from_value(json!({
+            "id": "event:event",
+            "name": "name",
+            "description": "description",
+            "start": "2030-01-01T12:00:00Z",
+            "end": "2030-01-01T14:00:00Z",
+            "capacity": 2,
+            "check_in": 30,
+            "created_at": "2029-01-01T00:00:00Z",
+        }))
+        .unwrap();
+        let entry = |registration, uuid: &str| CheckInEntry {
+            registration,
+            username: None,
+            uuid: Some(uuid.to_owned()),
+        };
+        let players = vec![
+            entry(
+                registration("a", RegistrationStatus::Registered, true, 0),
+                "A",
+            ),
+            entry(
+                registration("b", RegistrationStatus::Registered, false, 1),
+                "b",
+            ),
+            entry(
+                registration("c", RegistrationStatus::Waitlisted, true, 2),
+                "c",
+            ),
+        ];
+        let team: EventTeam = serde_json::from_value(json!({
+            "id": "event_team:team",
+            "name": "Iron Legion",
+            "token": "IRON",
+            "event": "event:event",
+            "members": ["a", "b"],
+            "created_at": "2029-01-01T00:00:00Z",
+        }))
+        .unwrap();
+
+        let dashboard = dashboard(&event, players, &[team]);
+        assert_eq!(
+            Some("2030-01-01T11:30:00Z".parse::<DateTime<Utc>>().unwrap()),
+            dashboard.opens_at
+        );
+        assert_eq!(Some(*event.start()), dashboard.closes_at);
+        assert_eq!(
+            (2, 2, 1, 0),
+            (
+                dashboard.registered,
+                dashboard.checked_in,
+                dashboard.waitlisted,
+                dashboard.dropped
+            )
+        );
+        assert_eq!(
+            (2, 1),
+            (dashboard.teams[0].members, dashboard.teams[0].checked_in)
+        );
+    }
+}
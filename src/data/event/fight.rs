@@ -0,0 +1,128 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::Event;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// A fight within an event, e.g. a slot of the tournament bracket
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct EventFight {
+    id: Id,
+    event: Id,
+    /// the name of the fight, e.g. "Semi-final 1"
+    name: String,
+    #[serde(default)]
+    state: FightState,
+    /// the uuid of the winner once the fight is finished, absent for draws
+    winner: Option<String>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FightState {
+    #[default]
+    Scheduled,
+    Finished,
+}
+
+impl EventFight {
+    /// Adds the statement marking the fight as finished with the given winner to the query
+    pub fn finish<'r>(&self, winner: Option<&str>, query: DatabaseQuery<'r>) -> DatabaseQuery<'r> {
+        query
+            .query("UPDATE $fight SET state = 'finished', winner = $fight_winner")
+            .bind(("fight", self.id.to_thing()))
+            .bind(("fight_winner", winner))
+    }
+}
+
+impl Event {
+    /// Adds a new fight to the event
+    #[instrument(skip(self, connection))]
+    pub async fn create_fight(
+        &self,
+        name: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<EventFight> {
+        self.status.editable()?;
+        if name.trim().is_empty() {
+            return Err(ApplicationError::BadRequest(
+                "the name can't be empty".to_owned(),
+            ));
+        }
+
+        let fight = sql_span!(connection
+            .query("CREATE event_fight SET event = $event, name = $name")
+            .bind(("event", self.id.to_thing()))
+            .bind(("name", name.trim()))
+            .await?
+            .take::<Option<EventFight>>(0)?);
+        fight.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch a fight of the event by its id
+    #[instrument(skip(self, connection))]
+    pub async fn fight(&self, id: &str, connection: &DatabaseConnection) -> Result<EventFight> {
+        let fight: Option<EventFight> = sql_span!(
+            connection
+                .select(&Id::try_from(("event_fight", id))?)
+                .await?
+        );
+
+        fight
+            .filter(|fight| fight.event == self.id)
+            .ok_or(ApplicationError::BadRequest("fight not found".to_owned()))
+    }
+
+    /// Lists the fights of the event in the order they were created
+    #[instrument(skip_all)]
+    pub async fn fights(&self, connection: &DatabaseConnection) -> Result<Vec<EventFight>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_fight WHERE event = $event ORDER BY created_at ASC")
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<EventFight>>(0)?))
+    }
+
+    /// Removes the fight from the event
+    #[instrument(skip(self, connection))]
+    pub async fn delete_fight(&self, id: &str, connection: &DatabaseConnection) -> Result<()> {
+        let fight = self.fight(id, connection).await?;
+        // the results of the fight are kept for the event
+        sql_span!(connection
+            .query("UPDATE match_result SET fight = NONE WHERE fight = $fight")
+            .query("DELETE $fight")
+            .bind(("fight", fight.id.to_thing()))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+}
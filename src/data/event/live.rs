@@ -0,0 +1,214 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::fight::EventFight;
+use crate::data::event::registration::EventRegistration;
+use crate::data::event::result::MatchResult;
+use crate::data::event::Event;
+use crate::data::notification::Notification;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use futures::Stream;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+/// The amount of messages per topic kept for slow subscribers before they start lagging behind
+const LIVE_CAPACITY: usize = 256;
+/// The maximum length of announcements
+const ANNOUNCEMENT_LENGTH: usize = 1000;
+
+/// A manual announcement by an organiser of the event
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct EventAnnouncement {
+    id: Id,
+    event: Id,
+    message: String,
+    /// the organiser who published the announcement, machine clients don't have one
+    author: Option<Id>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// A message pushed to the viewers of an event
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(tag = "type", content = "data", rename_all = "lowercase")]
+pub enum LiveMessage {
+    /// the event itself has been changed, e.g. its schedule or status
+    Updated(Event),
+    Announcement(EventAnnouncement),
+    /// a fight has been added or its state changed
+    Fight(EventFight),
+    /// a match outcome has been reported
+    Result(MatchResult),
+    /// a registration checked in, moved up from the waitlist or was dropped
+    Registration(EventRegistration),
+    /// a new notification, only pushed to the stream of the notified account
+    Notification(Notification),
+    /// the subscriber was too slow and missed messages, the state has to be fetched again
+    Lagged(u64),
+}
+
+impl LiveMessage {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LiveMessage::Updated(_) => "updated",
+            LiveMessage::Announcement(_) => "announcement",
+            LiveMessage::Fight(_) => "fight",
+            LiveMessage::Result(_) => "result",
+            LiveMessage::Registration(_) => "registration",
+            LiveMessage::Notification(_) => "notification",
+            LiveMessage::Lagged(_) => "lagged",
+        }
+    }
+}
+
+/// In-process bus distributing live messages to the viewers of events and to notified accounts.
+/// Every event or account has its own channel, so busy topics don't make others lag behind.
+#[derive(Debug, Clone, Default)]
+pub struct LiveBus {
+    topics: Arc<Mutex<HashMap<Id, broadcast::Sender<LiveMessage>>>>,
+}
+
+impl LiveBus {
+    /// Pushes the message to all current viewers of the event
+    pub fn publish(&self, event: &Id, message: LiveMessage) {
+        let mut topics = self.topics.lock().unwrap();
+        if let Some(sender) = topics.get(event) {
+            // sending only fails without any viewers, whose channel isn't needed anymore
+            if sender.send(message).is_err() {
+                topics.remove(event);
+            }
+        }
+    }
+
+    /// Subscribes to the messages of the event, starting with the next published one
+    pub fn subscribe(&self, event: &Id) -> impl Stream<Item = LiveMessage> {
+        let receiver = self
+            .topics
+            .lock()
+            .unwrap()
+            .entry(event.clone())
+            .or_insert_with(|| broadcast::channel(LIVE_CAPACITY).0)
+            .subscribe();
+
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            match receiver.recv().await {
+                Ok(message) => Some((message, receiver)),
+                Err(RecvError::Lagged(skipped)) => Some((LiveMessage::Lagged(skipped), receiver)),
+                Err(RecvError::Closed) => None,
+            }
+        })
+    }
+}
+
+impl Event {
+    /// Saves an announcement for the event, which has to be published to the viewers afterwards
+    #[instrument(skip_all)]
+    pub async fn announce(
+        &self,
+        message: &str,
+        author: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<EventAnnouncement> {
+        let message = message.trim();
+        if message.is_empty() || message.chars().count() > ANNOUNCEMENT_LENGTH {
+            return Err(ApplicationError::BadRequest(format!(
+                "announcements have to contain 1 to {ANNOUNCEMENT_LENGTH} characters"
+            )));
+        }
+
+        let announcement = sql_span!(connection
+            .query("CREATE event_announcement SET event = $event, message = $message, author = $author")
+            .bind(("event", self.id.to_thing()))
+            .bind(("message", message))
+            .bind(("author", author.map(Id::to_thing)))
+            .await?
+            .take::<Option<EventAnnouncement>>(0)?);
+        announcement.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Lists all announcements for the event, the latest first
+    #[instrument(skip_all)]
+    pub async fn announcements(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<EventAnnouncement>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_announcement WHERE event = $event ORDER BY created_at DESC")
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<EventAnnouncement>>(0)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::live::{LiveBus, LiveMessage};
+    use crate::prelude::*;
+    use futures::StreamExt;
+
+    #[tokio::test]
+    async fn test_bus() {
+        let bus = LiveBus::default();
+        let event = Id::new(("event", "a"));
+        let stream = bus.subscribe(&event);
+        futures::pin_mut!(stream);
+
+        bus.publish(&Id::new(("event", "b")), LiveMessage::Lagged(1));
+        bus.publish(&event, LiveMessage::Lagged(2));
+        assert_eq!(Some(LiveMessage::Lagged(2)), stream.next().await);
+    }
+
+    #[tokio::test]
+    async fn test_lagged() {
+        let bus = LiveBus::default();
+        let event = Id::new(("event", "a"));
+        let stream = bus.subscribe(&event);
+        futures::pin_mut!(stream);
+
+        for _ in 0..300 {
+            bus.publish(&event, LiveMessage::Lagged(0));
+        }
+        assert_eq!(Some(LiveMessage::Lagged(44)), stream.next().await);
+    }
+
+    #[tokio::test]
+    async fn test_topics() {
+        let bus = LiveBus::default();
+        let event = Id::new(("event", "a"));
+        let stream = bus.subscribe(&event);
+        assert_eq!(1, bus.topics.lock().unwrap().len());
+
+        // the channel is dropped with the first message nobody receives
+        drop(stream);
+        bus.publish(&event, LiveMessage::Lagged(0));
+        assert!(bus.topics.lock().unwrap().is_empty());
+    }
+}
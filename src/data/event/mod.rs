@@ -24,10 +24,28 @@
  *
  */
 
+use crate::data::asset::image::ImageVariants;
+use crate::data::event::attachment::EventAttachment;
+use crate::data::event::series::EventSeries;
+use crate::data::feed::{page_link, FeedEntry};
+use crate::data::patch::{check_id, nullable};
 use crate::prelude::*;
 use chrono::{DateTime, Utc};
 
-mod team;
+pub mod attachment;
+pub mod calendar;
+pub mod check_in;
+pub mod fight;
+pub mod live;
+pub mod registration;
+pub mod result;
+pub mod search;
+pub mod seeding;
+pub mod series;
+pub mod team;
+
+/// The earliest the check-in may open before the start, one week
+const CHECK_IN_LIMIT: u32 = 7 * 24 * 60;
 
 #[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
 #[serde(rename_all = "camelCase")]
@@ -41,6 +59,342 @@ pub struct Event {
     start: DateTime<Utc>,
     /// the scheduled end of the event
     end: DateTime<Utc>,
+    #[serde(default)]
+    status: EventStatus,
+    /// the maximum amount of registered players, further registrations are waitlisted
+    capacity: Option<u32>,
+    /// the minutes before the start in which registered players have to check in
+    #[serde(alias = "check_in")]
+    check_in: Option<u32>,
+    /// the series the event is an occurrence of
+    series: Option<Id>,
+    /// whether the occurrence was edited on its own, changes of the series skip it
+    #[serde(default)]
+    detached: bool,
+    /// the variants of the uploaded cover image
+    #[serde(default)]
+    images: Option<ImageVariants>,
+    /// files like the rules or the schematic of the arena
+    #[serde(default)]
+    attachments: Vec<EventAttachment>,
     #[serde(alias = "created_at")]
     created_at: DateTime<Utc>,
 }
+
+/// The lifecycle of an event. Drafts are only visible to organisers, finished and cancelled
+/// events can't be changed anymore.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum EventStatus {
+    Draft,
+    #[default]
+    Published,
+    Running,
+    Finished,
+    Cancelled,
+}
+
+impl EventStatus {
+    /// Whether the status may be changed to the given one
+    pub fn can_transition(&self, to: EventStatus) -> bool {
+        use EventStatus::*;
+
+        matches!(
+            (self, to),
+            (Draft, Published)
+                | (Draft, Cancelled)
+                | (Published, Draft)
+                | (Published, Running)
+                | (Published, Cancelled)
+                | (Running, Finished)
+                | (Running, Cancelled)
+        )
+    }
+
+    /// Fails if the status may not be changed to the given one
+    pub fn transition(&self, to: EventStatus) -> Result<()> {
+        if *self != to && !self.can_transition(to) {
+            return Err(ApplicationError::BadRequest(format!(
+                "the status can't be changed from {} to {}",
+                self.name(),
+                to.name()
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Fails if the event is finished or cancelled
+    pub fn editable(&self) -> Result<()> {
+        if matches!(self, EventStatus::Finished | EventStatus::Cancelled) {
+            return Err(ApplicationError::BadRequest(format!(
+                "{} events can't be changed anymore",
+                self.name()
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventStatus::Draft => "draft",
+            EventStatus::Published => "published",
+            EventStatus::Running => "running",
+            EventStatus::Finished => "finished",
+            EventStatus::Cancelled => "cancelled",
+        }
+    }
+}
+
+/// A partial update of an event, absent fields are left untouched
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EventPatch {
+    /// has to match the updated event if given
+    pub id: Option<Id>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+    pub status: Option<EventStatus>,
+    /// null removes the capacity
+    #[serde(default, deserialize_with = "nullable")]
+    pub capacity: Option<Option<u32>>,
+    /// null disables the check-in
+    #[serde(default, deserialize_with = "nullable", alias = "check_in")]
+    pub check_in: Option<Option<u32>>,
+}
+
+impl From<Event> for EventPatch {
+    fn from(event: Event) -> Self {
+        Self {
+            id: Some(event.id),
+            name: Some(event.name),
+            description: Some(event.description),
+            start: Some(event.start),
+            end: Some(event.end),
+            status: Some(event.status),
+            capacity: Some(event.capacity),
+            check_in: Some(event.check_in),
+        }
+    }
+}
+
+/// Fails if the event doesn't end after its start
+pub fn validate_schedule(start: &DateTime<Utc>, end: &DateTime<Utc>) -> Result<()> {
+    if end <= start {
+        return Err(ApplicationError::BadRequest(
+            "the end has to be after the start".to_owned(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fails if the capacity or the check-in window is out of range
+pub fn validate_registration(capacity: Option<u32>, check_in: Option<u32>) -> Result<()> {
+    if capacity == Some(0) {
+        return Err(ApplicationError::BadRequest(
+            "the capacity has to be at least 1".to_owned(),
+        ));
+    }
+    if check_in.is_some_and(|minutes| !(1..=CHECK_IN_LIMIT).contains(&minutes)) {
+        return Err(ApplicationError::BadRequest(format!(
+            "the check-in has to open between 1 and {CHECK_IN_LIMIT} minutes before the start"
+        )));
+    }
+
+    Ok(())
+}
+
+impl Event {
+    /// Fetch an event by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let event: Option<Event> =
+            sql_span!(connection.select(&Id::try_from(("event", id))?).await?);
+
+        event.ok_or(ApplicationError::BadRequest("event not found".to_owned()))
+    }
+
+    /// Fetch an event by its id, drafts are treated as missing
+    pub async fn visible(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let event = Self::from_id(id, connection).await?;
+        if event.status == EventStatus::Draft {
+            return Err(ApplicationError::BadRequest("event not found".to_owned()));
+        }
+
+        Ok(event)
+    }
+
+    /// Changes the status of the event, if the lifecycle allows it
+    #[instrument(skip(self, connection))]
+    pub async fn set_status(
+        &mut self,
+        status: EventStatus,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        self.status.transition(status)?;
+
+        let event = sql_span!(connection
+            .query("UPDATE $event SET status = $status")
+            .bind(("event", self.id.to_thing()))
+            .bind(("status", status))
+            .await?
+            .take::<Option<Event>>(0)?);
+        *self = event.ok_or(ApplicationError::BadRequest("event not found".to_owned()))?;
+        Ok(())
+    }
+
+    /// Applies the given changes. The id and creation time are owned by the server and can't be
+    /// changed, status changes have to follow the lifecycle.
+    #[instrument(skip_all)]
+    pub async fn patch(
+        &mut self,
+        patch: &EventPatch,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        check_id(&self.id, patch.id.as_ref())?;
+        self.status.editable()?;
+        if let Some(status) = patch.status {
+            self.status.transition(status)?;
+        }
+        validate_schedule(
+            patch.start.as_ref().unwrap_or(&self.start),
+            patch.end.as_ref().unwrap_or(&self.end),
+        )?;
+        validate_registration(
+            patch.capacity.unwrap_or(self.capacity),
+            patch.check_in.unwrap_or(self.check_in),
+        )?;
+
+        let mut changes = serde_json::Map::new();
+        if let Some(name) = &patch.name {
+            changes.insert("name".to_owned(), json!(name));
+        }
+        if let Some(description) = &patch.description {
+            changes.insert("description".to_owned(), json!(description));
+        }
+        if let Some(start) = &patch.start {
+            changes.insert("start".to_owned(), json!(start));
+        }
+        if let Some(end) = &patch.end {
+            changes.insert("end".to_owned(), json!(end));
+        }
+        if let Some(status) = &patch.status {
+            changes.insert("status".to_owned(), json!(status));
+        }
+        if self.series.is_some() {
+            changes.insert("detached".to_owned(), json!(true));
+        }
+        // null can't be merged into the optional fields, removed ones are set to none
+        let mut removed = vec![];
+        for (field, value) in [("capacity", patch.capacity), ("check_in", patch.check_in)] {
+            match value {
+                Some(Some(value)) => {
+                    changes.insert(field.to_owned(), json!(value));
+                }
+                Some(None) => removed.push(format!("{field} = NONE")),
+                None => {}
+            }
+        }
+
+        let mut query = connection.query("UPDATE $event MERGE $changes");
+        if !removed.is_empty() {
+            query = query.query(format!("UPDATE $event SET {}", removed.join(", ")));
+        }
+        let event = sql_span!(query
+            .bind(("event", self.id.to_thing()))
+            .bind(("changes", changes))
+            .await?
+            .take::<Option<Event>>(usize::from(!removed.is_empty()))?);
+        let previous = self.start;
+        *self = event.ok_or(ApplicationError::BadRequest("event not found".to_owned()))?;
+
+        // moved occurrences are skipped at their original start, otherwise the series would
+        // create them again
+        if let Some(series) = &self.series {
+            if self.start != previous {
+                EventSeries::skip(series, &previous, connection).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Select all events except drafts, ordered by their start
+    #[instrument(skip_all)]
+    pub async fn all(connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event WHERE status != 'draft' ORDER BY start ASC")
+            .await?
+            .take::<Vec<Event>>(0)?))
+    }
+
+    /// Select the published events which haven't ended yet, the next one first
+    #[instrument(skip(connection))]
+    pub async fn upcoming(limit: u64, connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query(format!(
+                "SELECT * FROM event WHERE end > time::now() \
+                AND status INSIDE ['published', 'running'] ORDER BY start ASC LIMIT {limit}"
+            ))
+            .await?
+            .take::<Vec<Event>>(0)?))
+    }
+}
+
+impl From<&Event> for FeedEntry {
+    fn from(event: &Event) -> Self {
+        Self {
+            link: page_link(format!("/event/{}", event.id.id).as_str()),
+            title: event.name.clone(),
+            content: Some(format!(
+                "<p>{} - {}</p><p>{}</p>",
+                event.start.to_rfc2822(),
+                event.end.to_rfc2822(),
+                ammonia::clean(event.description.as_str())
+            )),
+            published: event.created_at,
+            updated: event.created_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn test_transition() {
+        assert!(EventStatus::Draft
+            .transition(EventStatus::Published)
+            .is_ok());
+        assert!(EventStatus::Published
+            .transition(EventStatus::Published)
+            .is_ok());
+        assert!(EventStatus::Running
+            .transition(EventStatus::Finished)
+            .is_ok());
+        assert!(EventStatus::Draft.transition(EventStatus::Running).is_err());
+        assert!(EventStatus::Finished
+            .transition(EventStatus::Running)
+            .is_err());
+        assert!(EventStatus::Cancelled
+            .transition(EventStatus::Published)
+            .is_err());
+
+        assert!(EventStatus::Running.editable().is_ok());
+        assert!(EventStatus::Finished.editable().is_err());
+        assert!(EventStatus::Cancelled.editable().is_err());
+    }
+
+    #[test]
+    fn test_validate_schedule() {
+        let now = Utc::now();
+        assert!(validate_schedule(&now, &(now + Duration::hours(1))).is_ok());
+        assert!(validate_schedule(&now, &now).is_err());
+        assert!(validate_schedule(&now, &(now - Duration::hours(1))).is_err());
+    }
+}
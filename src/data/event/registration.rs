@@ -0,0 +1,226 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::{Event, EventStatus};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// The registration of an account for an event
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct EventRegistration {
+    id: Id,
+    event: Id,
+    account: Id,
+    #[serde(default)]
+    status: RegistrationStatus,
+    /// the time the player checked in, if the event requires it
+    #[serde(alias = "checked_in_at")]
+    checked_in_at: Option<DateTime<Utc>>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RegistrationStatus {
+    #[default]
+    Registered,
+    /// the event was full, the registration moves up once a place becomes free
+    Waitlisted,
+    /// the player didn't check in before the start
+    Dropped,
+}
+
+/// The uuid of the account linked to a registration
+#[derive(Deserialize)]
+struct RegisteredUuid {
+    uuid: Option<String>,
+}
+
+/// A registration with its fetched event
+#[derive(Deserialize)]
+struct RegisteredEvent {
+    event: Event,
+}
+
+impl Event {
+    /// Fails if the event doesn't accept registrations, like ended or cancelled events
+    pub(crate) fn accepts_registrations(&self) -> Result<()> {
+        if self.end <= Utc::now() {
+            return Err(ApplicationError::BadRequest(
+                "the event has already ended".to_owned(),
+            ));
+        }
+        if !matches!(self.status, EventStatus::Published | EventStatus::Running) {
+            return Err(ApplicationError::BadRequest(
+                "the event doesn't accept registrations".to_owned(),
+            ));
+        }
+        if self.check_in_closed() {
+            return Err(ApplicationError::BadRequest(
+                "the registration closed with the check-in".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Registers the account for the event. Ended events don't accept registrations anymore,
+    /// registrations for full events are waitlisted.
+    #[instrument(skip_all)]
+    pub async fn register(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<EventRegistration> {
+        self.accepts_registrations()?;
+        if self.registration(account, connection).await?.is_some() {
+            return Err(ApplicationError::Conflict(
+                "already registered for the event".to_owned(),
+            ));
+        }
+        let status = match self.free_places(connection).await? {
+            Some(0) => RegistrationStatus::Waitlisted,
+            _ => RegistrationStatus::Registered,
+        };
+
+        let registration = sql_span!(connection
+            .query(
+                "CREATE event_registration SET event = $event, account = $account, \
+                status = $status"
+            )
+            .bind(("event", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .bind(("status", status))
+            .await?
+            .take::<Option<EventRegistration>>(0)?);
+        registration.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Counts the places left until the capacity is reached, unlimited without a capacity
+    pub(crate) async fn free_places(&self, connection: &DatabaseConnection) -> Result<Option<u32>> {
+        let Some(capacity) = self.capacity else {
+            return Ok(None);
+        };
+        let registered = self
+            .registrations(connection)
+            .await?
+            .iter()
+            .filter(|registration| registration.status == RegistrationStatus::Registered)
+            .count();
+
+        Ok(Some(capacity.saturating_sub(registered as u32)))
+    }
+
+    /// Removes the registration of the account for the event. The freed place is given to the
+    /// waitlist, the promoted registrations are returned.
+    #[instrument(skip_all)]
+    pub async fn unregister(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<EventRegistration>> {
+        sql_span!(connection
+            .query("DELETE event_registration WHERE event = $event AND account = $account")
+            .bind(("event", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .await?
+            .check()?);
+
+        self.fill(connection).await
+    }
+
+    /// Fetch the registration of the account for the event
+    #[instrument(skip_all)]
+    pub async fn registration(
+        &self,
+        account: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<Option<EventRegistration>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_registration WHERE event = $event AND account = $account")
+            .bind(("event", self.id.to_thing()))
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Option<EventRegistration>>(0)?))
+    }
+
+    /// Lists all registrations for the event, the earliest first
+    #[instrument(skip_all)]
+    pub async fn registrations(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<EventRegistration>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_registration WHERE event = $event ORDER BY created_at ASC")
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<EventRegistration>>(0)?))
+    }
+
+    /// Lists the lowercase uuids of the registered accounts, accounts without a linked
+    /// minecraft account as well as waitlisted and dropped registrations are left out
+    pub(crate) async fn registered_uuids(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<String>> {
+        Ok(sql_span!(connection
+            .query(
+                "SELECT account.uuid AS uuid, created_at FROM event_registration \
+                WHERE event = $event AND status = 'registered' ORDER BY created_at ASC"
+            )
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<RegisteredUuid>>(0)?)
+        .into_iter()
+        .filter_map(|registered| registered.uuid.map(|uuid| uuid.to_lowercase()))
+        .collect())
+    }
+
+    /// Select all visible events the account holds a place for, ordered by their start.
+    /// Waitlisted and dropped registrations are left out.
+    #[instrument(skip_all)]
+    pub async fn registered(account: &Id, connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        let registrations = sql_span!(connection
+            .query(
+                "SELECT event FROM event_registration \
+                WHERE account = $account AND status = 'registered' FETCH event"
+            )
+            .bind(("account", account.to_thing()))
+            .await?
+            .take::<Vec<RegisteredEvent>>(0)?);
+
+        let mut events = registrations
+            .into_iter()
+            .map(|registration| registration.event)
+            .filter(|event| event.status != EventStatus::Draft)
+            .collect::<Vec<Event>>();
+        events.sort_by_key(|event| event.start);
+        Ok(events)
+    }
+}
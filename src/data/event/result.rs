@@ -0,0 +1,330 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::data::event::fight::FightState;
+use crate::data::event::{Event, EventStatus};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
+
+/// The performance of a single player within a match
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct MatchParticipant {
+    /// the uuid of the minecraft account
+    pub uuid: String,
+    #[serde(default)]
+    pub kills: u32,
+    #[serde(default)]
+    pub deaths: u32,
+}
+
+/// A match outcome as reported by a game server
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+pub struct MatchReport {
+    /// the key identifying the match, repeated reports of the same match are only stored once
+    pub key: String,
+    /// the fight of the event the match was played for
+    pub fight: Option<String>,
+    pub participants: Vec<MatchParticipant>,
+    /// the duration of the match in seconds
+    pub duration: u64,
+    /// the uuid of the winner, absent for draws
+    pub winner: Option<String>,
+}
+
+/// A stored match outcome
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct MatchResult {
+    id: Id,
+    key: String,
+    event: Id,
+    fight: Option<Id>,
+    participants: Vec<MatchParticipant>,
+    /// the duration of the match in seconds
+    duration: u64,
+    /// the uuid of the winner, absent for draws
+    winner: Option<String>,
+    /// the machine client which reported the match
+    reporter: Option<Id>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+impl MatchReport {
+    /// Normalises the uuids, so they can be compared with the linked accounts
+    fn normalize(mut self) -> Self {
+        self.key = self.key.trim().to_owned();
+        for participant in self.participants.iter_mut() {
+            participant.uuid = participant.uuid.trim().to_lowercase();
+        }
+        self.winner = self.winner.map(|winner| winner.trim().to_lowercase());
+        self
+    }
+
+    /// Checks the report for consistency, without consulting the database
+    fn validate(&self) -> Result<()> {
+        if self.key.is_empty() {
+            return Err(ApplicationError::BadRequest(
+                "the match key can't be empty".to_owned(),
+            ));
+        }
+        if self.participants.len() < 2 {
+            return Err(ApplicationError::BadRequest(
+                "a match needs at least two participants".to_owned(),
+            ));
+        }
+
+        let mut uuids = HashSet::new();
+        if !self
+            .participants
+            .iter()
+            .all(|participant| uuids.insert(participant.uuid.as_str()))
+        {
+            return Err(ApplicationError::BadRequest(
+                "participants can't be listed twice".to_owned(),
+            ));
+        }
+        if let Some(winner) = &self.winner {
+            if !uuids.contains(winner.as_str()) {
+                return Err(ApplicationError::BadRequest(
+                    "the winner has to be a participant".to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Whether the stored result describes the same match outcome
+    fn is_reported_by(&self, event: &Event, result: &MatchResult) -> bool {
+        result.event == event.id
+            && result.fight.as_ref().map(Id::to_string) == self.fight
+            && result.participants == self.participants
+            && result.duration == self.duration
+            && result.winner == self.winner
+    }
+}
+
+impl Event {
+    /// Stores the reported match outcome. Reports are idempotent on their key: repeating a
+    /// report returns the stored result, while a different outcome for the same key is a
+    /// conflict. The bool tells whether the result was newly created.
+    #[instrument(skip_all)]
+    pub async fn report(
+        &self,
+        report: MatchReport,
+        reporter: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<(MatchResult, bool)> {
+        let report = report.normalize();
+        report.validate()?;
+
+        if let Some(result) = MatchResult::from_key(report.key.as_str(), connection).await? {
+            return if report.is_reported_by(self, &result) {
+                Ok((result, false))
+            } else {
+                Err(ApplicationError::Conflict(
+                    "another outcome has already been reported for the match".to_owned(),
+                ))
+            };
+        }
+
+        if self.status != EventStatus::Running {
+            return Err(ApplicationError::BadRequest(
+                "results can only be reported for running events".to_owned(),
+            ));
+        }
+        let fight = match &report.fight {
+            Some(fight) => {
+                let fight = self.fight(fight.as_str(), connection).await?;
+                if *fight.state() == FightState::Finished {
+                    return Err(ApplicationError::Conflict(
+                        "the fight has already been finished".to_owned(),
+                    ));
+                }
+                Some(fight)
+            }
+            None => None,
+        };
+        self.check_registered(&report.participants, connection)
+            .await?;
+
+        // the result, the statistics, the ratings and the fight are stored together, so a failed
+        // report can be retried without losing any of them
+        let id = Id::new(("match_result", nanoid::nanoid!(20, &ALPHABET).as_str()));
+        let mut query = connection
+            .query("BEGIN TRANSACTION")
+            .query(
+                "CREATE $result SET key = $key, event = $event, fight = $result_fight, \
+                participants = $participants, duration = $duration, winner = $winner, \
+                reporter = $reporter",
+            )
+            .bind(("result", id.to_thing()))
+            .bind(("key", report.key.as_str()))
+            .bind(("event", self.id.to_thing()))
+            .bind((
+                "result_fight",
+                fight.as_ref().map(|fight| fight.id().to_thing()),
+            ))
+            .bind(("participants", &report.participants))
+            .bind(("duration", report.duration))
+            .bind(("winner", report.winner.as_deref()))
+            .bind(("reporter", reporter.map(Id::to_thing)));
+        query = report.participate(query);
+        query = report.rate(&id, query, connection).await?;
+        if let Some(fight) = &fight {
+            query = fight.finish(report.winner.as_deref(), query);
+        }
+        let created = sql_span!(query
+            .query("COMMIT TRANSACTION")
+            .await
+            .and_then(|response| response.check())
+            .and_then(|mut response| response.take::<Option<MatchResult>>(0)));
+        let result = match created {
+            Ok(Some(result)) => result,
+            Ok(None) => return Err(ApplicationError::InternalServerError),
+            // the unique key was taken by a concurrent report in the meantime
+            Err(error) => {
+                return match MatchResult::from_key(report.key.as_str(), connection).await? {
+                    Some(result) if report.is_reported_by(self, &result) => Ok((result, false)),
+                    Some(_) => Err(ApplicationError::Conflict(
+                        "another outcome has already been reported for the match".to_owned(),
+                    )),
+                    None => Err(error.into()),
+                };
+            }
+        };
+
+        Ok((result, true))
+    }
+
+    /// Fails if any of the participants isn't registered for the event with a linked account
+    async fn check_registered(
+        &self,
+        participants: &[MatchParticipant],
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let registered = self
+            .registered_uuids(connection)
+            .await?
+            .into_iter()
+            .collect::<HashSet<String>>();
+
+        let unregistered = participants
+            .iter()
+            .filter(|participant| !registered.contains(&participant.uuid))
+            .map(|participant| participant.uuid.as_str())
+            .collect::<Vec<&str>>();
+        if !unregistered.is_empty() {
+            return Err(ApplicationError::BadRequest(format!(
+                "players aren't registered for the event: {}",
+                unregistered.join(", ")
+            )));
+        }
+
+        Ok(())
+    }
+
+    /// Lists the results of the event, the earliest first
+    #[instrument(skip_all)]
+    pub async fn results(&self, connection: &DatabaseConnection) -> Result<Vec<MatchResult>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM match_result WHERE event = $event ORDER BY created_at ASC")
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<MatchResult>>(0)?))
+    }
+}
+
+impl MatchReport {
+    /// Adds a participation for every player to the query creating the result, which feed the
+    /// statistics of the players
+    fn participate<'r>(&self, mut query: DatabaseQuery<'r>) -> DatabaseQuery<'r> {
+        for (index, participant) in self.participants.iter().enumerate() {
+            let wins = u32::from(self.winner.as_ref() == Some(&participant.uuid));
+            query = query
+                .query(format!(
+                    "CREATE match_participation SET result = $result, event = $event, \
+                    uuid = $participant{index}, kills = {}, deaths = {}, wins = {wins}, \
+                    duration = $duration",
+                    participant.kills, participant.deaths
+                ))
+                .bind((format!("participant{index}"), participant.uuid.as_str()));
+        }
+        query
+    }
+}
+
+impl MatchResult {
+    /// Fetch a result by the key of its match
+    #[instrument(skip(connection))]
+    pub async fn from_key(key: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM match_result WHERE key = $key")
+            .bind(("key", key))
+            .await?
+            .take::<Option<MatchResult>>(0)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::result::{MatchParticipant, MatchReport};
+
+    fn report(uuids: &[&str], winner: Option<&str>) -> MatchReport {
+        MatchReport {
+            key: " match-1 ".to_owned(),
+            fight: None,
+            participants: uuids
+                .iter()
+                .map(|uuid| MatchParticipant {
+                    uuid: uuid.to_string(),
+                    kills: 1,
+                    deaths: 0,
+                })
+                .collect(),
+            duration: 120,
+            winner: winner.map(str::to_owned),
+        }
+        .normalize()
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = report(&["A", "b"], Some("a"));
+        assert_eq!("match-1", valid.key);
+        assert_eq!("a", valid.participants[0].uuid);
+        assert!(valid.validate().is_ok());
+        assert!(report(&["a", "b"], None).validate().is_ok());
+
+        assert!(report(&["a"], None).validate().is_err());
+        assert!(report(&["a", "A"], None).validate().is_err());
+        assert!(report(&["a", "b"], Some("c")).validate().is_err());
+    }
+}
@@ -0,0 +1,134 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::EventStatus;
+use crate::data::schematic::search::SortOrder;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// The time window events are in, relative to now
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EventWindow {
+    /// events which haven't started yet
+    Upcoming,
+    /// events which have started, but not ended yet
+    Ongoing,
+    /// events which have ended
+    Past,
+}
+
+/// The filters of the event list. All given filters have to match.
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct EventSearch {
+    pub window: Option<EventWindow>,
+    /// only events ending after the given time
+    pub from: Option<DateTime<Utc>>,
+    /// only events starting before the given time
+    pub to: Option<DateTime<Utc>>,
+    pub status: Option<EventStatus>,
+    /// the order of the start of the events, ascending by default
+    pub order: Option<SortOrder>,
+}
+
+impl EventSearch {
+    /// Builds the query selecting the matching events. Drafts are only included for organisers.
+    /// User input is only passed as bindings.
+    pub fn query(&self, drafts: bool) -> (String, Vec<(&'static str, Value)>) {
+        let mut conditions = vec![];
+        let mut bindings = vec![];
+
+        if !drafts {
+            conditions.push("status != 'draft'");
+        }
+        match self.window {
+            Some(EventWindow::Upcoming) => conditions.push("start > time::now()"),
+            Some(EventWindow::Ongoing) => {
+                conditions.push("start <= time::now() AND end > time::now()")
+            }
+            Some(EventWindow::Past) => conditions.push("end <= time::now()"),
+            None => {}
+        }
+        if let Some(from) = self.from {
+            conditions.push("end >= <datetime> $from");
+            bindings.push(("from", Value::from(from.to_rfc3339())));
+        }
+        if let Some(to) = self.to {
+            conditions.push("start <= <datetime> $to");
+            bindings.push(("to", Value::from(to.to_rfc3339())));
+        }
+        if let Some(status) = self.status {
+            conditions.push("status = $status");
+            bindings.push(("status", Value::from(status.name())));
+        }
+
+        let filter = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {} ", conditions.join(" AND ")),
+        };
+        let order = match self.order.unwrap_or(SortOrder::Asc) {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+
+        (
+            format!("SELECT * FROM event {filter}ORDER BY start {order}"),
+            bindings,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::search::{EventSearch, EventWindow};
+    use crate::data::event::EventStatus;
+    use crate::data::schematic::search::SortOrder;
+    use chrono::Utc;
+
+    #[test]
+    fn test_query() {
+        let (query, bindings) = EventSearch::default().query(true);
+        assert_eq!("SELECT * FROM event ORDER BY start ASC", query);
+        assert!(bindings.is_empty());
+
+        let search = EventSearch {
+            window: Some(EventWindow::Upcoming),
+            to: Some(Utc::now()),
+            status: Some(EventStatus::Running),
+            order: Some(SortOrder::Desc),
+            ..Default::default()
+        };
+        let (query, bindings) = search.query(false);
+        assert_eq!(
+            "SELECT * FROM event WHERE status != 'draft' AND start > time::now() \
+            AND start <= <datetime> $to AND status = $status ORDER BY start DESC",
+            query
+        );
+        assert!(bindings.contains(&("status", serde_json::Value::from("running"))));
+        assert_eq!(2, bindings.len());
+    }
+}
@@ -0,0 +1,122 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::Event;
+use crate::data::rating::Rating;
+use crate::prelude::*;
+
+/// The largest group matchmaking accepts
+pub const MAX_GROUP_SIZE: usize = 64;
+
+/// A registered player placed by their rating in the current season
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct SeededPlayer {
+    /// the seed of the player, starting with 1 for the highest rating
+    pub seed: usize,
+    /// the uuid of the minecraft account
+    pub uuid: String,
+    pub rating: f64,
+}
+
+/// Orders the players by their rating, the highest first. Equal ratings are ordered by uuid.
+fn seed(mut players: Vec<(String, f64)>) -> Vec<SeededPlayer> {
+    players.sort_by(|(uuid, rating), (other_uuid, other)| {
+        other.total_cmp(rating).then_with(|| uuid.cmp(other_uuid))
+    });
+
+    players
+        .into_iter()
+        .enumerate()
+        .map(|(index, (uuid, rating))| SeededPlayer {
+            seed: index + 1,
+            uuid,
+            rating,
+        })
+        .collect()
+}
+
+/// Groups the seeded players into matches of players with similar ratings. The last group
+/// holds the remaining players and can be smaller.
+pub fn matchmake(players: Vec<SeededPlayer>, size: usize) -> Result<Vec<Vec<SeededPlayer>>> {
+    if !(2..=MAX_GROUP_SIZE).contains(&size) {
+        return Err(ApplicationError::BadRequest(format!(
+            "the group size has to be between 2 and {MAX_GROUP_SIZE}"
+        )));
+    }
+
+    Ok(players.chunks(size).map(<[SeededPlayer]>::to_vec).collect())
+}
+
+impl Event {
+    /// Seeds the registered players with a linked account by their rating in the current
+    /// season, e.g. to fill a bracket
+    #[instrument(skip_all)]
+    pub async fn seeding(&self, connection: &DatabaseConnection) -> Result<Vec<SeededPlayer>> {
+        let uuids = self.registered_uuids(connection).await?;
+        let uuids = uuids.iter().map(String::as_str).collect::<Vec<&str>>();
+        let ratings = Rating::current(uuids.as_slice(), connection).await?;
+
+        Ok(seed(
+            uuids.into_iter().map(str::to_owned).zip(ratings).collect(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::seeding::{matchmake, seed};
+
+    #[test]
+    fn test_seed() {
+        let seeded = seed(vec![
+            ("b".to_owned(), 1500.0),
+            ("c".to_owned(), 1620.5),
+            ("a".to_owned(), 1500.0),
+        ]);
+        assert_eq!(
+            vec![("c", 1), ("a", 2), ("b", 3)],
+            seeded
+                .iter()
+                .map(|player| (player.uuid.as_str(), player.seed))
+                .collect::<Vec<(&str, usize)>>()
+        );
+
+        let groups = matchmake(seeded.clone(), 2).unwrap();
+        assert_eq!(2, groups.len());
+        assert_eq!(
+            vec!["c", "a"],
+            groups[0]
+                .iter()
+                .map(|p| p.uuid.as_str())
+                .collect::<Vec<&str>>()
+        );
+        assert_eq!(1, groups[1].len());
+
+        assert!(matchmake(seeded.clone(), 1).is_err());
+        assert!(matchmake(seeded, 65).is_err());
+        assert!(matchmake(vec![], 2).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,630 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::data::asset::Asset;
+use crate::data::event::{validate_registration, validate_schedule, Event, EventStatus};
+use crate::data::patch::nullable;
+use crate::prelude::*;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+
+/// How far ahead the occurrences of a series are created
+const SERIES_HORIZON_DAYS: i64 = 90;
+/// How often the occurrences of the series are created as the horizon moves on
+const SERIES_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+/// The maximum amount of candidates checked when expanding a rule
+const EXPANSION_LIMIT: u32 = 10_000;
+/// The maximum amount of periods between two occurrences
+const MAX_INTERVAL: u32 = 1_000;
+/// The occurrences of a series which follow its changes from a date onward
+const OCCURRENCES: &str = "series = $series AND detached = false \
+    AND start >= <datetime> $from AND status INSIDE ['draft', 'published']";
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    /// on the day of the month of the first occurrence, months without that day are skipped
+    Monthly,
+}
+
+/// When the occurrences of a series take place, modelled after the iCalendar RRULE. The
+/// occurrences repeat in UTC.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    /// the amount of periods between two occurrences, 1 by default
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    /// the maximum amount of occurrences, skipped ones included
+    pub count: Option<u32>,
+    /// no occurrences start after it
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+impl RecurrenceRule {
+    fn validate(&self) -> Result<()> {
+        if self.interval == 0 {
+            return Err(ApplicationError::BadRequest(
+                "the interval has to be at least 1".to_owned(),
+            ));
+        }
+        if self.interval > MAX_INTERVAL {
+            return Err(ApplicationError::BadRequest(format!(
+                "the interval can't exceed {MAX_INTERVAL}"
+            )));
+        }
+        if self.count == Some(0) {
+            return Err(ApplicationError::BadRequest(
+                "the count has to be at least 1".to_owned(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// The start of the candidate in the given period, none if the month lacks the day or the
+    /// date is out of range
+    fn candidate(&self, start: &DateTime<Utc>, period: u32) -> Option<DateTime<Utc>> {
+        let steps = i64::from(period) * i64::from(self.interval);
+        match self.frequency {
+            Frequency::Daily => start.checked_add_signed(Duration::try_days(steps)?),
+            Frequency::Weekly => start.checked_add_signed(Duration::try_weeks(steps)?),
+            Frequency::Monthly => {
+                let months = i64::from(start.month0()) + steps;
+                let year = start.year() + i32::try_from(months / 12).ok()?;
+                let date = NaiveDate::from_ymd_opt(year, (months % 12) as u32 + 1, start.day())?;
+                Some(Utc.from_utc_datetime(&date.and_time(start.time())))
+            }
+        }
+    }
+
+    /// The starts of all occurrences in order, exceptions included
+    fn iter<'a>(&'a self, start: &'a DateTime<Utc>) -> impl Iterator<Item = DateTime<Utc>> + 'a {
+        (0..EXPANSION_LIMIT)
+            .filter_map(move |period| self.candidate(start, period))
+            .take_while(move |occurrence| self.until.is_none_or(|until| *occurrence <= until))
+            .take(self.count.map_or(usize::MAX, |count| count as usize))
+    }
+
+    /// The starts of all occurrences before the given time, exceptions included
+    fn expand(&self, start: &DateTime<Utc>, before: &DateTime<Utc>) -> Vec<DateTime<Utc>> {
+        self.iter(start)
+            .take_while(|occurrence| occurrence < before)
+            .collect()
+    }
+
+    /// The starts of the occurrences within the given range, without the exceptions
+    pub fn occurrences(
+        &self,
+        start: &DateTime<Utc>,
+        exceptions: &[DateTime<Utc>],
+        from: &DateTime<Utc>,
+        to: &DateTime<Utc>,
+    ) -> Vec<DateTime<Utc>> {
+        self.expand(start, to)
+            .into_iter()
+            .filter(|occurrence| occurrence >= from && !exceptions.contains(occurrence))
+            .collect()
+    }
+}
+
+/// A template for recurring events. Its occurrences are created as regular events ahead of
+/// time, which are linked to the series.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct EventSeries {
+    id: Id,
+    name: String,
+    description: String,
+    /// the start of the first occurrence
+    start: DateTime<Utc>,
+    /// the length of every occurrence in minutes
+    duration: u32,
+    rule: RecurrenceRule,
+    /// the starts of skipped occurrences
+    #[serde(default)]
+    exceptions: Vec<DateTime<Utc>>,
+    /// the status of newly created occurrences, either draft or published
+    #[serde(default)]
+    status: EventStatus,
+    capacity: Option<u32>,
+    #[serde(alias = "check_in")]
+    check_in: Option<u32>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// A new series of events
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct CreateSeriesRequest {
+    name: String,
+    description: String,
+    /// the start of the first occurrence
+    start: DateTime<Utc>,
+    /// the end of the first occurrence, which sets the length of all occurrences
+    end: DateTime<Utc>,
+    rule: RecurrenceRule,
+    /// the starts of occurrences to skip
+    #[serde(default)]
+    exceptions: Vec<DateTime<Utc>>,
+    /// either draft or published (default)
+    #[serde(default)]
+    status: EventStatus,
+    capacity: Option<u32>,
+    #[serde(alias = "checkIn")]
+    check_in: Option<u32>,
+}
+
+/// Changes of the series from a date onward, absent fields are left untouched
+#[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SeriesPatch {
+    /// the changes apply to the occurrences starting at or after it, the whole series if absent
+    pub from: Option<DateTime<Utc>>,
+    pub name: Option<String>,
+    pub description: Option<String>,
+    /// null removes the capacity
+    #[serde(default, deserialize_with = "nullable")]
+    pub capacity: Option<Option<u32>>,
+    /// null disables the check-in
+    #[serde(default, deserialize_with = "nullable")]
+    pub check_in: Option<Option<u32>>,
+    /// ends the series, later occurrences are cancelled. null lets the series go on.
+    #[serde(default, deserialize_with = "nullable")]
+    pub until: Option<Option<DateTime<Utc>>>,
+}
+
+impl EventSeries {
+    /// Creates the series and its occurrences within the horizon
+    #[instrument(skip_all)]
+    pub async fn create(
+        request: CreateSeriesRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        validate_schedule(&request.start, &request.end)?;
+        validate_registration(request.capacity, request.check_in)?;
+        request.rule.validate()?;
+        if !matches!(request.status, EventStatus::Draft | EventStatus::Published) {
+            return Err(ApplicationError::BadRequest(
+                "series have to be created as draft or published".to_owned(),
+            ));
+        }
+        let duration = u32::try_from((request.end - request.start).num_minutes())
+            .ok()
+            .filter(|duration| *duration > 0)
+            .ok_or(ApplicationError::BadRequest(
+                "occurrences have to last at least a minute".to_owned(),
+            ))?;
+
+        let series = sql_span!(connection
+            .query(
+                "CREATE event_series SET name = $name, description = $description, \
+                start = <datetime> $start, duration = $duration, rule = $rule, \
+                exceptions = $exceptions, status = $status, capacity = $capacity, \
+                check_in = $check_in"
+            )
+            .bind(("name", request.name))
+            .bind(("description", request.description))
+            .bind(("start", request.start.to_rfc3339()))
+            .bind(("duration", duration))
+            .bind(("rule", request.rule))
+            .bind(("exceptions", request.exceptions))
+            .bind(("status", request.status))
+            .bind(("capacity", request.capacity))
+            .bind(("check_in", request.check_in))
+            .await?
+            .take::<Option<EventSeries>>(0)?)
+        .ok_or(ApplicationError::InternalServerError)?;
+
+        series.materialize(connection).await?;
+        Ok(series)
+    }
+
+    /// Fetch a series by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let series: Option<EventSeries> = sql_span!(
+            connection
+                .select(&Id::try_from(("event_series", id))?)
+                .await?
+        );
+
+        series.ok_or(ApplicationError::BadRequest("series not found".to_owned()))
+    }
+
+    /// Select all series, ordered by their first occurrence
+    #[instrument(skip_all)]
+    pub async fn all(connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_series ORDER BY start ASC")
+            .await?
+            .take::<Vec<EventSeries>>(0)?))
+    }
+
+    /// Lists the created occurrences of the series, ordered by their start
+    #[instrument(skip_all)]
+    pub async fn events(&self, connection: &DatabaseConnection) -> Result<Vec<Event>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event WHERE series = $series ORDER BY start ASC")
+            .bind(("series", self.id.to_thing()))
+            .await?
+            .take::<Vec<Event>>(0)?))
+    }
+
+    /// Creates the missing occurrences up to the horizon, returns the created events
+    #[instrument(skip_all)]
+    pub async fn materialize(&self, connection: &DatabaseConnection) -> Result<Vec<Event>> {
+        let existing = self
+            .events(connection)
+            .await?
+            .into_iter()
+            .map(|event| *event.start())
+            .collect::<Vec<DateTime<Utc>>>();
+        let missing = self
+            .rule
+            .occurrences(
+                &self.start,
+                self.exceptions.as_slice(),
+                &Utc::now(),
+                &(Utc::now() + Duration::days(SERIES_HORIZON_DAYS)),
+            )
+            .into_iter()
+            .filter(|start| !existing.contains(start))
+            .collect::<Vec<DateTime<Utc>>>();
+        if missing.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut query = connection.query("BEGIN TRANSACTION");
+        for (index, start) in missing.iter().enumerate() {
+            query = query
+                .query(format!(
+                    "CREATE event SET name = $name, description = $description, \
+                    start = <datetime> $start{index}, end = <datetime> $end{index}, \
+                    status = $status, capacity = $capacity, check_in = $check_in, \
+                    series = $series"
+                ))
+                .bind((format!("start{index}"), start.to_rfc3339()))
+                .bind((
+                    format!("end{index}"),
+                    (*start + Duration::minutes(self.duration.into())).to_rfc3339(),
+                ));
+        }
+        let mut response = sql_span!(query
+            .query("COMMIT TRANSACTION")
+            .bind(("name", self.name.as_str()))
+            .bind(("description", self.description.as_str()))
+            .bind(("status", self.status))
+            .bind(("capacity", self.capacity))
+            .bind(("check_in", self.check_in))
+            .bind(("series", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        let mut events = vec![];
+        for index in 0..missing.len() {
+            events.extend(response.take::<Option<Event>>(index)?);
+        }
+        Ok(events)
+    }
+
+    /// Applies the changes to the series and its occurrences. Changes from a date after the
+    /// first occurrence split the series: the original ends before the date and a new series
+    /// takes over the later occurrences. Occurrences edited on their own, running or finished
+    /// ones are left untouched. Returns the series the changes were applied to.
+    #[instrument(skip_all)]
+    pub async fn patch(self, patch: &SeriesPatch, connection: &DatabaseConnection) -> Result<Self> {
+        validate_registration(
+            patch.capacity.unwrap_or(self.capacity),
+            patch.check_in.unwrap_or(self.check_in),
+        )?;
+
+        let series = match patch.from {
+            Some(from) if from > self.start => self.split(&from, connection).await?,
+            _ => self,
+        };
+        let from = patch.from.unwrap_or(series.start);
+
+        let mut changes = serde_json::Map::new();
+        if let Some(name) = &patch.name {
+            changes.insert("name".to_owned(), json!(name));
+        }
+        if let Some(description) = &patch.description {
+            changes.insert("description".to_owned(), json!(description));
+        }
+        let mut removed = vec![];
+        for (field, value) in [("capacity", patch.capacity), ("check_in", patch.check_in)] {
+            match value {
+                Some(Some(value)) => {
+                    changes.insert(field.to_owned(), json!(value));
+                }
+                Some(None) => removed.push(format!("{field} = NONE")),
+                None => {}
+            }
+        }
+        let unset = removed.join(", ");
+
+        let mut query = connection
+            .query("BEGIN TRANSACTION")
+            .query("UPDATE $series MERGE $changes");
+        if !removed.is_empty() {
+            query = query.query(format!("UPDATE $series SET {unset}"));
+        }
+        query = query.query(format!("UPDATE event MERGE $changes WHERE {OCCURRENCES}"));
+        if !removed.is_empty() {
+            query = query.query(format!("UPDATE event SET {unset} WHERE {OCCURRENCES}"));
+        }
+        let mut series = sql_span!(query
+            .query("COMMIT TRANSACTION")
+            .bind(("series", series.id.to_thing()))
+            .bind(("changes", changes))
+            .bind(("from", from.to_rfc3339()))
+            .await?
+            .check()?
+            .take::<Option<EventSeries>>(usize::from(!removed.is_empty()))?)
+        .ok_or(ApplicationError::InternalServerError)?;
+
+        if let Some(until) = patch.until {
+            series.end(until, connection).await?;
+        }
+        Ok(series)
+    }
+
+    /// Ends the series at the given time, or lets it go on without an end. Occurrences after
+    /// the end which haven't started yet are cancelled.
+    async fn end(
+        &mut self,
+        until: Option<DateTime<Utc>>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let mut rule = self.rule.clone();
+        rule.until = until;
+
+        let mut query = connection
+            .query("UPDATE $series SET rule = $rule")
+            .bind(("series", self.id.to_thing()))
+            .bind(("rule", &rule));
+        if let Some(until) = until {
+            query = query
+                .query(
+                    "UPDATE event SET status = 'cancelled' WHERE series = $series \
+                    AND start > <datetime> $until AND status INSIDE ['draft', 'published']",
+                )
+                .bind(("until", until.to_rfc3339()));
+        }
+        *self = sql_span!(query.await?.check()?.take::<Option<EventSeries>>(0)?)
+            .ok_or(ApplicationError::InternalServerError)?;
+
+        self.materialize(connection).await?;
+        Ok(())
+    }
+
+    /// Ends the series before the given date and continues its later occurrences in a new
+    /// series, which is returned
+    async fn split(self, from: &DateTime<Utc>, connection: &DatabaseConnection) -> Result<Self> {
+        let before = self.rule.expand(&self.start, from);
+        let Some(start) = self
+            .rule
+            .iter(&self.start)
+            .find(|occurrence| occurrence >= from)
+        else {
+            return Err(ApplicationError::BadRequest(
+                "the series has no occurrences after the date".to_owned(),
+            ));
+        };
+
+        let mut rule = self.rule.clone();
+        rule.count = rule.count.map(|count| count - before.len() as u32);
+        let exceptions = self
+            .exceptions
+            .iter()
+            .filter(|exception| *exception >= from)
+            .cloned()
+            .collect::<Vec<DateTime<Utc>>>();
+        let mut original = self.rule.clone();
+        original.until = Some(*from - Duration::seconds(1));
+        original.count = None;
+
+        let id = Id::new(("event_series", nanoid::nanoid!(20, &ALPHABET).as_str()));
+        let series = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query(
+                "CREATE $series SET name = $name, description = $description, \
+                start = <datetime> $start, duration = $duration, rule = $rule, \
+                exceptions = $exceptions, status = $status, capacity = $capacity, \
+                check_in = $check_in"
+            )
+            .query("UPDATE $original SET rule = $original_rule")
+            .query(
+                "UPDATE event SET series = $series \
+                WHERE series = $original AND start >= <datetime> $start",
+            )
+            .query("COMMIT TRANSACTION")
+            .bind(("name", self.name.as_str()))
+            .bind(("description", self.description.as_str()))
+            .bind(("start", start.to_rfc3339()))
+            .bind(("duration", self.duration))
+            .bind(("rule", rule))
+            .bind(("exceptions", exceptions))
+            .bind(("status", self.status))
+            .bind(("capacity", self.capacity))
+            .bind(("check_in", self.check_in))
+            .bind(("series", id.to_thing()))
+            .bind(("original", self.id.to_thing()))
+            .bind(("original_rule", original))
+            .await?
+            .check()?
+            .take::<Option<EventSeries>>(0)?);
+
+        series.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Skips the occurrence at the given start, so it isn't created again
+    #[instrument(skip(connection))]
+    pub async fn skip(
+        series: &Id,
+        start: &DateTime<Utc>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        sql_span!(connection
+            .query("UPDATE $series SET exceptions += <datetime> $start")
+            .bind(("series", series.to_thing()))
+            .bind(("start", start.to_rfc3339()))
+            .await?
+            .check()?);
+        Ok(())
+    }
+
+    /// Deletes the series. Its occurrences which haven't started yet are deleted, the others are
+    /// kept as regular events.
+    #[instrument(skip_all)]
+    pub async fn delete(self, connection: &DatabaseConnection) -> Result<()> {
+        let upcoming = sql_span!(connection
+            .query(
+                "SELECT VALUE id FROM event WHERE series = $series \
+                AND status INSIDE ['draft', 'published'] AND start > time::now()"
+            )
+            .bind(("series", self.id.to_thing()))
+            .await?
+            .take::<Vec<Id>>(0)?);
+        for event in upcoming.iter() {
+            Asset::delete_owned(event, connection).await?;
+        }
+
+        sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("DELETE event_registration WHERE event INSIDE $upcoming")
+            .query("DELETE event_team WHERE event INSIDE $upcoming")
+            .query("DELETE $upcoming")
+            .query("UPDATE event SET series = NONE, detached = false WHERE series = $series")
+            .query("DELETE $series")
+            .query("COMMIT TRANSACTION")
+            .bind(("series", self.id.to_thing()))
+            .bind((
+                "upcoming",
+                upcoming.iter().map(Id::to_thing).collect::<Vec<_>>()
+            ))
+            .await?
+            .check()?);
+        Ok(())
+    }
+}
+
+/// Creates the occurrences of all series as the horizon moves on
+pub async fn materialize_series(state: ApplicationState) {
+    let mut interval = tokio::time::interval(SERIES_INTERVAL);
+    loop {
+        interval.tick().await;
+
+        let series = match EventSeries::all(state.connection()).await {
+            Ok(series) => series,
+            Err(error) => {
+                warn!("failed to select the event series: {error}");
+                continue;
+            }
+        };
+        for series in series {
+            if let Err(error) = series.materialize(state.connection()).await {
+                warn!(
+                    "failed to create the occurrences of {}: {error}",
+                    series.id.id
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(frequency: Frequency, count: Option<u32>) -> RecurrenceRule {
+        RecurrenceRule {
+            frequency,
+            interval: 1,
+            count,
+            until: None,
+        }
+    }
+
+    #[test]
+    fn test_occurrences() {
+        let start = Utc.with_ymd_and_hms(2023, 1, 31, 18, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        let weekly = rule(Frequency::Weekly, Some(4));
+        let exceptions = [start + Duration::weeks(1)];
+        assert_eq!(
+            vec![
+                start,
+                start + Duration::weeks(2),
+                start + Duration::weeks(3)
+            ],
+            weekly.occurrences(&start, &exceptions, &start, &to)
+        );
+        assert_eq!(
+            vec![start + Duration::weeks(3)],
+            weekly.occurrences(&start, &exceptions, &(start + Duration::days(15)), &to)
+        );
+
+        // months without the 31st are skipped
+        let mut monthly = rule(Frequency::Monthly, Some(3));
+        assert_eq!(
+            vec![
+                start,
+                Utc.with_ymd_and_hms(2023, 3, 31, 18, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 5, 31, 18, 0, 0).unwrap()
+            ],
+            monthly.occurrences(&start, &[], &start, &to)
+        );
+        monthly.count = None;
+        monthly.interval = 12;
+        assert_eq!(vec![start], monthly.occurrences(&start, &[], &start, &to));
+
+        let mut daily = rule(Frequency::Daily, None);
+        daily.interval = 2;
+        daily.until = Some(start + Duration::days(4));
+        assert_eq!(3, daily.occurrences(&start, &[], &start, &to).len());
+        assert!(daily.validate().is_ok());
+        daily.interval = 0;
+        assert!(daily.validate().is_err());
+        daily.interval = MAX_INTERVAL + 1;
+        assert!(daily.validate().is_err());
+
+        // candidates out of range are left out instead of overflowing
+        let mut weekly = rule(Frequency::Weekly, None);
+        weekly.interval = MAX_INTERVAL;
+        let late = Utc.with_ymd_and_hms(262_000, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(8, weekly.expand(&late, &DateTime::<Utc>::MAX_UTC).len());
+    }
+}
@@ -24,9 +24,15 @@
  *
  */
 
+use crate::data::clan::{Clan, ClanRole};
+use crate::data::event::registration::RegistrationStatus;
+use crate::data::event::Event;
 use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use std::collections::HashSet;
 
-#[derive(Deserialize, Serialize, Debug, Clone, Getters, JsonSchema)]
+#[derive(Deserialize, Serialize, Debug, Clone, Getters, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
 #[get = "pub"]
 pub struct EventTeam {
     id: Id,
@@ -38,4 +44,188 @@ pub struct EventTeam {
     event: Id,
     /// all the members of the team (uuid)
     members: Vec<String>,
+    /// the clan which formed the team
+    clan: Option<Id>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// A selected clan member with their linked minecraft account
+#[derive(Deserialize)]
+struct RosterAccount {
+    id: Id,
+    uuid: Option<String>,
+}
+
+impl Event {
+    /// Joins the event as a team of the clan on behalf of an officer. The team is formed by
+    /// the selected members, who all need a linked account, and they are registered for the
+    /// event unless they already are. Members beyond the capacity are waitlisted.
+    #[instrument(skip_all)]
+    pub async fn join_as_clan(
+        &self,
+        clan: &Clan,
+        actor: &Id,
+        roster: &[String],
+        connection: &DatabaseConnection,
+    ) -> Result<EventTeam> {
+        self.accepts_registrations()?;
+        clan.authorize(actor, ClanRole::Officer, connection).await?;
+
+        let members = clan
+            .members(connection)
+            .await?
+            .into_iter()
+            .map(|member| member.account().clone())
+            .collect::<HashSet<Id>>();
+        let mut selected = vec![];
+        for account in roster {
+            let account = Id::try_from(("account", account.as_str()))?;
+            if !members.contains(&account) {
+                return Err(ApplicationError::BadRequest(format!(
+                    "{} isn't a member of the clan",
+                    account.to_string()
+                )));
+            }
+            if !selected.contains(&account) {
+                selected.push(account);
+            }
+        }
+        if selected.is_empty() {
+            return Err(ApplicationError::BadRequest(
+                "the roster can't be empty".to_owned(),
+            ));
+        }
+
+        let accounts = sql_span!(connection
+            .query("SELECT id, uuid FROM account WHERE id INSIDE $accounts")
+            .bind((
+                "accounts",
+                selected.iter().map(Id::to_thing).collect::<Vec<_>>()
+            ))
+            .await?
+            .take::<Vec<RosterAccount>>(0)?);
+        let uuids = accounts
+            .iter()
+            .map(|account| {
+                account
+                    .uuid
+                    .as_ref()
+                    .map(|uuid| uuid.to_lowercase())
+                    .ok_or_else(|| {
+                        ApplicationError::BadRequest(format!(
+                            "{} has no linked minecraft account",
+                            account.id.to_string()
+                        ))
+                    })
+            })
+            .collect::<Result<Vec<String>>>()?;
+
+        let teams = self.teams(connection).await?;
+        if teams
+            .iter()
+            .any(|team| team.clan.as_ref() == Some(clan.id()))
+        {
+            return Err(ApplicationError::Conflict(
+                "the clan already joined the event".to_owned(),
+            ));
+        }
+        if teams
+            .iter()
+            .flat_map(|team| team.members.iter())
+            .any(|member| uuids.contains(member))
+        {
+            return Err(ApplicationError::Conflict(
+                "players of the roster already play for another team".to_owned(),
+            ));
+        }
+
+        let registered = self
+            .registrations(connection)
+            .await?
+            .into_iter()
+            .map(|registration| registration.account().clone())
+            .collect::<HashSet<Id>>();
+        // the new registrations are waitlisted as soon as the event is full
+        let mut free = self.free_places(connection).await?;
+        let mut query = connection.query("BEGIN TRANSACTION").query(
+            "CREATE event_team SET name = $name, token = $token, event = $event, \
+            members = $members, clan = $clan",
+        );
+        for (index, account) in selected
+            .iter()
+            .filter(|account| !registered.contains(account))
+            .enumerate()
+        {
+            let status = match free {
+                Some(0) => RegistrationStatus::Waitlisted,
+                _ => RegistrationStatus::Registered,
+            };
+            free = free.map(|free| free.saturating_sub(1));
+            query = query
+                .query(format!(
+                    "CREATE event_registration SET event = $event, account = $account{index}, \
+                    status = $status{index}"
+                ))
+                .bind((format!("account{index}"), account.to_thing()))
+                .bind((format!("status{index}"), status));
+        }
+        let team = sql_span!(query
+            .query("COMMIT TRANSACTION")
+            .bind(("name", clan.name()))
+            .bind(("token", clan.tag()))
+            .bind(("event", self.id.to_thing()))
+            .bind(("members", uuids))
+            .bind(("clan", clan.id().to_thing()))
+            .await?
+            .check()?
+            .take::<Option<EventTeam>>(0)?);
+
+        team.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Lists the teams of the event in the order they joined
+    #[instrument(skip_all)]
+    pub async fn teams(&self, connection: &DatabaseConnection) -> Result<Vec<EventTeam>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM event_team WHERE event = $event ORDER BY created_at ASC")
+            .bind(("event", self.id.to_thing()))
+            .await?
+            .take::<Vec<EventTeam>>(0)?))
+    }
+
+    /// Withdraws the team from the event on behalf of an officer of its clan. The registrations
+    /// of the members are kept.
+    #[instrument(skip_all)]
+    pub async fn withdraw_team(
+        &self,
+        team_id: &str,
+        actor: &Id,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let team: Option<EventTeam> = sql_span!(
+            connection
+                .select(&Id::try_from(("event_team", team_id))?)
+                .await?
+        );
+        let team = team
+            .filter(|team| team.event == self.id)
+            .ok_or(ApplicationError::BadRequest("team not found".to_owned()))?;
+        let Some(clan) = &team.clan else {
+            return Err(ApplicationError::Forbidden(
+                "the team wasn't formed by a clan".to_owned(),
+            ));
+        };
+        Clan::from_id(clan.to_string().as_str(), connection)
+            .await?
+            .authorize(actor, ClanRole::Officer, connection)
+            .await?;
+
+        sql_span!(connection
+            .query("DELETE $team")
+            .bind(("team", team.id.to_thing()))
+            .await?
+            .check()?);
+        Ok(())
+    }
 }
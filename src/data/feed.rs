@@ -0,0 +1,252 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::prelude::*;
+use atom_syndication::{Content, Entry, Link, Text};
+use chrono::{DateTime, Utc};
+use rss::{Channel, Guid, Item};
+
+/// the maximum amount of entries per feed
+pub const FEED_LIMIT: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    pub fn mime(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "rss",
+            FeedFormat::Atom => "atom",
+        }
+    }
+}
+
+/// A syndication feed, which can be rendered as RSS or Atom
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub description: String,
+    /// the absolute link of the page the feed belongs to
+    pub link: String,
+    /// the absolute link of the feed itself, without the format extension
+    pub location: String,
+    pub language: Option<String>,
+    pub entries: Vec<FeedEntry>,
+}
+
+/// An entry of a feed
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    /// the absolute link of the entry, which also identifies it
+    pub link: String,
+    pub title: String,
+    /// the html content
+    pub content: Option<String>,
+    pub published: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// Builds the absolute link of a page of the frontend
+pub fn page_link(path: &str) -> String {
+    format!("{}{path}", *ROOT)
+}
+
+/// Builds the absolute link of a resource of the api
+pub fn api_link(path: &str) -> String {
+    format!("{}{path}", *API_ROOT)
+}
+
+impl Feed {
+    fn updated(&self) -> DateTime<Utc> {
+        self.entries
+            .iter()
+            .map(|entry| entry.updated)
+            .max()
+            .unwrap_or_else(Utc::now)
+    }
+
+    pub fn render(&self, format: FeedFormat) -> String {
+        match format {
+            FeedFormat::Rss => self.rss(),
+            FeedFormat::Atom => self.atom(),
+        }
+    }
+
+    /// Renders the feed as RSS 2.0 channel
+    pub fn rss(&self) -> String {
+        let items = self
+            .entries
+            .iter()
+            .map(|entry| Item {
+                title: Some(entry.title.clone()),
+                link: Some(entry.link.clone()),
+                guid: Some(Guid {
+                    value: entry.link.clone(),
+                    permalink: true,
+                }),
+                description: entry.content.clone(),
+                pub_date: Some(entry.published.to_rfc2822()),
+                ..Default::default()
+            })
+            .collect();
+
+        Channel {
+            title: self.title.clone(),
+            link: self.link.clone(),
+            description: self.description.clone(),
+            language: self.language.clone(),
+            last_build_date: Some(self.updated().to_rfc2822()),
+            atom_ext: Some(rss::extension::atom::AtomExtension {
+                links: vec![rss::extension::atom::Link {
+                    href: format!("{}.{}", self.location, FeedFormat::Rss.extension()),
+                    rel: "self".to_owned(),
+                    mime_type: Some("application/rss+xml".to_owned()),
+                    ..Default::default()
+                }],
+            }),
+            items,
+            ..Default::default()
+        }
+        .to_string()
+    }
+
+    /// Renders the feed as Atom feed
+    pub fn atom(&self) -> String {
+        let entries = self
+            .entries
+            .iter()
+            .map(|entry| Entry {
+                title: Text::plain(entry.title.as_str()),
+                id: entry.link.clone(),
+                updated: entry.updated.into(),
+                published: Some(entry.published.into()),
+                links: vec![Link {
+                    href: entry.link.clone(),
+                    rel: "alternate".to_owned(),
+                    ..Default::default()
+                }],
+                content: entry.content.as_ref().map(|content| Content {
+                    value: Some(content.clone()),
+                    content_type: Some("html".to_owned()),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })
+            .collect();
+
+        atom_syndication::Feed {
+            title: Text::plain(self.title.as_str()),
+            subtitle: Some(Text::plain(self.description.as_str())),
+            id: format!("{}.{}", self.location, FeedFormat::Atom.extension()),
+            updated: self.updated().into(),
+            links: vec![
+                Link {
+                    href: self.link.clone(),
+                    rel: "alternate".to_owned(),
+                    ..Default::default()
+                },
+                Link {
+                    href: format!("{}.{}", self.location, FeedFormat::Atom.extension()),
+                    rel: "self".to_owned(),
+                    mime_type: Some("application/atom+xml".to_owned()),
+                    ..Default::default()
+                },
+            ],
+            lang: self.language.clone(),
+            entries,
+            ..Default::default()
+        }
+        .to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::feed::{Feed, FeedEntry, FeedFormat};
+    use chrono::{TimeZone, Utc};
+
+    fn feed() -> Feed {
+        let published = Utc.with_ymd_and_hms(2023, 4, 1, 12, 0, 0).unwrap();
+
+        Feed {
+            title: "title".to_owned(),
+            description: "description".to_owned(),
+            link: "https://example.com/".to_owned(),
+            location: "https://example.com/api/news/feed".to_owned(),
+            language: Some("en".to_owned()),
+            entries: vec![FeedEntry {
+                link: "https://example.com/news/1".to_owned(),
+                title: "first & foremost".to_owned(),
+                content: Some("<p>content</p>".to_owned()),
+                published,
+                updated: published,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_rss() {
+        let rss = feed().render(FeedFormat::Rss);
+
+        let channel = rss::Channel::read_from(rss.as_bytes()).unwrap();
+        assert_eq!("en", channel.language().unwrap());
+        let item = &channel.items()[0];
+        assert_eq!("first & foremost", item.title().unwrap());
+        assert_eq!("https://example.com/news/1", item.link().unwrap());
+        assert_eq!("<p>content</p>", item.description().unwrap());
+        assert_eq!("Sat, 1 Apr 2023 12:00:00 +0000", item.pub_date().unwrap());
+        assert_eq!(
+            "https://example.com/api/news/feed.rss",
+            channel.atom_ext().unwrap().links()[0].href()
+        );
+    }
+
+    #[test]
+    fn test_atom() {
+        let atom = feed().render(FeedFormat::Atom);
+
+        let feed = atom_syndication::Feed::read_from(atom.as_bytes()).unwrap();
+        assert_eq!("https://example.com/api/news/feed.atom", feed.id());
+        assert_eq!("2023-04-01T12:00:00+00:00", feed.updated().to_rfc3339());
+        let entry = &feed.entries()[0];
+        assert_eq!("https://example.com/news/1", entry.id());
+        assert_eq!("first & foremost", entry.title().as_str());
+        assert_eq!(
+            Some("<p>content</p>"),
+            entry.content().and_then(|content| content.value())
+        );
+    }
+}
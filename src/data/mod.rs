@@ -25,6 +25,15 @@
  */
 
 pub mod account;
+pub mod asset;
+pub mod audit;
+pub mod clan;
 pub mod event;
+pub mod feed;
 pub mod news;
+pub mod notification;
+pub mod patch;
+pub mod player;
+pub mod rating;
 pub mod schematic;
+pub mod season;
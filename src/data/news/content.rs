@@ -0,0 +1,141 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use pulldown_cmark::{html, Options, Parser};
+
+/// The format news are authored in. Either way the content is delivered as sanitised html.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentFormat {
+    /// the content is authored as html directly
+    #[default]
+    Html,
+    /// the content is rendered from a markdown source (CommonMark with tables)
+    Markdown,
+}
+
+impl ContentFormat {
+    /// Renders the given source to sanitised html
+    pub fn render(&self, source: &str) -> String {
+        match self {
+            ContentFormat::Html => ammonia::clean(source),
+            ContentFormat::Markdown => {
+                let parser = Parser::new_ext(source, Options::ENABLE_TABLES);
+                let mut rendered = String::with_capacity(source.len() * 3 / 2);
+                html::push_html(&mut rendered, parser);
+
+                ammonia::clean(rendered.as_str())
+            }
+        }
+    }
+}
+
+/// The authored content of news
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, JsonSchema)]
+pub struct NewsContent {
+    #[serde(default)]
+    pub format: ContentFormat,
+    /// the markdown source, only present for markdown content
+    pub source: Option<String>,
+    /// the sanitised html content, rendered from the source for markdown
+    pub content: Option<String>,
+}
+
+impl NewsContent {
+    /// Renders the content from its source. Html is authored in the content itself, so only
+    /// markdown keeps a separate source.
+    pub fn render(&self) -> Self {
+        let (source, content) = match self.format {
+            ContentFormat::Html => (None, self.content.as_deref()),
+            ContentFormat::Markdown => (self.source.clone(), self.source.as_deref()),
+        };
+
+        Self {
+            format: self.format,
+            content: content.map(|content| self.format.render(content)),
+            source,
+        }
+    }
+
+    /// The text authors work on, used to compare revisions
+    pub fn authored(&self) -> &str {
+        self.source
+            .as_deref()
+            .or(self.content.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::news::content::{ContentFormat, NewsContent};
+
+    #[test]
+    fn test_render() {
+        assert_eq!(
+            "<p><strong>bold</strong> text</p>\n",
+            ContentFormat::Markdown.render("**bold** text")
+        );
+        assert!(ContentFormat::Markdown
+            .render("| a | b |\n|---|---|\n| 1 | 2 |")
+            .contains("<td>1</td>"));
+        // raw html within markdown is sanitised as well
+        assert_eq!(
+            "<p>text</p>\n",
+            ContentFormat::Markdown.render("<p>text<script>alert(1)</script></p>\n")
+        );
+        assert_eq!(
+            "<p><strong>nice</strong> content </p>\n",
+            ContentFormat::Markdown.render("**nice** content <script>alert(1)</script>")
+        );
+        assert_eq!(
+            "<a href=\"https://example.com\" rel=\"noopener noreferrer\">link</a>",
+            ContentFormat::Html.render("<a href=\"https://example.com\" onclick=\"x()\">link</a>")
+        );
+    }
+
+    #[test]
+    fn test_content() {
+        let html = NewsContent {
+            format: ContentFormat::Html,
+            source: Some("ignored".to_owned()),
+            content: Some("<p>text</p>".to_owned()),
+        }
+        .render();
+        assert_eq!(None, html.source);
+        assert_eq!(Some("<p>text</p>".to_owned()), html.content);
+
+        let markdown = NewsContent {
+            format: ContentFormat::Markdown,
+            source: Some("# title".to_owned()),
+            content: Some("ignored".to_owned()),
+        }
+        .render();
+        assert_eq!(Some("# title".to_owned()), markdown.source);
+        assert_eq!(Some("<h1>title</h1>\n".to_owned()), markdown.content);
+        assert_eq!("# title", markdown.authored());
+    }
+}
@@ -0,0 +1,488 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::asset::image::ImageVariants;
+use crate::data::asset::Asset;
+use crate::data::feed::{api_link, page_link, FeedEntry};
+use crate::data::news::content::{ContentFormat, NewsContent};
+use crate::data::news::revision::{NewsDiff, NewsRevision};
+use crate::data::patch::{check_id, nullable};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+pub mod content;
+pub mod revision;
+pub mod translation;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema, Getters, Setters)]
+#[get = "pub"]
+#[serde(rename_all = "camelCase")]
+pub struct News {
+    id: Id,
+    title: String,
+    /// the language of the title and content
+    #[serde(default = "default_language")]
+    language: String,
+    /// the content (html or markdown -> rendered and cleaned up server side)
+    #[serde(flatten)]
+    body: NewsContent,
+    shown: bool,
+    #[serde(flatten)]
+    schedule: NewsSchedule,
+    /// the variants of the uploaded image
+    #[serde(default)]
+    images: Option<ImageVariants>,
+    /// the current revision, which has to be sent along with updates
+    #[serde(default = "default_revision")]
+    revision: u32,
+    /// the author of the current revision
+    author: Option<Id>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+    #[serde(alias = "updated_at")]
+    updated_at: Option<DateTime<Utc>>,
+}
+
+fn default_revision() -> u32 {
+    1
+}
+
+fn default_language() -> String {
+    DEFAULT_LANGUAGE.clone()
+}
+
+/// The publishing schedule and placement of news
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsSchedule {
+    /// the time the news get public at, immediately if absent
+    #[serde(alias = "publish_at")]
+    pub publish_at: Option<DateTime<Utc>>,
+    /// the time the news get hidden again at, never if absent
+    #[serde(alias = "unpublish_at")]
+    pub unpublish_at: Option<DateTime<Utc>>,
+    /// pinned news are listed before all others
+    #[serde(default)]
+    pub pinned: bool,
+    /// news with a higher priority are listed first, before sorting newest-first
+    #[serde(default)]
+    pub priority: i64,
+}
+
+impl NewsSchedule {
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(publish_at), Some(unpublish_at)) = (self.publish_at, self.unpublish_at) {
+            if unpublish_at <= publish_at {
+                return Err(ApplicationError::BadRequest(
+                    "unpublishAt has to be after publishAt".to_owned(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A partial update of news, absent fields are left untouched
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsPatch {
+    /// has to match the updated news if given
+    pub id: Option<Id>,
+    /// the revision the changes are based on, has to be the current one
+    pub revision: u32,
+    pub title: Option<String>,
+    pub format: Option<ContentFormat>,
+    pub source: Option<String>,
+    pub content: Option<String>,
+    pub shown: Option<bool>,
+    /// `null` publishes the news immediately
+    #[serde(default, deserialize_with = "nullable")]
+    pub publish_at: Option<Option<DateTime<Utc>>>,
+    /// `null` never hides the news again
+    #[serde(default, deserialize_with = "nullable")]
+    pub unpublish_at: Option<Option<DateTime<Utc>>>,
+    pub pinned: Option<bool>,
+    pub priority: Option<i64>,
+}
+
+/// Builds the query selecting the matching news in their listed order
+fn ordered(filter: &str) -> String {
+    format!(
+        "SELECT *, publish_at OR created_at AS published FROM news {filter} \
+        ORDER BY pinned DESC, priority DESC, published DESC"
+    )
+}
+
+impl News {
+    #[instrument(skip_all)]
+    pub async fn new(
+        title: &str,
+        body: &NewsContent,
+        shown: bool,
+        schedule: &NewsSchedule,
+        author: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        schedule.validate()?;
+        // render and cleanup the content
+        let body = body.render();
+
+        // save into the database
+        let news = sql_span!(connection
+            .query(
+                "CREATE news SET format = $format, source = $source, content = $content, \
+                title = $title, shown = $shown, publish_at = $publish_at, \
+                unpublish_at = $unpublish_at, pinned = $pinned, priority = $priority, \
+                author = $author"
+            )
+            .bind(("format", body.format))
+            .bind(("source", body.source))
+            .bind(("content", body.content))
+            .bind(("title", title))
+            .bind(("shown", shown))
+            .bind(("publish_at", schedule.publish_at))
+            .bind(("unpublish_at", schedule.unpublish_at))
+            .bind(("pinned", schedule.pinned))
+            .bind(("priority", schedule.priority))
+            .bind(("author", author.map(Id::to_thing)))
+            .await?
+            .take::<Option<News>>(0)?);
+
+        news.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch news by their id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let news: Option<News> = sql_span!(connection.select(&Id::try_from(("news", id))?).await?);
+
+        news.ok_or(ApplicationError::BadRequest("news not found".to_owned()))
+    }
+
+    /// Applies the editable fields of the given news as new revision. Fails if the given news
+    /// are not based on the current revision, so concurrent edits can't overwrite each other.
+    /// The id and creation time are owned by the server and never changed.
+    #[instrument(skip_all)]
+    pub async fn update(
+        &mut self,
+        changes: &News,
+        author: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        check_id(&self.id, Some(&changes.id))?;
+        if changes.revision != self.revision {
+            return Err(ApplicationError::Conflict(format!(
+                "the news have been changed in the meantime, the current revision is {}",
+                self.revision
+            )));
+        }
+        if changes.language != *DEFAULT_LANGUAGE {
+            return Err(ApplicationError::BadRequest(
+                "translations are edited separately from the news".to_owned(),
+            ));
+        }
+        changes.schedule.validate()?;
+        // render and cleanup the content
+        let body = changes.body.render();
+
+        // archive the current revision, the unique index rejects concurrent updates
+        let news = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query(
+                "CREATE news_revision SET news = $news, revision = $revision, title = $title, \
+                format = $format, source = $source, content = $content, author = $author, created_at = $created_at"
+            )
+            .bind(("news", self.id.to_thing()))
+            .bind(("revision", self.revision))
+            .bind(("title", self.title.as_str()))
+            .bind(("format", self.body.format))
+            .bind(("source", self.body.source.as_deref()))
+            .bind(("content", self.body.content.as_deref()))
+            .bind(("author", self.author.as_ref().map(Id::to_thing)))
+            .bind(("created_at", self.updated_at.unwrap_or(self.created_at)))
+            .query("UPDATE $news SET author = $editor WHERE revision = $revision")
+            .bind(("editor", author.map(Id::to_thing)))
+            .query("UPDATE $news MERGE $changes WHERE revision = $revision")
+            .bind((
+                "changes",
+                serde_json::json!({
+                    "title": changes.title,
+                    "format": body.format,
+                    "source": body.source,
+                    "content": body.content,
+                    "shown": changes.shown,
+                    "publish_at": changes.schedule.publish_at,
+                    "unpublish_at": changes.schedule.unpublish_at,
+                    "pinned": changes.schedule.pinned,
+                    "priority": changes.schedule.priority,
+                    "revision": self.revision + 1,
+                    "updated_at": Utc::now()
+                }),
+            ))
+            .query("COMMIT TRANSACTION")
+            .await
+            .and_then(|response| response.check())
+            .and_then(|mut response| response.take::<Option<News>>(2)));
+        let news = match news {
+            Ok(news) => news,
+            // the revision was archived by a concurrent update in the meantime
+            Err(error) => {
+                return match self.is_archived(self.revision, connection).await? {
+                    true => Err(ApplicationError::Conflict(
+                        "the news have been changed in the meantime".to_owned(),
+                    )),
+                    false => Err(error.into()),
+                };
+            }
+        };
+
+        *self = news.ok_or(ApplicationError::Conflict(
+            "the news have been changed in the meantime".to_owned(),
+        ))?;
+        Ok(())
+    }
+
+    /// Whether the given revision has already been archived
+    async fn is_archived(&self, revision: u32, connection: &DatabaseConnection) -> Result<bool> {
+        let archived = sql_span!(connection
+            .query("SELECT VALUE id FROM news_revision WHERE news = $news AND revision = $revision")
+            .bind(("news", self.id.to_thing()))
+            .bind(("revision", revision))
+            .await?
+            .take::<Option<Id>>(0)?);
+
+        Ok(archived.is_some())
+    }
+
+    /// Applies the given partial changes as new revision, see [News::update]
+    #[instrument(skip_all)]
+    pub async fn patch(
+        &mut self,
+        patch: &NewsPatch,
+        author: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        check_id(&self.id, patch.id.as_ref())?;
+
+        let mut changes = self.clone();
+        changes.revision = patch.revision;
+        if let Some(title) = &patch.title {
+            changes.title = title.clone();
+        }
+        changes.body = NewsContent {
+            format: patch.format.unwrap_or(self.body.format),
+            source: patch.source.clone().or(changes.body.source),
+            content: patch.content.clone().or(changes.body.content),
+        };
+        if let Some(shown) = patch.shown {
+            changes.shown = shown;
+        }
+        if let Some(publish_at) = patch.publish_at {
+            changes.schedule.publish_at = publish_at;
+        }
+        if let Some(unpublish_at) = patch.unpublish_at {
+            changes.schedule.unpublish_at = unpublish_at;
+        }
+        if let Some(pinned) = patch.pinned {
+            changes.schedule.pinned = pinned;
+        }
+        if let Some(priority) = patch.priority {
+            changes.schedule.priority = priority;
+        }
+
+        self.update(&changes, author, connection).await
+    }
+
+    /// Lists all revisions including the current one, newest first
+    #[instrument(skip_all)]
+    pub async fn revisions(&self, connection: &DatabaseConnection) -> Result<Vec<NewsRevision>> {
+        let mut revisions = vec![self.current_revision()];
+        revisions.append(&mut sql_span!(connection
+            .query(
+                "SELECT revision, title, format, source, content, author, created_at \
+                FROM news_revision WHERE news = $news ORDER BY revision DESC"
+            )
+            .bind(("news", self.id.to_thing()))
+            .await?
+            .take::<Vec<NewsRevision>>(0)?));
+
+        Ok(revisions)
+    }
+
+    fn current_revision(&self) -> NewsRevision {
+        NewsRevision {
+            revision: self.revision,
+            title: self.title.clone(),
+            body: self.body.clone(),
+            author: self.author.clone(),
+            current: true,
+            created_at: self.updated_at.unwrap_or(self.created_at),
+        }
+    }
+
+    /// Fetch the given revision, which may be the current one
+    #[instrument(skip(self, connection))]
+    pub async fn find_revision(
+        &self,
+        revision: u32,
+        connection: &DatabaseConnection,
+    ) -> Result<NewsRevision> {
+        if revision == self.revision {
+            return Ok(self.current_revision());
+        }
+
+        sql_span!(connection
+            .query(
+                "SELECT revision, title, format, source, content, author, created_at \
+                FROM news_revision WHERE news = $news AND revision = $revision"
+            )
+            .bind(("news", self.id.to_thing()))
+            .bind(("revision", revision))
+            .await?
+            .take::<Option<NewsRevision>>(0)?)
+        .ok_or(ApplicationError::BadRequest(
+            "revision not found".to_owned(),
+        ))
+    }
+
+    /// Compares the wording of two revisions
+    pub async fn diff(
+        &self,
+        from: u32,
+        to: u32,
+        connection: &DatabaseConnection,
+    ) -> Result<NewsDiff> {
+        Ok(NewsDiff::new(
+            &self.find_revision(from, connection).await?,
+            &self.find_revision(to, connection).await?,
+        ))
+    }
+
+    /// Restores the wording of the given revision by saving it as new revision
+    #[instrument(skip(self, author, connection))]
+    pub async fn restore(
+        &mut self,
+        revision: u32,
+        author: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        if revision == self.revision {
+            return Err(ApplicationError::BadRequest(
+                "revision is already the current revision".to_owned(),
+            ));
+        }
+
+        let restored = self.find_revision(revision, connection).await?;
+        let mut changes = self.clone();
+        changes.title = restored.title;
+        changes.body = restored.body;
+
+        self.update(&changes, author, connection).await
+    }
+
+    /// Deletes the news including their revisions, translations and assets
+    #[instrument(skip_all)]
+    pub async fn delete(self, connection: &DatabaseConnection) -> Result<()> {
+        Asset::delete_owned(&self.id, connection).await?;
+        sql_span!(connection
+            .query("DELETE news_revision WHERE news = $news")
+            .query("DELETE news_translation WHERE news = $news")
+            .query("DELETE $news")
+            .bind(("news", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+
+    /// Select a page of all news, pinned and newest first
+    #[instrument(skip(connection))]
+    pub async fn page(
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<Self>> {
+        request
+            .execute::<News, &str>(ordered("").as_str(), None, connection)
+            .await
+    }
+
+    /// Whether the news are shown and within their publishing schedule
+    pub fn is_published(&self) -> bool {
+        let now = Utc::now();
+        self.shown
+            && self
+                .schedule
+                .publish_at
+                .is_none_or(|publish_at| publish_at <= now)
+            && self
+                .schedule
+                .unpublish_at
+                .is_none_or(|unpublish_at| unpublish_at > now)
+    }
+
+    /// Select a page of the shown news within their publishing schedule, pinned and newest first
+    #[instrument(skip(connection))]
+    pub async fn published(
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<Self>> {
+        let query = ordered(
+            "WHERE shown = true AND (publish_at IS NONE OR publish_at <= time::now()) \
+            AND (unpublish_at IS NONE OR unpublish_at > time::now())",
+        );
+
+        request
+            .execute::<News, &str>(query.as_str(), None, connection)
+            .await
+    }
+}
+
+impl From<&News> for FeedEntry {
+    fn from(news: &News) -> Self {
+        let published = news.schedule.publish_at.unwrap_or(news.created_at);
+        // feed readers can't resolve relative links
+        let image = news.images.as_ref().map(|images| {
+            format!(
+                "<p><img src=\"{}\" width=\"{}\" height=\"{}\" alt=\"\"></p>",
+                api_link(images.card.jpeg.as_str()),
+                images.card.width,
+                images.card.height
+            )
+        });
+
+        Self {
+            link: page_link(format!("/news/{}", news.id.id).as_str()),
+            title: news.title.clone(),
+            content: match (image, news.body.content.as_deref()) {
+                (Some(image), content) => Some(image + content.unwrap_or_default()),
+                (None, content) => content.map(str::to_owned),
+            },
+            published,
+            updated: news.updated_at.unwrap_or(published).max(published),
+        }
+    }
+}
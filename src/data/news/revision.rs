@@ -0,0 +1,138 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::news::content::NewsContent;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use similar::{ChangeTag, TextDiff};
+
+/// A revision of the wording of news. Previous revisions are archived on every update, the
+/// current one is stored on the news itself.
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsRevision {
+    pub revision: u32,
+    pub title: String,
+    #[serde(flatten)]
+    pub body: NewsContent,
+    /// the account which authored the revision, absent for machine clients
+    pub author: Option<Id>,
+    /// whether this is the current revision of the news
+    #[serde(default)]
+    pub current: bool,
+    #[serde(alias = "created_at")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffTag {
+    Equal,
+    Insert,
+    Delete,
+}
+
+/// A section of text which is either unchanged, inserted or deleted
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct DiffChange {
+    pub tag: DiffTag,
+    pub value: String,
+}
+
+/// The word-wise difference between two revisions
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct NewsDiff {
+    pub from: u32,
+    pub to: u32,
+    pub title: Vec<DiffChange>,
+    pub content: Vec<DiffChange>,
+}
+
+impl NewsDiff {
+    pub fn new(from: &NewsRevision, to: &NewsRevision) -> Self {
+        Self {
+            from: from.revision,
+            to: to.revision,
+            title: diff(from.title.as_str(), to.title.as_str()),
+            content: diff(from.body.authored(), to.body.authored()),
+        }
+    }
+}
+
+/// Diffs the given texts word-wise and merges consecutive changes of the same kind
+fn diff(old: &str, new: &str) -> Vec<DiffChange> {
+    let mut changes: Vec<DiffChange> = Vec::new();
+
+    for change in TextDiff::from_words(old, new).iter_all_changes() {
+        let tag = match change.tag() {
+            ChangeTag::Equal => DiffTag::Equal,
+            ChangeTag::Insert => DiffTag::Insert,
+            ChangeTag::Delete => DiffTag::Delete,
+        };
+
+        match changes.last_mut() {
+            Some(last) if last.tag == tag => last.value.push_str(change.value()),
+            _ => changes.push(DiffChange {
+                tag,
+                value: change.value().to_owned(),
+            }),
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::news::revision::{diff, DiffChange, DiffTag};
+
+    #[test]
+    fn test_diff() {
+        let changes = diff("the old event", "the new big event");
+
+        assert_eq!(
+            vec![
+                DiffChange {
+                    tag: DiffTag::Equal,
+                    value: "the ".to_owned()
+                },
+                DiffChange {
+                    tag: DiffTag::Delete,
+                    value: "old".to_owned()
+                },
+                DiffChange {
+                    tag: DiffTag::Insert,
+                    value: "new big".to_owned()
+                },
+                DiffChange {
+                    tag: DiffTag::Equal,
+                    value: " event".to_owned()
+                },
+            ],
+            changes
+        );
+    }
+}
@@ -0,0 +1,245 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::news::content::NewsContent;
+use crate::data::news::News;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use surrealdb::sql::Thing;
+
+/// The title and content of news in another than the default language
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsTranslation {
+    pub news: Id,
+    pub language: String,
+    pub title: String,
+    #[serde(flatten)]
+    pub body: NewsContent,
+    #[serde(alias = "updated_at")]
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The existing and missing translations of news
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct NewsTranslations {
+    pub translations: Vec<NewsTranslation>,
+    /// the languages the news have not been translated to yet
+    pub missing: Vec<String>,
+}
+
+/// News which are not translated to all languages yet
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+pub struct MissingTranslations {
+    pub id: Id,
+    pub title: String,
+    #[serde(default)]
+    pub missing: Vec<String>,
+}
+
+/// The languages besides the default language missing in the given translated languages
+pub fn missing<'a>(translated: impl Iterator<Item = &'a str> + Clone) -> Vec<String> {
+    LANGUAGES
+        .iter()
+        .filter(|language| **language != *DEFAULT_LANGUAGE)
+        .filter(|language| !translated.clone().any(|translated| translated == *language))
+        .cloned()
+        .collect()
+}
+
+/// Fails if news can't be translated to the given language
+pub fn validate_language(language: &str) -> Result<()> {
+    if language == DEFAULT_LANGUAGE.as_str() {
+        return Err(ApplicationError::BadRequest(
+            "the default language is edited on the news itself".to_owned(),
+        ));
+    }
+    if !LANGUAGES.iter().any(|supported| supported == language) {
+        return Err(ApplicationError::BadRequest(format!(
+            "unsupported language {language}"
+        )));
+    }
+
+    Ok(())
+}
+
+/// The language news have been translated to
+#[derive(Deserialize)]
+struct TranslatedLanguage {
+    news: Id,
+    language: String,
+}
+
+impl News {
+    /// Replaces the title and content of the given news with their translation to the given
+    /// language. News without a translation keep the default language.
+    #[instrument(skip(news, connection))]
+    pub async fn localize(
+        news: &mut [News],
+        language: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        if language == DEFAULT_LANGUAGE.as_str() || news.is_empty() {
+            return Ok(());
+        }
+
+        let translations = sql_span!(connection
+            .query(
+                "SELECT * FROM news_translation WHERE language = $language AND news INSIDE $news"
+            )
+            .bind(("language", language))
+            .bind((
+                "news",
+                news.iter()
+                    .map(|news| news.id.to_thing())
+                    .collect::<Vec<Thing>>()
+            ))
+            .await?
+            .take::<Vec<NewsTranslation>>(0)?);
+
+        for translation in translations {
+            if let Some(news) = news.iter_mut().find(|news| news.id == translation.news) {
+                news.title = translation.title;
+                news.body = translation.body;
+                news.language = translation.language;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Lists the translations of the news and the languages they are missing for
+    #[instrument(skip_all)]
+    pub async fn translations(&self, connection: &DatabaseConnection) -> Result<NewsTranslations> {
+        let translations = sql_span!(connection
+            .query("SELECT * FROM news_translation WHERE news = $news ORDER BY language")
+            .bind(("news", self.id.to_thing()))
+            .await?
+            .take::<Vec<NewsTranslation>>(0)?);
+
+        Ok(NewsTranslations {
+            missing: missing(
+                translations
+                    .iter()
+                    .map(|translation| translation.language.as_str()),
+            ),
+            translations,
+        })
+    }
+
+    /// Creates or replaces the translation of the news to the given language
+    #[instrument(skip(self, title, body, connection))]
+    pub async fn translate(
+        &self,
+        language: &str,
+        title: &str,
+        body: &NewsContent,
+        connection: &DatabaseConnection,
+    ) -> Result<NewsTranslation> {
+        validate_language(language)?;
+        // render and cleanup the content
+        let body = body.render();
+
+        let translation = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("DELETE news_translation WHERE news = $news AND language = $language")
+            .query(
+                "CREATE news_translation SET news = $news, language = $language, title = $title, \
+                format = $format, source = $source, content = $content"
+            )
+            .query("COMMIT TRANSACTION")
+            .bind(("news", self.id.to_thing()))
+            .bind(("language", language))
+            .bind(("title", title))
+            .bind(("format", body.format))
+            .bind(("source", body.source))
+            .bind(("content", body.content))
+            .await?
+            .check()?
+            .take::<Option<NewsTranslation>>(1)?);
+
+        translation.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Removes the translation of the news to the given language
+    #[instrument(skip(self, connection))]
+    pub async fn remove_translation(
+        &self,
+        language: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        validate_language(language)?;
+        sql_span!(connection
+            .query("DELETE news_translation WHERE news = $news AND language = $language")
+            .bind(("news", self.id.to_thing()))
+            .bind(("language", language))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+
+    /// Lists all news which are not translated to every language, newest first
+    #[instrument(skip_all)]
+    pub async fn missing_translations(
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<MissingTranslations>> {
+        let mut response = sql_span!(connection
+            .query("SELECT id, title, created_at FROM news ORDER BY created_at DESC")
+            .query("SELECT news, language FROM news_translation")
+            .await?
+            .check()?);
+        let mut news = response.take::<Vec<MissingTranslations>>(0)?;
+        let translated = response.take::<Vec<TranslatedLanguage>>(1)?;
+
+        for news in news.iter_mut() {
+            news.missing = missing(
+                translated
+                    .iter()
+                    .filter(|translated| translated.news == news.id)
+                    .map(|translated| translated.language.as_str()),
+            );
+        }
+        news.retain(|news| !news.missing.is_empty());
+
+        Ok(news)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::news::translation::{missing, validate_language};
+
+    #[test]
+    fn test_missing() {
+        assert_eq!(vec!["en".to_owned()], missing(std::iter::empty()));
+        assert!(missing(["en"].into_iter()).is_empty());
+
+        assert!(validate_language("en").is_ok());
+        assert!(validate_language("de").is_err());
+        assert!(validate_language("fr").is_err());
+    }
+}
@@ -0,0 +1,234 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::live::LiveMessage;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// What a notification is about, notifications can be muted per kind
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Eq, Hash)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationKind {
+    /// a share link of an own schematic has been used
+    SchematicShare,
+    /// an invitation to join a clan
+    ClanInvitation,
+    /// a confirmed registration or waitlist place for an event
+    Registration,
+    /// a place became available and the waitlisted registration was moved up
+    Promotion,
+    /// a new login, a changed password or toggled two-factor authentication
+    Security,
+}
+
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct Notification {
+    id: Id,
+    /// the notified account
+    account: Id,
+    kind: NotificationKind,
+    message: String,
+    /// the record the notification is about, e.g. the event or the clan invitation
+    target: Option<Id>,
+    /// when the notification has been marked as read
+    #[serde(alias = "read_at")]
+    read_at: Option<DateTime<Utc>>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// The notification settings of an account
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct NotificationPreferences {
+    /// the kinds of notifications the account doesn't want to receive
+    #[serde(default)]
+    pub muted: Vec<NotificationKind>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct NotificationSearch {
+    /// only notifications which haven't been read yet
+    #[serde(default)]
+    pub unread: bool,
+}
+
+impl NotificationPreferences {
+    fn id(account: &Id) -> Id {
+        Id::new(("notification_preference", account.id.as_str()))
+    }
+
+    /// Fetch the preferences of the account, nothing is muted by default
+    #[instrument(skip(connection))]
+    pub async fn of(account: &Id, connection: &DatabaseConnection) -> Result<Self> {
+        let preferences: Option<NotificationPreferences> =
+            sql_span!(connection.select(&Self::id(account)).await?);
+
+        Ok(preferences.unwrap_or_default())
+    }
+
+    /// Replaces the preferences of the account
+    #[instrument(skip(connection))]
+    pub async fn set(
+        account: &Id,
+        muted: Vec<NotificationKind>,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let mut unique = Vec::with_capacity(muted.len());
+        for kind in muted {
+            if !unique.contains(&kind) {
+                unique.push(kind);
+            }
+        }
+        let preferences = sql_span!(connection
+            .query("UPDATE $preferences SET account = $account, muted = $muted")
+            .bind(("preferences", Self::id(account).to_thing()))
+            .bind(("account", account.to_thing()))
+            .bind(("muted", unique))
+            .await?
+            .take::<Option<NotificationPreferences>>(0)?);
+
+        preferences.ok_or(ApplicationError::InternalServerError)
+    }
+}
+
+impl Notification {
+    /// Saves the notification and pushes it to the live stream of the account. Muted kinds are
+    /// dropped, which returns none.
+    #[instrument(skip(state))]
+    pub async fn send(
+        account: &Id,
+        kind: NotificationKind,
+        message: &str,
+        target: Option<&Id>,
+        state: &ApplicationState,
+    ) -> Result<Option<Self>> {
+        let connection = state.connection();
+        if NotificationPreferences::of(account, connection)
+            .await?
+            .muted
+            .contains(&kind)
+        {
+            return Ok(None);
+        }
+
+        let notification = sql_span!(connection
+            .query(
+                "CREATE notification SET account = $account, kind = $kind, message = $message, \
+                target = $target"
+            )
+            .bind(("account", account.to_thing()))
+            .bind(("kind", kind))
+            .bind(("message", message))
+            .bind(("target", target.map(Id::to_thing)))
+            .await?
+            .take::<Option<Notification>>(0)?)
+        .ok_or(ApplicationError::InternalServerError)?;
+
+        state
+            .live()
+            .publish(account, LiveMessage::Notification(notification.clone()));
+        Ok(Some(notification))
+    }
+
+    /// Fetch a notification of the account by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, account: &Id, connection: &DatabaseConnection) -> Result<Self> {
+        let notification: Option<Notification> = sql_span!(
+            connection
+                .select(&Id::try_from(("notification", id))?)
+                .await?
+        );
+
+        notification
+            .filter(|notification| notification.account == *account)
+            .ok_or(ApplicationError::BadRequest(
+                "notification not found".to_owned(),
+            ))
+    }
+
+    /// Lists the notifications of the account, the latest first
+    #[instrument(skip(request, connection))]
+    pub async fn list(
+        account: &Id,
+        search: &NotificationSearch,
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<Self>> {
+        let unread = match search.unread {
+            true => "AND read_at IS NONE ",
+            false => "",
+        };
+        request
+            .execute::<Notification, _>(
+                format!(
+                    "SELECT * FROM notification WHERE account = type::thing('account', $account) \
+                    {unread}ORDER BY created_at DESC"
+                )
+                .as_str(),
+                Some(&[("account", account.id.as_str())]),
+                connection,
+            )
+            .await
+    }
+
+    /// Marks the notification as read, already read notifications keep their time
+    #[instrument(skip_all)]
+    pub async fn read(&mut self, connection: &DatabaseConnection) -> Result<()> {
+        let notification = sql_span!(connection
+            .query("UPDATE $notification SET read_at = read_at OR time::now()")
+            .bind(("notification", self.id.to_thing()))
+            .await?
+            .take::<Option<Notification>>(0)?);
+        *self = notification.ok_or(ApplicationError::InternalServerError)?;
+        Ok(())
+    }
+
+    /// Marks all notifications of the account as read
+    #[instrument(skip(connection))]
+    pub async fn read_all(account: &Id, connection: &DatabaseConnection) -> Result<()> {
+        sql_span!(connection
+            .query("UPDATE notification SET read_at = time::now() WHERE account = $account AND read_at IS NONE")
+            .bind(("account", account.to_thing()))
+            .await?
+            .check()?);
+        Ok(())
+    }
+}
+
+/// Sends the notification, failures are only logged as they mustn't fail the notified action
+pub async fn notify(
+    state: &ApplicationState,
+    account: &Id,
+    kind: NotificationKind,
+    message: &str,
+    target: Option<&Id>,
+) {
+    if let Err(error) = Notification::send(account, kind, message, target, state).await {
+        warn!("failed to notify {}: {error}", account.id);
+    }
+}
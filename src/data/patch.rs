@@ -0,0 +1,81 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::prelude::*;
+use serde::{Deserialize, Deserializer};
+
+/// Deserializes a field of a partial update which may be cleared. Absent fields are left
+/// untouched (`None`), while an explicit `null` clears the field (`Some(None)`). Has to be used
+/// together with `#[serde(default)]`.
+pub fn nullable<'de, D, T>(deserializer: D) -> std::result::Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Partial updates may contain the id of the updated object, which then has to match
+pub fn check_id(id: &Id, given: Option<&Id>) -> Result<()> {
+    match given {
+        Some(given) if given != id => Err(ApplicationError::BadRequest(
+            "the id doesn't match the updated object".to_owned(),
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::patch::{check_id, nullable};
+    use crate::prelude::*;
+    use chrono::{DateTime, Utc};
+
+    #[derive(Deserialize)]
+    struct Patch {
+        #[serde(default, deserialize_with = "nullable")]
+        publish_at: Option<Option<DateTime<Utc>>>,
+    }
+
+    #[test]
+    fn test_nullable() {
+        let patch: Patch = serde_json::from_str("{}").unwrap();
+        assert_eq!(None, patch.publish_at);
+        let patch: Patch = serde_json::from_str(r#"{"publish_at": null}"#).unwrap();
+        assert_eq!(Some(None), patch.publish_at);
+        let patch: Patch =
+            serde_json::from_str(r#"{"publish_at": "2023-05-01T10:00:00Z"}"#).unwrap();
+        assert!(matches!(patch.publish_at, Some(Some(_))));
+    }
+
+    #[test]
+    fn test_check_id() {
+        let id = Id::new(("event", "a"));
+        assert!(check_id(&id, None).is_ok());
+        assert!(check_id(&id, Some(&Id::new(("event", "a")))).is_ok());
+        assert!(check_id(&id, Some(&Id::new(("event", "b")))).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::season::Season;
+use crate::prelude::*;
+use serde_json::Value;
+
+/// The statistics of a player aggregated over the matches they participated in
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct PlayerStats {
+    /// the uuid of the minecraft account
+    pub uuid: String,
+    pub matches: u64,
+    pub wins: u64,
+    pub kills: u64,
+    pub deaths: u64,
+    /// the time played in seconds
+    pub playtime: u64,
+    /// the kills per death, players without deaths count as having died once
+    #[serde(default)]
+    pub kd: f64,
+}
+
+impl PlayerStats {
+    fn with_kd(mut self) -> Self {
+        self.kd = self.kills as f64 / self.deaths.max(1) as f64;
+        self
+    }
+
+    fn add(&mut self, other: &PlayerStats) {
+        self.matches += other.matches;
+        self.wins += other.wins;
+        self.kills += other.kills;
+        self.deaths += other.deaths;
+        self.playtime += other.playtime;
+    }
+}
+
+/// The statistics of a player within a single event
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+pub struct EventStats {
+    pub event: Id,
+    #[serde(flatten)]
+    pub stats: PlayerStats,
+}
+
+/// The public profile of a player
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PlayerProfile {
+    pub uuid: String,
+    /// the username of the linked account, if there is one
+    pub username: Option<String>,
+    /// the statistics over all events
+    pub total: PlayerStats,
+    pub events: Vec<EventStats>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaderboardSort {
+    #[default]
+    Wins,
+    Kills,
+    Matches,
+    Playtime,
+}
+
+/// The filters of the leaderboard. All given filters have to match.
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+pub struct LeaderboardSearch {
+    /// only matches of the given event
+    pub event: Option<String>,
+    /// only matches played within the given season
+    pub season: Option<String>,
+    /// the statistic players are ranked by, descending
+    #[serde(default)]
+    pub sort: LeaderboardSort,
+}
+
+/// The projection aggregating the participations per group
+const AGGREGATION: &str = "count() AS matches, math::sum(wins) AS wins, \
+    math::sum(kills) AS kills, math::sum(deaths) AS deaths, math::sum(duration) AS playtime";
+
+impl LeaderboardSearch {
+    /// Builds the query ranking the players. The season has to be the one selected by the
+    /// search, user input is only passed as bindings.
+    pub fn query(&self, season: Option<&Season>) -> Result<(String, Vec<(&'static str, Value)>)> {
+        let mut conditions = vec![];
+        let mut bindings = vec![];
+
+        if let Some(event) = &self.event {
+            let event = Id::try_from(("event", event.as_str()))?;
+            conditions.push("event = type::thing('event', $event)");
+            bindings.push(("event", Value::from(event.id)));
+        }
+        if let Some(season) = season {
+            conditions.push("created_at >= <datetime> $from");
+            bindings.push(("from", Value::from(season.start().to_rfc3339())));
+            if let Some(end) = season.end() {
+                conditions.push("created_at < <datetime> $to");
+                bindings.push(("to", Value::from(end.to_rfc3339())));
+            }
+        }
+
+        let filter = match conditions.is_empty() {
+            true => String::new(),
+            false => format!("WHERE {} ", conditions.join(" AND ")),
+        };
+        let sort = match self.sort {
+            LeaderboardSort::Wins => "wins",
+            LeaderboardSort::Kills => "kills",
+            LeaderboardSort::Matches => "matches",
+            LeaderboardSort::Playtime => "playtime",
+        };
+
+        Ok((
+            format!(
+                "SELECT uuid, {AGGREGATION} FROM match_participation {filter}\
+                GROUP BY uuid ORDER BY {sort} DESC, uuid ASC"
+            ),
+            bindings,
+        ))
+    }
+
+    /// Select a page of the ranked players
+    #[instrument(skip(connection))]
+    pub async fn execute(
+        &self,
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<PlayerStats>> {
+        let season = match &self.season {
+            Some(season) => Some(Season::from_id(season.as_str(), connection).await?),
+            None => None,
+        };
+        let (query, bindings) = self.query(season.as_ref())?;
+
+        let mut page = request
+            .execute::<PlayerStats, _>(query.as_str(), Some(bindings.as_slice()), connection)
+            .await?;
+        page.data = page.data.into_iter().map(PlayerStats::with_kd).collect();
+        Ok(page)
+    }
+}
+
+#[derive(Deserialize)]
+struct LinkedAccount {
+    username: String,
+}
+
+impl PlayerProfile {
+    /// Collects the statistics of the player by the uuid of their minecraft account
+    #[instrument(skip(connection))]
+    pub async fn from_uuid(uuid: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let uuid = uuid.trim().to_lowercase();
+        let mut response = sql_span!(connection
+            .query(format!(
+                "SELECT event, uuid, {AGGREGATION} FROM match_participation \
+                WHERE uuid = $uuid GROUP BY event, uuid"
+            ))
+            .query("SELECT username FROM account WHERE string::lowercase(uuid) = $uuid")
+            .bind(("uuid", uuid.as_str()))
+            .await?
+            .check()?);
+        let events = response
+            .take::<Vec<EventStats>>(0)?
+            .into_iter()
+            .map(|stats| EventStats {
+                event: stats.event,
+                stats: stats.stats.with_kd(),
+            })
+            .collect::<Vec<EventStats>>();
+        let account = response.take::<Option<LinkedAccount>>(1)?;
+
+        if events.is_empty() && account.is_none() {
+            return Err(ApplicationError::BadRequest("player not found".to_owned()));
+        }
+
+        let mut total = PlayerStats {
+            uuid: uuid.clone(),
+            ..Default::default()
+        };
+        events.iter().for_each(|event| total.add(&event.stats));
+
+        Ok(Self {
+            uuid,
+            username: account.map(|account| account.username),
+            total: total.with_kd(),
+            events,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::player::{LeaderboardSearch, LeaderboardSort, PlayerStats};
+
+    #[test]
+    fn test_query() {
+        let (query, bindings) = LeaderboardSearch::default().query(None).unwrap();
+        assert_eq!(
+            "SELECT uuid, count() AS matches, math::sum(wins) AS wins, \
+            math::sum(kills) AS kills, math::sum(deaths) AS deaths, \
+            math::sum(duration) AS playtime FROM match_participation \
+            GROUP BY uuid ORDER BY wins DESC, uuid ASC",
+            query
+        );
+        assert!(bindings.is_empty());
+
+        let search = LeaderboardSearch {
+            event: Some("event:abc".to_owned()),
+            sort: LeaderboardSort::Kills,
+            ..Default::default()
+        };
+        let (query, bindings) = search.query(None).unwrap();
+        assert!(query.contains("WHERE event = type::thing('event', $event) GROUP BY uuid"));
+        assert!(query.ends_with("ORDER BY kills DESC, uuid ASC"));
+        assert_eq!(vec![("event", serde_json::Value::from("abc"))], bindings);
+
+        let search = LeaderboardSearch {
+            event: Some("news:abc".to_owned()),
+            ..Default::default()
+        };
+        assert!(search.query(None).is_err());
+    }
+
+    #[test]
+    fn test_kd() {
+        let stats = PlayerStats {
+            kills: 3,
+            deaths: 2,
+            ..Default::default()
+        };
+        assert_eq!(1.5, stats.with_kd().kd);
+
+        let stats = PlayerStats {
+            kills: 3,
+            ..Default::default()
+        };
+        assert_eq!(3.0, stats.with_kd().kd);
+    }
+}
@@ -0,0 +1,376 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::event::result::MatchReport;
+use crate::data::season::Season;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// The rating of new players, soft resets pull the ratings towards it
+pub const INITIAL_RATING: f64 = 1500.0;
+/// The maximum change of a rating within a single match
+const K_FACTOR: f64 = 32.0;
+/// The share of the distance to the initial rating a player keeps into a new season
+const SOFT_RESET: f64 = 0.5;
+
+/// The Elo rating of a player within a season. Ratings outside of any season are stored
+/// without one.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct Rating {
+    id: Id,
+    /// the uuid of the minecraft account
+    uuid: String,
+    season: Option<Id>,
+    rating: f64,
+    /// the count of rated matches within the season
+    matches: u64,
+    #[serde(alias = "updated_at")]
+    updated_at: DateTime<Utc>,
+}
+
+/// A change of the rating of a player caused by a match result
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct RatingChange {
+    id: Id,
+    uuid: String,
+    season: Option<Id>,
+    result: Id,
+    /// the rating after the match
+    rating: f64,
+    change: f64,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// The rating a player enters their next match with
+#[derive(Debug, Clone, PartialEq)]
+struct Standing {
+    /// the stored rating of the season, absent before the first match of the season
+    current: Option<Id>,
+    rating: f64,
+    matches: u64,
+}
+
+/// The expected score of a player against the opponent, between 0 and 1
+pub fn expected(rating: f64, opponent: f64) -> f64 {
+    1.0 / (1.0 + 10f64.powf((opponent - rating) / 400.0))
+}
+
+/// Calculates the rating changes of a match in the order of the ratings. Every player is
+/// compared with every other player: the winner beats everyone, all others draw among each
+/// other. The changes are scaled by the count of opponents, so a match never moves a rating
+/// by more than the k-factor.
+pub fn elo(ratings: &[f64], winner: Option<usize>) -> Vec<f64> {
+    let opponents = ratings.len().saturating_sub(1).max(1) as f64;
+
+    ratings
+        .iter()
+        .enumerate()
+        .map(|(index, rating)| {
+            ratings
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(other, opponent)| {
+                    let score = match winner {
+                        Some(winner) if winner == index => 1.0,
+                        Some(winner) if winner == other => 0.0,
+                        _ => 0.5,
+                    };
+                    score - expected(*rating, *opponent)
+                })
+                .sum::<f64>()
+                * K_FACTOR
+                / opponents
+        })
+        .collect()
+}
+
+/// The rating a player starts a new season with
+pub fn soft_reset(rating: f64) -> f64 {
+    INITIAL_RATING + (rating - INITIAL_RATING) * SOFT_RESET
+}
+
+/// Picks the standing of the player from their stored ratings, the latest first. The latest
+/// rating of another season is carried over with a soft reset.
+fn standing(uuid: &str, season: Option<&Id>, ratings: &[Rating]) -> Standing {
+    let ratings = ratings
+        .iter()
+        .filter(|rating| rating.uuid == uuid)
+        .collect::<Vec<&Rating>>();
+
+    if let Some(current) = ratings
+        .iter()
+        .find(|rating| rating.season.as_ref() == season)
+    {
+        return Standing {
+            current: Some(current.id.clone()),
+            rating: current.rating,
+            matches: current.matches,
+        };
+    }
+    Standing {
+        current: None,
+        rating: ratings
+            .first()
+            .map_or(INITIAL_RATING, |latest| soft_reset(latest.rating)),
+        matches: 0,
+    }
+}
+
+impl Rating {
+    /// Fetch the ratings the players enter their next match of the season with, in the order
+    /// of the uuids
+    async fn standings(
+        uuids: &[&str],
+        season: Option<&Id>,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<Standing>> {
+        let ratings = sql_span!(connection
+            .query("SELECT * FROM rating WHERE uuid INSIDE $uuids ORDER BY updated_at DESC")
+            .bind(("uuids", uuids))
+            .await?
+            .take::<Vec<Rating>>(0)?);
+
+        Ok(uuids
+            .iter()
+            .map(|uuid| standing(uuid, season, ratings.as_slice()))
+            .collect())
+    }
+
+    /// Fetch the ratings of the players in the current season, in the order of the uuids.
+    /// Players without a rating in the season yet get the rating they would start with.
+    #[instrument(skip(connection))]
+    pub async fn current(uuids: &[&str], connection: &DatabaseConnection) -> Result<Vec<f64>> {
+        let season = Season::current(connection).await?;
+        Ok(
+            Self::standings(uuids, season.as_ref().map(Season::id), connection)
+                .await?
+                .into_iter()
+                .map(|standing| standing.rating)
+                .collect(),
+        )
+    }
+
+    /// Builds the query ranking the ratings of the season, or the ones outside of any season
+    fn ranking_query(season: Option<&Season>) -> (String, Vec<(&'static str, Value)>) {
+        match season {
+            Some(season) => (
+                "SELECT * FROM rating WHERE season = type::thing('season', $season) \
+                ORDER BY rating DESC, uuid ASC"
+                    .to_owned(),
+                vec![("season", Value::from(season.id().id.clone()))],
+            ),
+            None => (
+                "SELECT * FROM rating WHERE season IS NONE ORDER BY rating DESC, uuid ASC"
+                    .to_owned(),
+                vec![],
+            ),
+        }
+    }
+
+    /// Select a page of the ratings of the given season, the current season by default
+    #[instrument(skip(connection))]
+    pub async fn ranking(
+        season: Option<&str>,
+        request: PagingRequest,
+        connection: &DatabaseConnection,
+    ) -> Result<Page<Rating>> {
+        let season = match season {
+            Some(season) => Some(Season::from_id(season, connection).await?),
+            None => Season::current(connection).await?,
+        };
+        let (query, bindings) = Self::ranking_query(season.as_ref());
+
+        request
+            .execute::<Rating, _>(query.as_str(), Some(bindings.as_slice()), connection)
+            .await
+    }
+}
+
+impl RatingChange {
+    /// Lists the rating changes of the player, the earliest first
+    #[instrument(skip(connection))]
+    pub async fn history(
+        uuid: &str,
+        season: Option<&str>,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<Self>> {
+        let uuid = uuid.trim().to_lowercase();
+        let query = match season {
+            Some(season) => connection
+                .query(
+                    "SELECT * FROM rating_change WHERE uuid = $uuid AND season = $season \
+                    ORDER BY created_at ASC",
+                )
+                .bind((
+                    "season",
+                    Season::from_id(season, connection).await?.id().to_thing(),
+                )),
+            None => connection
+                .query("SELECT * FROM rating_change WHERE uuid = $uuid ORDER BY created_at ASC"),
+        };
+
+        Ok(sql_span!(query
+            .bind(("uuid", uuid))
+            .await?
+            .take::<Vec<RatingChange>>(0)?))
+    }
+}
+
+impl MatchReport {
+    /// Adds the updates of the ratings of the participants within the current season to the
+    /// query creating the result
+    #[instrument(skip_all)]
+    pub(crate) async fn rate<'r>(
+        &self,
+        result: &Id,
+        query: DatabaseQuery<'r>,
+        connection: &DatabaseConnection,
+    ) -> Result<DatabaseQuery<'r>> {
+        let season = Season::current(connection).await?;
+        let season = season.as_ref().map(Season::id);
+        let uuids = self
+            .participants
+            .iter()
+            .map(|participant| participant.uuid.as_str())
+            .collect::<Vec<&str>>();
+        let standings = Rating::standings(uuids.as_slice(), season, connection).await?;
+
+        let winner = self
+            .winner
+            .as_ref()
+            .and_then(|winner| uuids.iter().position(|uuid| uuid == winner));
+        let changes = elo(
+            standings
+                .iter()
+                .map(|standing| standing.rating)
+                .collect::<Vec<f64>>()
+                .as_slice(),
+            winner,
+        );
+
+        let mut query = query
+            .bind(("season", season.map(Id::to_thing)))
+            .bind(("rated", result.to_thing()));
+        for (index, ((uuid, standing), change)) in
+            uuids.iter().zip(standings).zip(changes).enumerate()
+        {
+            query = match standing.current {
+                Some(current) => query
+                    .query(format!(
+                        "UPDATE $rating{index} SET rating = $value{index}, matches = $matches{index}"
+                    ))
+                    .bind((format!("rating{index}"), current.to_thing())),
+                None => query.query(format!(
+                    "CREATE rating SET uuid = $uuid{index}, season = $season, \
+                    rating = $value{index}, matches = $matches{index}"
+                )),
+            };
+            query = query
+                .query(format!(
+                    "CREATE rating_change SET uuid = $uuid{index}, season = $season, \
+                    result = $rated, rating = $value{index}, change = $change{index}"
+                ))
+                .bind((format!("uuid{index}"), *uuid))
+                .bind((format!("value{index}"), standing.rating + change))
+                .bind((format!("matches{index}"), standing.matches + 1))
+                .bind((format!("change{index}"), change));
+        }
+        Ok(query)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::rating::{elo, expected, soft_reset, standing, Rating, INITIAL_RATING};
+    use crate::prelude::Id;
+    use chrono::Utc;
+
+    fn rating(id: &str, uuid: &str, season: Option<&str>, value: f64) -> Rating {
+        Rating {
+            id: Id::new(("rating", id)),
+            uuid: uuid.to_owned(),
+            season: season.map(|season| Id::new(("season", season))),
+            rating: value,
+            matches: 3,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_elo() {
+        assert_eq!(0.5, expected(1500.0, 1500.0));
+        assert!(expected(1700.0, 1500.0) > 0.75);
+
+        assert_eq!(vec![16.0, -16.0], elo(&[1500.0, 1500.0], Some(0)));
+        assert_eq!(vec![0.0, 0.0], elo(&[1500.0, 1500.0], None));
+
+        // an upset moves the ratings further than the expected outcome
+        let expected = elo(&[1700.0, 1500.0], Some(0));
+        let upset = elo(&[1700.0, 1500.0], Some(1));
+        assert!(upset[1] > expected[0]);
+        assert!((expected[0] + expected[1]).abs() < 1e-9);
+
+        // the winner of a free for all gains at most the k-factor
+        let changes = elo(&[1500.0, 1500.0, 1500.0], Some(2));
+        assert_eq!(16.0, changes[2]);
+        assert_eq!(-8.0, changes[0]);
+        assert!(changes.iter().sum::<f64>().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_standing() {
+        let season = Id::new(("season", "two"));
+        let ratings = vec![
+            rating("b", "b", Some("one"), 1700.0),
+            rating("a2", "a", Some("two"), 1540.0),
+            rating("a1", "a", Some("one"), 1800.0),
+        ];
+
+        let a = standing("a", Some(&season), &ratings);
+        assert_eq!(Some(Id::new(("rating", "a2"))), a.current);
+        assert_eq!(1540.0, a.rating);
+        assert_eq!(3, a.matches);
+
+        let b = standing("b", Some(&season), &ratings);
+        assert_eq!(None, b.current);
+        assert_eq!(soft_reset(1700.0), b.rating);
+        assert_eq!(1600.0, b.rating);
+        assert_eq!(0, b.matches);
+
+        let c = standing("c", Some(&season), &ratings);
+        assert_eq!(INITIAL_RATING, c.rating);
+        assert_eq!(0, c.matches);
+
+        assert_eq!(soft_reset(1540.0), standing("a", None, &ratings).rating);
+    }
+}
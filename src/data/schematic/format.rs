@@ -0,0 +1,459 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::prelude::*;
+use fastnbt::Value;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+
+/// The maximum amount of bytes a schematic may inflate to
+const MAX_DECOMPRESSED_SIZE: u64 = 64 * 1024 * 1024;
+/// The maximum volume (in blocks) of a schematic we are willing to convert
+const MAX_VOLUME: usize = 256 * 256 * 256;
+
+pub type Compound = HashMap<String, Value>;
+
+/// The file formats schematics can be imported from and exported to. Internally every schematic
+/// is normalised to the sponge format.
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, JsonSchema, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SchematicFormat {
+    /// the sponge schematic format (`.schem`) used by WorldEdit 7 and newer
+    #[default]
+    Schem,
+    /// the legacy MCEdit format (`.schematic`) with numeric block ids
+    Schematic,
+    /// the litematica format (`.litematic`)
+    Litematic,
+}
+
+impl SchematicFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Schem => "schem",
+            Self::Schematic => "schematic",
+            Self::Litematic => "litematic",
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Self::Schem => "application/x-schem",
+            Self::Schematic => "application/x-schematic",
+            Self::Litematic => "application/x-litematic",
+        }
+    }
+
+    /// Strips a known schematic extension from the given file name
+    pub fn strip_extension(name: &str) -> &str {
+        [Self::Schem, Self::Schematic, Self::Litematic]
+            .iter()
+            .find_map(|format| name.strip_suffix(&format!(".{}", format.extension())))
+            .unwrap_or(name)
+    }
+
+    /// Detects the format of the given (uncompressed) root compound by its characteristic tags
+    fn detect(root: &Compound) -> Result<Self> {
+        if root.contains_key("Regions") {
+            Ok(Self::Litematic)
+        } else if root.contains_key("Materials") || root.contains_key("Blocks") {
+            Ok(Self::Schematic)
+        } else if root.contains_key("Palette") || root.contains_key("Schematic") {
+            Ok(Self::Schem)
+        } else {
+            Err(invalid("unknown schematic format"))
+        }
+    }
+}
+
+/// A block entity (e.g. a chest) positioned relative to the origin of the structure
+#[derive(Debug, Clone, PartialEq)]
+pub struct BlockEntity {
+    pub position: [i32; 3],
+    pub id: String,
+    /// the remaining nbt data of the block entity
+    pub data: Compound,
+}
+
+/// The format independent representation of a schematic. The blocks are stored as palette indices
+/// in y-z-x order, which all supported formats share.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Structure {
+    pub width: u16,
+    pub height: u16,
+    pub length: u16,
+    pub data_version: i32,
+    /// full block states, e.g. `minecraft:oak_stairs[facing=east,half=bottom]`
+    pub palette: Vec<String>,
+    /// the position of every state in the palette
+    palette_indices: HashMap<String, u32>,
+    pub blocks: Vec<u32>,
+    pub block_entities: Vec<BlockEntity>,
+}
+
+impl Structure {
+    pub fn new(
+        [width, height, length]: [u16; 3],
+        data_version: i32,
+        palette: Vec<String>,
+        blocks: Vec<u32>,
+        block_entities: Vec<BlockEntity>,
+    ) -> Self {
+        let mut palette_indices = HashMap::with_capacity(palette.len());
+        for (index, state) in palette.iter().enumerate() {
+            palette_indices.entry(state.clone()).or_insert(index as u32);
+        }
+
+        Self {
+            width,
+            height,
+            length,
+            data_version,
+            palette,
+            palette_indices,
+            blocks,
+            block_entities,
+        }
+    }
+
+    /// Creates an empty (air filled) structure with the given dimensions
+    pub fn empty(width: u16, height: u16, length: u16, data_version: i32) -> Result<Self> {
+        let (_, volume) = volume(width as i64, height as i64, length as i64)?;
+
+        Ok(Self::new(
+            [width, height, length],
+            data_version,
+            vec![AIR.to_owned()],
+            vec![0; volume],
+            Vec::new(),
+        ))
+    }
+
+    pub fn volume(&self) -> usize {
+        self.blocks.len()
+    }
+
+    /// Counts the blocks which are not any kind of air
+    pub fn block_count(&self) -> u64 {
+        let air = self
+            .palette
+            .iter()
+            .map(|state| {
+                matches!(
+                    split_state(state).0,
+                    AIR | "minecraft:cave_air" | "minecraft:void_air"
+                )
+            })
+            .collect::<Vec<bool>>();
+
+        self.blocks
+            .iter()
+            .filter(|block| !air.get(**block as usize).copied().unwrap_or(false))
+            .count() as u64
+    }
+
+    pub fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        (y * self.length as usize + z) * self.width as usize + x
+    }
+
+    /// Returns the palette index of the given state, inserting it if not yet present
+    pub fn palette_index(&mut self, state: &str) -> u32 {
+        if let Some(index) = self.palette_indices.get(state) {
+            return *index;
+        }
+
+        let index = self.palette.len() as u32;
+        self.palette.push(state.to_owned());
+        self.palette_indices.insert(state.to_owned(), index);
+        index
+    }
+
+    /// Decodes the given file and detects its format
+    pub fn read(data: &[u8]) -> Result<(Self, SchematicFormat)> {
+        let root = read_nbt(data)?;
+        let format = SchematicFormat::detect(&root)?;
+
+        let structure = match format {
+            SchematicFormat::Schem => super::sponge::read(root)?,
+            SchematicFormat::Schematic => super::mcedit::read(root)?,
+            SchematicFormat::Litematic => super::litematic::read(root)?,
+        };
+
+        Ok((structure, format))
+    }
+
+    /// Encodes the structure into the given format
+    pub fn write(&self, format: SchematicFormat, name: &str) -> Result<Vec<u8>> {
+        let (root_name, root) = match format {
+            SchematicFormat::Schem => ("Schematic", super::sponge::write(self, name)?),
+            SchematicFormat::Schematic => ("Schematic", super::mcedit::write(self)?),
+            SchematicFormat::Litematic => ("", super::litematic::write(self, name)?),
+        };
+
+        write_nbt(root_name, &root)
+    }
+}
+
+/// Converts the given (sponge) file into the requested format.
+pub fn convert(data: &[u8], name: &str, target: SchematicFormat) -> Result<Vec<u8>> {
+    let (structure, format) = Structure::read(data)?;
+    if format.eq(&target) {
+        return Ok(data.to_vec());
+    }
+
+    structure.write(target, name)
+}
+
+/// Runs the given parsing or conversion on the blocking thread pool, as it is cpu bound
+pub async fn blocking<T, F>(task: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|_| ApplicationError::InternalServerError)?
+}
+
+pub const AIR: &str = "minecraft:air";
+
+/// Splits a block state into its name and its properties
+pub fn split_state(state: &str) -> (&str, Vec<(&str, &str)>) {
+    match state.split_once('[') {
+        Some((name, properties)) => (
+            name,
+            properties
+                .trim_end_matches(']')
+                .split(',')
+                .filter_map(|property| property.split_once('='))
+                .collect(),
+        ),
+        None => (state, Vec::new()),
+    }
+}
+
+pub fn invalid(message: &str) -> ApplicationError {
+    ApplicationError::BadRequest(format!("invalid schematic: {message}"))
+}
+
+/// Validates the dimensions of a schematic and returns them together with the volume
+pub fn volume(width: i64, height: i64, length: i64) -> Result<([u16; 3], usize)> {
+    if width < 0 || height < 0 || length < 0 {
+        return Err(invalid("negative dimensions"));
+    }
+    let [Ok(width), Ok(height), Ok(length)] = [width, height, length].map(u16::try_from) else {
+        return Err(invalid("dimensions exceed 65535 blocks"));
+    };
+    let volume = (width as usize)
+        .checked_mul(height as usize)
+        .and_then(|area| area.checked_mul(length as usize))
+        .filter(|volume| *volume <= MAX_VOLUME)
+        .ok_or_else(|| {
+            ApplicationError::BadRequest(format!(
+                "schematic is too large (at most {MAX_VOLUME} blocks are supported)"
+            ))
+        })?;
+
+    Ok(([width, height, length], volume))
+}
+
+fn read_nbt(data: &[u8]) -> Result<Compound> {
+    // schematics are usually gzip compressed, but some tools write plain nbt
+    let mut buffer = Vec::new();
+    if data.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(data)
+            .take(MAX_DECOMPRESSED_SIZE + 1)
+            .read_to_end(&mut buffer)
+            .map_err(|_| invalid("corrupted gzip stream"))?;
+        if buffer.len() as u64 > MAX_DECOMPRESSED_SIZE {
+            return Err(invalid("decompressed data is too large"));
+        }
+    } else {
+        buffer.extend_from_slice(data);
+    }
+
+    match fastnbt::from_bytes::<Value>(buffer.as_slice()) {
+        Ok(Value::Compound(root)) => Ok(root),
+        Ok(_) => Err(invalid("root tag is not a compound")),
+        Err(error) => Err(ApplicationError::BadRequest(format!(
+            "invalid schematic: malformed nbt ({error})"
+        ))),
+    }
+}
+
+fn write_nbt(root_name: &str, root: &Compound) -> Result<Vec<u8>> {
+    let data = fastnbt::to_bytes_with_opts(root, fastnbt::SerOpts::new().root_name(root_name))
+        .map_err(|_| ApplicationError::InternalServerError)?;
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data.as_slice())?;
+    Ok(encoder.finish()?)
+}
+
+/// Typed accessors for nbt compounds which fail with descriptive errors
+pub trait CompoundExt {
+    fn value(&self, key: &str) -> Result<&Value>;
+
+    fn int(&self, key: &str) -> Result<i64>;
+
+    /// Reads a dimension, which the formats store as unsigned short in the signed nbt short
+    fn dimension(&self, key: &str) -> Result<i64>;
+
+    fn string(&self, key: &str) -> Result<&str>;
+
+    fn compound(&self, key: &str) -> Result<&Compound>;
+
+    fn list(&self, key: &str) -> Result<&Vec<Value>>;
+}
+
+impl CompoundExt for Compound {
+    fn value(&self, key: &str) -> Result<&Value> {
+        self.get(key)
+            .ok_or_else(|| invalid(format!("missing `{key}` tag").as_str()))
+    }
+
+    fn int(&self, key: &str) -> Result<i64> {
+        self.value(key)?
+            .as_i64()
+            .ok_or_else(|| invalid(format!("`{key}` is not a number").as_str()))
+    }
+
+    fn dimension(&self, key: &str) -> Result<i64> {
+        match self.value(key)? {
+            Value::Short(value) => Ok(*value as u16 as i64),
+            _ => self.int(key),
+        }
+    }
+
+    fn string(&self, key: &str) -> Result<&str> {
+        self.value(key)?
+            .as_str()
+            .ok_or_else(|| invalid(format!("`{key}` is not a string").as_str()))
+    }
+
+    fn compound(&self, key: &str) -> Result<&Compound> {
+        match self.value(key)? {
+            Value::Compound(compound) => Ok(compound),
+            _ => Err(invalid(format!("`{key}` is not a compound").as_str())),
+        }
+    }
+
+    fn list(&self, key: &str) -> Result<&Vec<Value>> {
+        match self.value(key)? {
+            Value::List(list) => Ok(list),
+            _ => Err(invalid(format!("`{key}` is not a list").as_str())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::format::{convert, volume, SchematicFormat, Structure};
+
+    fn structure() -> Structure {
+        let mut structure = Structure::empty(3, 2, 4, 3465).unwrap();
+        let stone = structure.palette_index("minecraft:stone");
+        let wool = structure.palette_index("minecraft:red_wool");
+        let index = structure.index(1, 1, 2);
+        structure.blocks[index] = stone;
+        let index = structure.index(2, 0, 3);
+        structure.blocks[index] = wool;
+
+        structure
+    }
+
+    #[test]
+    fn test_strip_extension() {
+        assert_eq!("house", SchematicFormat::strip_extension("house.schem"));
+        assert_eq!("house", SchematicFormat::strip_extension("house.litematic"));
+        assert_eq!("house.nbt", SchematicFormat::strip_extension("house.nbt"));
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let structure = structure();
+
+        for format in [
+            SchematicFormat::Schem,
+            SchematicFormat::Schematic,
+            SchematicFormat::Litematic,
+        ] {
+            let data = structure.write(format, "test").unwrap();
+            let (read, detected) = Structure::read(data.as_slice()).unwrap();
+            assert_eq!(format, detected);
+            assert_eq!(structure.width, read.width);
+            assert_eq!(structure.height, read.height);
+            assert_eq!(structure.length, read.length);
+
+            for (index, block) in structure.blocks.iter().enumerate() {
+                assert_eq!(
+                    structure.palette[*block as usize],
+                    read.palette[read.blocks[index] as usize]
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_roundtrip_large_dimensions() {
+        // dimensions above i16::MAX wrap around in the nbt short
+        let structure = Structure::empty(40000, 1, 2, 3465).unwrap();
+
+        for format in [SchematicFormat::Schem, SchematicFormat::Schematic] {
+            let data = structure.write(format, "test").unwrap();
+            let (read, _) = Structure::read(data.as_slice()).unwrap();
+            assert_eq!(40000, read.width);
+            assert_eq!(2, read.length);
+        }
+    }
+
+    #[test]
+    fn test_block_count() {
+        let mut structure = structure();
+        assert_eq!(2, structure.block_count());
+
+        structure.blocks[0] = structure.palette_index("minecraft:cave_air");
+        assert_eq!(2, structure.block_count());
+    }
+
+    #[test]
+    fn test_volume() {
+        assert_eq!(([3, 2, 4], 24), volume(3, 2, 4).unwrap());
+        assert!(volume(-1, 2, 4).is_err());
+        // dimensions have to fit into the u16 of the formats
+        assert!(volume(70000, 1, 1).is_err());
+        assert!(volume(i64::MAX, i64::MAX, i64::MAX).is_err());
+        assert!(volume(65535, 65535, 65535).is_err());
+    }
+
+    #[test]
+    fn test_convert_invalid() {
+        assert!(convert(b"definitely not nbt", "test", SchematicFormat::Litematic).is_err());
+    }
+}
@@ -0,0 +1,516 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+//! Mapping between the numeric block ids of the pre-1.13 MCEdit format and namespaced block states.
+//! Only the variants encoded in the data value (colors, wood types, ...) are mapped, other data
+//! values (e.g. the orientation of stairs) fall back to the default state.
+
+use std::collections::HashMap;
+
+const COLORS: [&str; 16] = [
+    "white",
+    "orange",
+    "magenta",
+    "light_blue",
+    "yellow",
+    "lime",
+    "pink",
+    "gray",
+    "light_gray",
+    "cyan",
+    "purple",
+    "blue",
+    "brown",
+    "green",
+    "red",
+    "black",
+];
+
+const WOODS: [&str; 6] = ["oak", "spruce", "birch", "jungle", "acacia", "dark_oak"];
+
+/// The default block state of each legacy id (without the `minecraft:` namespace)
+const BLOCKS: [Option<&str>; 256] = [
+    Some("air"),
+    Some("stone"),
+    Some("grass_block"),
+    Some("dirt"),
+    Some("cobblestone"),
+    Some("oak_planks"),
+    Some("oak_sapling"),
+    Some("bedrock"),
+    Some("water"),
+    Some("water"),
+    Some("lava"),
+    Some("lava"),
+    Some("sand"),
+    Some("gravel"),
+    Some("gold_ore"),
+    Some("iron_ore"),
+    Some("coal_ore"),
+    Some("oak_log"),
+    Some("oak_leaves"),
+    Some("sponge"),
+    Some("glass"),
+    Some("lapis_ore"),
+    Some("lapis_block"),
+    Some("dispenser"),
+    Some("sandstone"),
+    Some("note_block"),
+    Some("red_bed"),
+    Some("powered_rail"),
+    Some("detector_rail"),
+    Some("sticky_piston"),
+    Some("cobweb"),
+    Some("grass"),
+    Some("dead_bush"),
+    Some("piston"),
+    Some("piston_head"),
+    Some("white_wool"),
+    Some("moving_piston"),
+    Some("dandelion"),
+    Some("poppy"),
+    Some("brown_mushroom"),
+    Some("red_mushroom"),
+    Some("gold_block"),
+    Some("iron_block"),
+    Some("smooth_stone_slab[type=double]"),
+    Some("smooth_stone_slab"),
+    Some("bricks"),
+    Some("tnt"),
+    Some("bookshelf"),
+    Some("mossy_cobblestone"),
+    Some("obsidian"),
+    Some("torch"),
+    Some("fire"),
+    Some("spawner"),
+    Some("oak_stairs"),
+    Some("chest"),
+    Some("redstone_wire"),
+    Some("diamond_ore"),
+    Some("diamond_block"),
+    Some("crafting_table"),
+    Some("wheat"),
+    Some("farmland"),
+    Some("furnace"),
+    Some("furnace[lit=true]"),
+    Some("oak_sign"),
+    Some("oak_door"),
+    Some("ladder"),
+    Some("rail"),
+    Some("cobblestone_stairs"),
+    Some("oak_wall_sign"),
+    Some("lever"),
+    Some("stone_pressure_plate"),
+    Some("iron_door"),
+    Some("oak_pressure_plate"),
+    Some("redstone_ore"),
+    Some("redstone_ore[lit=true]"),
+    Some("redstone_torch[lit=false]"),
+    Some("redstone_torch"),
+    Some("stone_button"),
+    Some("snow"),
+    Some("ice"),
+    Some("snow_block"),
+    Some("cactus"),
+    Some("clay"),
+    Some("sugar_cane"),
+    Some("jukebox"),
+    Some("oak_fence"),
+    Some("carved_pumpkin"),
+    Some("netherrack"),
+    Some("soul_sand"),
+    Some("glowstone"),
+    Some("nether_portal"),
+    Some("jack_o_lantern"),
+    Some("cake"),
+    Some("repeater"),
+    Some("repeater[powered=true]"),
+    Some("white_stained_glass"),
+    Some("oak_trapdoor"),
+    Some("infested_stone"),
+    Some("stone_bricks"),
+    Some("brown_mushroom_block"),
+    Some("red_mushroom_block"),
+    Some("iron_bars"),
+    Some("glass_pane"),
+    Some("melon"),
+    Some("pumpkin_stem"),
+    Some("melon_stem"),
+    Some("vine"),
+    Some("oak_fence_gate"),
+    Some("brick_stairs"),
+    Some("stone_brick_stairs"),
+    Some("mycelium"),
+    Some("lily_pad"),
+    Some("nether_bricks"),
+    Some("nether_brick_fence"),
+    Some("nether_brick_stairs"),
+    Some("nether_wart"),
+    Some("enchanting_table"),
+    Some("brewing_stand"),
+    Some("cauldron"),
+    Some("end_portal"),
+    Some("end_portal_frame"),
+    Some("end_stone"),
+    Some("dragon_egg"),
+    Some("redstone_lamp"),
+    Some("redstone_lamp[lit=true]"),
+    Some("oak_slab[type=double]"),
+    Some("oak_slab"),
+    Some("cocoa"),
+    Some("sandstone_stairs"),
+    Some("emerald_ore"),
+    Some("ender_chest"),
+    Some("tripwire_hook"),
+    Some("tripwire"),
+    Some("emerald_block"),
+    Some("spruce_stairs"),
+    Some("birch_stairs"),
+    Some("jungle_stairs"),
+    Some("command_block"),
+    Some("beacon"),
+    Some("cobblestone_wall"),
+    Some("flower_pot"),
+    Some("carrots"),
+    Some("potatoes"),
+    Some("oak_button"),
+    Some("skeleton_skull"),
+    Some("anvil"),
+    Some("trapped_chest"),
+    Some("light_weighted_pressure_plate"),
+    Some("heavy_weighted_pressure_plate"),
+    Some("comparator"),
+    Some("comparator[powered=true]"),
+    Some("daylight_detector"),
+    Some("redstone_block"),
+    Some("nether_quartz_ore"),
+    Some("hopper"),
+    Some("quartz_block"),
+    Some("quartz_stairs"),
+    Some("activator_rail"),
+    Some("dropper"),
+    Some("white_terracotta"),
+    Some("white_stained_glass_pane"),
+    Some("acacia_leaves"),
+    Some("acacia_log"),
+    Some("acacia_stairs"),
+    Some("dark_oak_stairs"),
+    Some("slime_block"),
+    Some("barrier"),
+    Some("iron_trapdoor"),
+    Some("prismarine"),
+    Some("sea_lantern"),
+    Some("hay_block"),
+    Some("white_carpet"),
+    Some("terracotta"),
+    Some("coal_block"),
+    Some("packed_ice"),
+    Some("sunflower"),
+    Some("white_banner"),
+    Some("white_wall_banner"),
+    Some("daylight_detector[inverted=true]"),
+    Some("red_sandstone"),
+    Some("red_sandstone_stairs"),
+    Some("red_sandstone_slab[type=double]"),
+    Some("red_sandstone_slab"),
+    Some("spruce_fence_gate"),
+    Some("birch_fence_gate"),
+    Some("jungle_fence_gate"),
+    Some("dark_oak_fence_gate"),
+    Some("acacia_fence_gate"),
+    Some("spruce_fence"),
+    Some("birch_fence"),
+    Some("jungle_fence"),
+    Some("dark_oak_fence"),
+    Some("acacia_fence"),
+    Some("spruce_door"),
+    Some("birch_door"),
+    Some("jungle_door"),
+    Some("acacia_door"),
+    Some("dark_oak_door"),
+    Some("end_rod"),
+    Some("chorus_plant"),
+    Some("chorus_flower"),
+    Some("purpur_block"),
+    Some("purpur_pillar"),
+    Some("purpur_stairs"),
+    Some("purpur_slab[type=double]"),
+    Some("purpur_slab"),
+    Some("end_stone_bricks"),
+    Some("beetroots"),
+    Some("dirt_path"),
+    Some("end_gateway"),
+    Some("repeating_command_block"),
+    Some("chain_command_block"),
+    Some("frosted_ice"),
+    Some("magma_block"),
+    Some("nether_wart_block"),
+    Some("red_nether_bricks"),
+    Some("bone_block"),
+    Some("structure_void"),
+    Some("observer"),
+    Some("white_shulker_box"),
+    Some("orange_shulker_box"),
+    Some("magenta_shulker_box"),
+    Some("light_blue_shulker_box"),
+    Some("yellow_shulker_box"),
+    Some("lime_shulker_box"),
+    Some("pink_shulker_box"),
+    Some("gray_shulker_box"),
+    Some("light_gray_shulker_box"),
+    Some("cyan_shulker_box"),
+    Some("purple_shulker_box"),
+    Some("blue_shulker_box"),
+    Some("brown_shulker_box"),
+    Some("green_shulker_box"),
+    Some("red_shulker_box"),
+    Some("black_shulker_box"),
+    Some("white_glazed_terracotta"),
+    Some("orange_glazed_terracotta"),
+    Some("magenta_glazed_terracotta"),
+    Some("light_blue_glazed_terracotta"),
+    Some("yellow_glazed_terracotta"),
+    Some("lime_glazed_terracotta"),
+    Some("pink_glazed_terracotta"),
+    Some("gray_glazed_terracotta"),
+    Some("light_gray_glazed_terracotta"),
+    Some("cyan_glazed_terracotta"),
+    Some("purple_glazed_terracotta"),
+    Some("blue_glazed_terracotta"),
+    Some("brown_glazed_terracotta"),
+    Some("green_glazed_terracotta"),
+    Some("red_glazed_terracotta"),
+    Some("black_glazed_terracotta"),
+    Some("white_concrete"),
+    Some("white_concrete_powder"),
+    None,
+    None,
+    Some("structure_block"),
+];
+
+/// Returns the variants which are encoded in the data value of the given id. The data value
+/// (masked by the returned mask) indexes into the returned list.
+fn variants(id: u8) -> Option<(u8, Vec<String>)> {
+    let list = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    let suffixed = |names: &[&str], suffix: &str| {
+        names
+            .iter()
+            .map(|name| format!("{name}_{suffix}"))
+            .collect()
+    };
+
+    Some(match id {
+        1 => (
+            0x7,
+            list(&[
+                "stone",
+                "granite",
+                "polished_granite",
+                "diorite",
+                "polished_diorite",
+                "andesite",
+                "polished_andesite",
+            ]),
+        ),
+        3 => (0x3, list(&["dirt", "coarse_dirt", "podzol"])),
+        5 => (0x7, suffixed(&WOODS, "planks")),
+        6 => (0x7, suffixed(&WOODS, "sapling")),
+        12 => (0x1, list(&["sand", "red_sand"])),
+        17 => (0x3, suffixed(&WOODS[..4], "log")),
+        18 => (0x3, suffixed(&WOODS[..4], "leaves")),
+        19 => (0x1, list(&["sponge", "wet_sponge"])),
+        24 => (
+            0x3,
+            list(&["sandstone", "chiseled_sandstone", "cut_sandstone"]),
+        ),
+        31 => (0x3, list(&["dead_bush", "grass", "fern"])),
+        38 => (
+            0xf,
+            list(&[
+                "poppy",
+                "blue_orchid",
+                "allium",
+                "azure_bluet",
+                "red_tulip",
+                "orange_tulip",
+                "white_tulip",
+                "pink_tulip",
+                "oxeye_daisy",
+            ]),
+        ),
+        43 | 44 => (
+            0x7,
+            [
+                "smooth_stone",
+                "sandstone",
+                "petrified_oak",
+                "cobblestone",
+                "brick",
+                "stone_brick",
+                "nether_brick",
+                "quartz",
+            ]
+            .iter()
+            .map(|name| match id {
+                43 => format!("{name}_slab[type=double]"),
+                _ => format!("{name}_slab"),
+            })
+            .collect(),
+        ),
+        35 => (0xf, suffixed(&COLORS, "wool")),
+        95 => (0xf, suffixed(&COLORS, "stained_glass")),
+        97 => (
+            0x7,
+            list(&[
+                "infested_stone",
+                "infested_cobblestone",
+                "infested_stone_bricks",
+                "infested_mossy_stone_bricks",
+                "infested_cracked_stone_bricks",
+                "infested_chiseled_stone_bricks",
+            ]),
+        ),
+        98 => (
+            0x3,
+            list(&[
+                "stone_bricks",
+                "mossy_stone_bricks",
+                "cracked_stone_bricks",
+                "chiseled_stone_bricks",
+            ]),
+        ),
+        125 => (
+            0x7,
+            WOODS
+                .iter()
+                .map(|wood| format!("{wood}_slab[type=double]"))
+                .collect(),
+        ),
+        126 => (0x7, suffixed(&WOODS, "slab")),
+        155 => (
+            0x3,
+            list(&["quartz_block", "chiseled_quartz_block", "quartz_pillar"]),
+        ),
+        159 => (0xf, suffixed(&COLORS, "terracotta")),
+        160 => (0xf, suffixed(&COLORS, "stained_glass_pane")),
+        161 => (0x1, suffixed(&WOODS[4..], "leaves")),
+        162 => (0x1, suffixed(&WOODS[4..], "log")),
+        168 => (
+            0x3,
+            list(&["prismarine", "prismarine_bricks", "dark_prismarine"]),
+        ),
+        171 => (0xf, suffixed(&COLORS, "carpet")),
+        175 => (
+            0x7,
+            list(&[
+                "sunflower",
+                "lilac",
+                "tall_grass",
+                "large_fern",
+                "rose_bush",
+                "peony",
+            ]),
+        ),
+        179 => (
+            0x3,
+            list(&[
+                "red_sandstone",
+                "chiseled_red_sandstone",
+                "cut_red_sandstone",
+            ]),
+        ),
+        251 => (0xf, suffixed(&COLORS, "concrete")),
+        252 => (0xf, suffixed(&COLORS, "concrete_powder")),
+        _ => return None,
+    })
+}
+
+/// Resolves the block state of the given legacy id and data value
+pub fn to_state(id: u8, data: u8) -> Option<String> {
+    let state = match variants(id) {
+        Some((mask, variants)) => variants.get((data & mask) as usize).cloned(),
+        None => None,
+    }
+    .or_else(|| BLOCKS[id as usize].map(str::to_owned))?;
+
+    Some(format!("minecraft:{state}"))
+}
+
+lazy_static::lazy_static! {
+    /// The reverse lookup from (property less) block states to their legacy id and data value
+    static ref LEGACY_IDS: HashMap<String, (u8, u8)> = {
+        let mut ids = HashMap::new();
+        for id in 0..=255u8 {
+            // flowing water and lava flatten into the same states as their still variants
+            if matches!(id, 8 | 10) {
+                continue;
+            }
+
+            if let Some((_, variants)) = variants(id) {
+                for (data, state) in variants.into_iter().enumerate() {
+                    ids.entry(format!("minecraft:{state}")).or_insert((id, data as u8));
+                }
+            }
+            if let Some(state) = BLOCKS[id as usize] {
+                ids.entry(format!("minecraft:{state}")).or_insert((id, 0));
+            }
+        }
+
+        ids
+    };
+}
+
+/// Resolves the legacy id and data value of the given block state. The exact state is preferred,
+/// otherwise the properties are ignored.
+pub fn from_state(state: &str) -> Option<(u8, u8)> {
+    LEGACY_IDS.get(state).copied().or_else(|| {
+        state
+            .split_once('[')
+            .and_then(|(name, _)| LEGACY_IDS.get(name).copied())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::legacy::{from_state, to_state};
+
+    #[test]
+    fn test_mapping() {
+        assert_eq!(Some("minecraft:red_wool".to_owned()), to_state(35, 14));
+        assert_eq!(Some("minecraft:birch_planks".to_owned()), to_state(5, 2));
+        assert_eq!(Some("minecraft:oak_stairs".to_owned()), to_state(53, 3));
+        assert_eq!(None, to_state(253, 0));
+
+        assert_eq!(Some((35, 14)), from_state("minecraft:red_wool"));
+        assert_eq!(Some((9, 0)), from_state("minecraft:water"));
+        assert_eq!(Some((62, 0)), from_state("minecraft:furnace[lit=true]"));
+        assert_eq!(
+            Some((61, 0)),
+            from_state("minecraft:furnace[facing=east,lit=false]")
+        );
+        assert_eq!(None, from_state("minecraft:cherry_planks"));
+    }
+}
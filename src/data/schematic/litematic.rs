@@ -0,0 +1,401 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::schematic::format::{
+    invalid, split_state, volume, BlockEntity, Compound, CompoundExt, Structure, AIR,
+};
+use crate::prelude::*;
+use chrono::Utc;
+use fastnbt::{LongArray, Value};
+
+/// The litematic version we write
+const VERSION: i32 = 6;
+
+/// Reads a litematic file by merging all of its regions into a single structure
+pub fn read(root: Compound) -> Result<Structure> {
+    let data_version = root.int("MinecraftDataVersion").unwrap_or(1631) as i32;
+    let regions = root
+        .compound("Regions")?
+        .values()
+        .map(|region| match region {
+            Value::Compound(region) => Region::read(region),
+            _ => Err(invalid("region is not a compound")),
+        })
+        .collect::<Result<Vec<Region>>>()?;
+    if regions.is_empty() {
+        return Err(invalid("litematic without any regions"));
+    }
+
+    // compute the enclosing box of all regions
+    let min = (0..3)
+        .map(|axis| regions.iter().map(|region| region.min[axis]).min().unwrap())
+        .collect::<Vec<i64>>();
+    let max = (0..3)
+        .map(|axis| {
+            regions
+                .iter()
+                .map(|region| region.min[axis].saturating_add(region.size[axis]))
+                .max()
+                .unwrap()
+        })
+        .collect::<Vec<i64>>();
+    let size = (0..3)
+        .map(|axis| max[axis].saturating_sub(min[axis]))
+        .collect::<Vec<i64>>();
+    let ([width, height, length], _) = volume(size[0], size[1], size[2])?;
+
+    let mut structure = Structure::empty(width, height, length, data_version)?;
+    for region in regions {
+        let offset = (0..3)
+            .map(|axis| (region.min[axis] - min[axis]) as usize)
+            .collect::<Vec<usize>>();
+        let [width, height, length] = region.size.map(|value| value as usize);
+        let palette = region
+            .palette
+            .iter()
+            .map(|state| structure.palette_index(state))
+            .collect::<Vec<u32>>();
+        let bits = bits(region.palette.len());
+
+        for y in 0..height {
+            for z in 0..length {
+                for x in 0..width {
+                    let value = unpack(&region.states, bits, (y * length + z) * width + x)?;
+                    let state = *palette
+                        .get(value as usize)
+                        .ok_or_else(|| invalid("block states reference unknown palette entries"))?;
+                    let index = structure.index(x + offset[0], y + offset[1], z + offset[2]);
+                    structure.blocks[index] = state;
+                }
+            }
+        }
+
+        for mut entity in region.block_entities {
+            for (axis, value) in entity.position.iter_mut().enumerate() {
+                *value += offset[axis] as i32;
+            }
+            structure.block_entities.push(entity);
+        }
+    }
+
+    Ok(structure)
+}
+
+/// Writes the structure as a litematic with a single region
+pub fn write(structure: &Structure, name: &str) -> Result<Compound> {
+    // litematica expects air to be the first palette entry
+    let mut palette = vec![AIR];
+    palette.extend(
+        structure
+            .palette
+            .iter()
+            .map(String::as_str)
+            .filter(|state| !state.eq(&AIR)),
+    );
+    let mapping = structure
+        .palette
+        .iter()
+        .map(|state| palette.iter().position(|entry| entry.eq(state)).unwrap() as u64)
+        .collect::<Vec<u64>>();
+
+    let bits = bits(palette.len());
+    let mut states = vec![0i64; (structure.volume() * bits as usize).div_ceil(64)];
+    for (index, block) in structure.blocks.iter().enumerate() {
+        pack(&mut states, bits, index, mapping[*block as usize]);
+    }
+
+    let size = vector(
+        structure.width as i32,
+        structure.height as i32,
+        structure.length as i32,
+    );
+    let total_blocks = structure
+        .blocks
+        .iter()
+        .filter(|block| mapping[**block as usize] != 0)
+        .count();
+    let now = Utc::now().timestamp_millis();
+
+    let region = Compound::from([
+        ("Position".to_owned(), vector(0, 0, 0)),
+        ("Size".to_owned(), size.clone()),
+        (
+            "BlockStatePalette".to_owned(),
+            Value::List(palette.iter().map(|state| write_state(state)).collect()),
+        ),
+        (
+            "BlockStates".to_owned(),
+            Value::LongArray(LongArray::new(states)),
+        ),
+        (
+            "TileEntities".to_owned(),
+            Value::List(
+                structure
+                    .block_entities
+                    .iter()
+                    .map(|entity| {
+                        let mut compound = entity.data.clone();
+                        compound.insert("id".to_owned(), Value::String(entity.id.clone()));
+                        compound.insert("x".to_owned(), Value::Int(entity.position[0]));
+                        compound.insert("y".to_owned(), Value::Int(entity.position[1]));
+                        compound.insert("z".to_owned(), Value::Int(entity.position[2]));
+                        Value::Compound(compound)
+                    })
+                    .collect(),
+            ),
+        ),
+        ("Entities".to_owned(), Value::List(Vec::new())),
+        ("PendingBlockTicks".to_owned(), Value::List(Vec::new())),
+        ("PendingFluidTicks".to_owned(), Value::List(Vec::new())),
+    ]);
+    let metadata = Compound::from([
+        ("Name".to_owned(), Value::String(name.to_owned())),
+        ("Author".to_owned(), Value::String(String::new())),
+        ("Description".to_owned(), Value::String(String::new())),
+        ("RegionCount".to_owned(), Value::Int(1)),
+        ("TotalBlocks".to_owned(), Value::Int(total_blocks as i32)),
+        (
+            "TotalVolume".to_owned(),
+            Value::Int(structure.volume() as i32),
+        ),
+        ("EnclosingSize".to_owned(), size),
+        ("TimeCreated".to_owned(), Value::Long(now)),
+        ("TimeModified".to_owned(), Value::Long(now)),
+    ]);
+
+    Ok(Compound::from([
+        ("Version".to_owned(), Value::Int(VERSION)),
+        ("SubVersion".to_owned(), Value::Int(1)),
+        (
+            "MinecraftDataVersion".to_owned(),
+            Value::Int(structure.data_version),
+        ),
+        ("Metadata".to_owned(), Value::Compound(metadata)),
+        (
+            "Regions".to_owned(),
+            Value::Compound(Compound::from([(name.to_owned(), Value::Compound(region))])),
+        ),
+    ]))
+}
+
+struct Region {
+    /// the minimum corner of the region
+    min: [i64; 3],
+    /// the absolute size of the region
+    size: [i64; 3],
+    palette: Vec<String>,
+    states: Vec<i64>,
+    block_entities: Vec<BlockEntity>,
+}
+
+impl Region {
+    fn read(region: &Compound) -> Result<Self> {
+        let position = read_vector(region.compound("Position")?)?;
+        let size = read_vector(region.compound("Size")?)?;
+        // negative sizes extend from the position into the negative direction
+        let min = [0, 1, 2].map(|axis| {
+            if size[axis] < 0 {
+                position[axis].saturating_add(size[axis]).saturating_add(1)
+            } else {
+                position[axis]
+            }
+        });
+        let size = size.map(i64::saturating_abs);
+
+        let palette = region
+            .list("BlockStatePalette")?
+            .iter()
+            .map(read_state)
+            .collect::<Result<Vec<String>>>()?;
+        if palette.is_empty() {
+            return Err(invalid("region with an empty palette"));
+        }
+        let states = match region.value("BlockStates")? {
+            Value::LongArray(states) => states.to_vec(),
+            _ => return Err(invalid("block states are not a long array")),
+        };
+        let (_, volume) = volume(size[0], size[1], size[2])?;
+        let required = (volume * bits(palette.len()) as usize).div_ceil(64);
+        if states.len() < required {
+            return Err(invalid("block states are truncated"));
+        }
+
+        let block_entities = match region.get("TileEntities") {
+            Some(Value::List(entities)) => entities
+                .iter()
+                .filter_map(|entity| match entity {
+                    Value::Compound(entity) => Some(read_block_entity(entity.clone())),
+                    _ => None,
+                })
+                .collect::<Result<Vec<BlockEntity>>>()?,
+            _ => Vec::new(),
+        };
+
+        Ok(Self {
+            min,
+            size,
+            palette,
+            states,
+            block_entities,
+        })
+    }
+}
+
+fn read_block_entity(mut compound: Compound) -> Result<BlockEntity> {
+    let mut position = [0; 3];
+    for (axis, key) in ["x", "y", "z"].iter().enumerate() {
+        position[axis] = compound
+            .remove(*key)
+            .and_then(|value| value.as_i64())
+            .ok_or_else(|| invalid("block entity without a valid position"))?
+            as i32;
+    }
+    let id = compound
+        .remove("id")
+        .and_then(|id| id.as_str().map(str::to_owned))
+        .unwrap_or_default();
+
+    Ok(BlockEntity {
+        position,
+        id,
+        data: compound,
+    })
+}
+
+fn read_vector(vector: &Compound) -> Result<[i64; 3]> {
+    Ok([vector.int("x")?, vector.int("y")?, vector.int("z")?])
+}
+
+fn vector(x: i32, y: i32, z: i32) -> Value {
+    Value::Compound(Compound::from([
+        ("x".to_owned(), Value::Int(x)),
+        ("y".to_owned(), Value::Int(y)),
+        ("z".to_owned(), Value::Int(z)),
+    ]))
+}
+
+fn read_state(value: &Value) -> Result<String> {
+    let compound = match value {
+        Value::Compound(compound) => compound,
+        _ => return Err(invalid("palette entry is not a compound")),
+    };
+    let name = compound.string("Name")?;
+
+    match compound.get("Properties") {
+        Some(Value::Compound(properties)) if !properties.is_empty() => {
+            let mut properties = properties
+                .iter()
+                .map(|(key, value)| {
+                    value
+                        .as_str()
+                        .map(|value| format!("{key}={value}"))
+                        .ok_or_else(|| invalid("block property is not a string"))
+                })
+                .collect::<Result<Vec<String>>>()?;
+            properties.sort();
+
+            Ok(format!("{name}[{}]", properties.join(",")))
+        }
+        _ => Ok(name.to_owned()),
+    }
+}
+
+fn write_state(state: &str) -> Value {
+    let (name, properties) = split_state(state);
+
+    let mut compound = Compound::from([("Name".to_owned(), Value::String(name.to_owned()))]);
+    if !properties.is_empty() {
+        compound.insert(
+            "Properties".to_owned(),
+            Value::Compound(
+                properties
+                    .into_iter()
+                    .map(|(key, value)| (key.to_owned(), Value::String(value.to_owned())))
+                    .collect(),
+            ),
+        );
+    }
+
+    Value::Compound(compound)
+}
+
+/// The amount of bits per entry litematica uses for a palette of the given size
+fn bits(palette: usize) -> u32 {
+    (usize::BITS - (palette.max(1) - 1).leading_zeros()).max(2)
+}
+
+fn unpack(states: &[i64], bits: u32, index: usize) -> Result<u64> {
+    let start = index as u64 * bits as u64;
+    let (long, offset) = ((start / 64) as usize, start % 64);
+    let mask = (1u64 << bits) - 1;
+
+    let mut value = *states
+        .get(long)
+        .ok_or_else(|| invalid("block states are truncated"))? as u64
+        >> offset;
+    // the value may span two longs
+    if offset + bits as u64 > 64 {
+        let next = *states
+            .get(long + 1)
+            .ok_or_else(|| invalid("block states are truncated"))? as u64;
+        value |= next << (64 - offset);
+    }
+
+    Ok(value & mask)
+}
+
+fn pack(states: &mut [i64], bits: u32, index: usize, value: u64) {
+    let start = index as u64 * bits as u64;
+    let (long, offset) = ((start / 64) as usize, start % 64);
+
+    states[long] = (states[long] as u64 | value << offset) as i64;
+    if offset + bits as u64 > 64 {
+        states[long + 1] = (states[long + 1] as u64 | value >> (64 - offset)) as i64;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::litematic::{bits, pack, unpack};
+
+    #[test]
+    fn test_packing() {
+        assert_eq!(2, bits(1));
+        assert_eq!(2, bits(4));
+        assert_eq!(3, bits(5));
+        assert_eq!(5, bits(17));
+
+        let bits = 5;
+        let values = (0..100).map(|value| value % 31).collect::<Vec<u64>>();
+        let mut states = vec![0i64; (values.len() * bits as usize).div_ceil(64)];
+        for (index, value) in values.iter().enumerate() {
+            pack(&mut states, bits, index, *value);
+        }
+        for (index, value) in values.iter().enumerate() {
+            assert_eq!(*value, unpack(&states, bits, index).unwrap());
+        }
+    }
+}
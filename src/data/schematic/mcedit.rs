@@ -0,0 +1,187 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::schematic::format::{
+    invalid, volume, BlockEntity, Compound, CompoundExt, Structure,
+};
+use crate::data::schematic::legacy;
+use crate::prelude::*;
+use fastnbt::{ByteArray, Value};
+use std::collections::HashMap;
+
+/// The data version of the states the legacy ids are mapped to (1.20.1)
+const DATA_VERSION: i32 = 3465;
+
+/// Reads a legacy MCEdit schematic by mapping its numeric ids onto block states
+pub fn read(root: Compound) -> Result<Structure> {
+    if let Ok(materials) = root.string("Materials") {
+        if !materials.eq("Alpha") {
+            return Err(ApplicationError::BadRequest(format!(
+                "unsupported schematic materials `{materials}`"
+            )));
+        }
+    }
+
+    let width = root.dimension("Width")?;
+    let height = root.dimension("Height")?;
+    let length = root.dimension("Length")?;
+    let ([width, height, length], volume) = volume(width, height, length)?;
+
+    let blocks = byte_array(&root, "Blocks")?;
+    let data = byte_array(&root, "Data")?;
+    if blocks.len() != volume || data.len() != volume {
+        return Err(invalid("block data does not match the dimensions"));
+    }
+    // ids above 255 are not supported by the legacy mapping
+    if let Some(Value::ByteArray(add)) = root.get("AddBlocks") {
+        if add.iter().any(|value| *value != 0) {
+            return Err(ApplicationError::BadRequest(
+                "block ids above 255 are not supported".to_owned(),
+            ));
+        }
+    }
+
+    let mut structure = Structure::empty(width, height, length, DATA_VERSION)?;
+    // every legacy id is only mapped once
+    let mut states = HashMap::new();
+    for index in 0..volume {
+        let (id, data) = (blocks[index] as u8, data[index] as u8);
+        let state = match states.get(&(id, data)) {
+            Some(state) => *state,
+            None => {
+                let state = legacy::to_state(id, data).ok_or_else(|| {
+                    ApplicationError::BadRequest(format!("unknown legacy block id {id}:{data}"))
+                })?;
+                let state = structure.palette_index(state.as_str());
+                states.insert((id, data), state);
+                state
+            }
+        };
+        structure.blocks[index] = state;
+    }
+
+    if let Ok(entities) = root.list("TileEntities") {
+        for entity in entities {
+            if let Value::Compound(entity) = entity {
+                structure
+                    .block_entities
+                    .push(read_block_entity(entity.clone())?);
+            }
+        }
+    }
+
+    Ok(structure)
+}
+
+/// Writes the structure as legacy MCEdit schematic. Fails if any block does not exist prior to 1.13.
+pub fn write(structure: &Structure) -> Result<Compound> {
+    // unused palette entries must not fail the export
+    let mut used = vec![false; structure.palette.len()];
+    structure
+        .blocks
+        .iter()
+        .for_each(|block| used[*block as usize] = true);
+
+    let palette = structure
+        .palette
+        .iter()
+        .zip(used)
+        .map(|(state, used)| match legacy::from_state(state) {
+            Some(ids) => Ok(ids),
+            None if !used => Ok((0, 0)),
+            None => Err(ApplicationError::BadRequest(format!(
+                "`{state}` has no legacy block id and can't be exported as .schematic"
+            ))),
+        })
+        .collect::<Result<Vec<(u8, u8)>>>()?;
+
+    let (blocks, data): (Vec<i8>, Vec<i8>) = structure
+        .blocks
+        .iter()
+        .map(|block| {
+            let (id, data) = palette[*block as usize];
+            (id as i8, data as i8)
+        })
+        .unzip();
+
+    Ok(Compound::from([
+        ("Width".to_owned(), Value::Short(structure.width as i16)),
+        ("Height".to_owned(), Value::Short(structure.height as i16)),
+        ("Length".to_owned(), Value::Short(structure.length as i16)),
+        ("Materials".to_owned(), Value::String("Alpha".to_owned())),
+        (
+            "Blocks".to_owned(),
+            Value::ByteArray(ByteArray::new(blocks)),
+        ),
+        ("Data".to_owned(), Value::ByteArray(ByteArray::new(data))),
+        ("Entities".to_owned(), Value::List(Vec::new())),
+        (
+            "TileEntities".to_owned(),
+            Value::List(
+                structure
+                    .block_entities
+                    .iter()
+                    .map(|entity| {
+                        let mut compound = entity.data.clone();
+                        compound.insert("id".to_owned(), Value::String(entity.id.clone()));
+                        compound.insert("x".to_owned(), Value::Int(entity.position[0]));
+                        compound.insert("y".to_owned(), Value::Int(entity.position[1]));
+                        compound.insert("z".to_owned(), Value::Int(entity.position[2]));
+                        Value::Compound(compound)
+                    })
+                    .collect(),
+            ),
+        ),
+    ]))
+}
+
+fn byte_array<'a>(root: &'a Compound, key: &str) -> Result<&'a ByteArray> {
+    match root.value(key)? {
+        Value::ByteArray(array) => Ok(array),
+        _ => Err(invalid(format!("`{key}` is not a byte array").as_str())),
+    }
+}
+
+fn read_block_entity(mut compound: Compound) -> Result<BlockEntity> {
+    let mut position = [0; 3];
+    for (axis, key) in ["x", "y", "z"].iter().enumerate() {
+        position[axis] = compound
+            .remove(*key)
+            .and_then(|value| value.as_i64())
+            .ok_or_else(|| invalid("block entity without a valid position"))?
+            as i32;
+    }
+    let id = compound
+        .remove("id")
+        .and_then(|id| id.as_str().map(str::to_owned))
+        .ok_or_else(|| invalid("block entity without an id"))?;
+
+    Ok(BlockEntity {
+        position,
+        id,
+        data: compound,
+    })
+}
@@ -0,0 +1,492 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::schematic::format::Structure;
+use crate::data::schematic::quota::SchematicQuota;
+use crate::data::schematic::version::{SchematicVersion, SchematicVersionEntry};
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+pub mod format;
+mod legacy;
+mod litematic;
+mod mcedit;
+pub mod quota;
+pub mod search;
+pub mod share;
+mod sponge;
+pub mod version;
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Getters)]
+#[get = "pub"]
+pub struct Schematic {
+    id: Id,
+    /// the name of the schematic
+    name: String,
+    /// the base64 encoded schematic file (sponge format)
+    data: String,
+    /// the minecraft uuid
+    owner: String,
+    /// the current version number
+    #[serde(default = "default_version")]
+    version: u32,
+    /// the size of the decoded file in bytes
+    #[serde(default)]
+    size: u64,
+    /// the minecraft uuid of the uploader of the current version
+    uploader: Option<String>,
+    #[serde(flatten)]
+    metadata: SchematicMetadata,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+    #[serde(alias = "updated_at")]
+    updated_at: Option<DateTime<Utc>>,
+}
+
+fn default_version() -> u32 {
+    1
+}
+
+/// The searchable properties of the current version of a schematic
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+pub struct SchematicMetadata {
+    #[serde(default)]
+    pub width: u16,
+    #[serde(default)]
+    pub height: u16,
+    #[serde(default)]
+    pub length: u16,
+    /// the amount of non-air blocks
+    #[serde(default)]
+    pub blocks: u64,
+}
+
+impl From<&Structure> for SchematicMetadata {
+    fn from(structure: &Structure) -> Self {
+        Self {
+            width: structure.width,
+            height: structure.height,
+            length: structure.length,
+            blocks: structure.block_count(),
+        }
+    }
+}
+
+impl SchematicMetadata {
+    /// Reads the metadata of the given base64 encoded file
+    pub fn read(data: &str) -> Result<Self> {
+        let decoded = openssl::base64::decode_block(data)
+            .map_err(|_| ApplicationError::InternalServerError)?;
+        let (structure, _) = Structure::read(decoded.as_slice())?;
+
+        Ok(Self::from(&structure))
+    }
+}
+
+/// The maximum amount of tags per schematic
+const MAX_TAGS: usize = 16;
+/// The maximum length of a single tag
+const MAX_TAG_LENGTH: usize = 32;
+
+impl Schematic {
+    /// Fetch a schematic by its id.
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Option<Self>> {
+        let schematic: Option<Schematic> =
+            sql_span!(connection.select(&Id::try_from(("schematic", id))?).await?);
+
+        Ok(schematic)
+    }
+
+    /// Fetch a schematic by its owner and name.
+    #[instrument(skip(connection))]
+    pub async fn from_name(
+        owner: &str,
+        name: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<Option<Self>> {
+        let schematic = sql_span!(connection
+            .query("SELECT * FROM schematic WHERE owner = $owner AND name = $name")
+            .bind(("owner", owner))
+            .bind(("name", name))
+            .await?
+            .take::<Option<Schematic>>(0)?);
+
+        Ok(schematic)
+    }
+
+    /// Uploads the given (sponge) file. If the owner already has a schematic with the given name,
+    /// a new version of it will be created. Fails if the upload would exceed the given quota.
+    #[instrument(skip(data, metadata, quota, connection))]
+    pub async fn upload(
+        owner: &str,
+        name: &str,
+        data: &[u8],
+        metadata: SchematicMetadata,
+        quota: &SchematicQuota,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let encoded = openssl::base64::encode_block(data);
+        let size = data.len() as u64;
+
+        match Self::from_name(owner, name, connection).await? {
+            Some(mut schematic) => {
+                quota.check(size, false)?;
+                schematic
+                    .replace(encoded, size, metadata, owner, connection)
+                    .await?;
+                Ok(schematic)
+            }
+            None => {
+                quota.check(size, true)?;
+                let schematic: Schematic = sql_span!(
+                    connection
+                        .create("schematic")
+                        .content(&serde_json::json! ({
+                            "owner": owner,
+                            "uploader": owner,
+                            "data": encoded,
+                            "size": size,
+                            "name": name,
+                            "width": metadata.width,
+                            "height": metadata.height,
+                            "length": metadata.length,
+                            "blocks": metadata.blocks
+                        }))
+                        .await?
+                );
+
+                Ok(schematic)
+            }
+        }
+    }
+
+    /// Archives the current version and replaces it with the given (base64 encoded) data
+    #[instrument(skip_all)]
+    async fn replace(
+        &mut self,
+        data: String,
+        size: u64,
+        metadata: SchematicMetadata,
+        uploader: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query(
+                "CREATE schematic_version SET schematic = $schematic, version = $version, \
+                data = $data, uploader = $uploader, size = $size, created_at = $created_at",
+            )
+            .bind(("schematic", self.id.to_thing()))
+            .bind(("version", self.version))
+            .bind(("data", self.data.as_str()))
+            .bind((
+                "uploader",
+                self.uploader.as_deref().unwrap_or(self.owner.as_str()),
+            ))
+            .bind(("size", self.size))
+            .bind(("created_at", self.updated_at.unwrap_or(self.created_at)))
+            .query(
+                "UPDATE $schematic SET data = $new_data, size = $new_size, \
+                uploader = $new_uploader, version = $new_version, updated_at = time::now(), \
+                width = $width, height = $height, length = $length, blocks = $blocks",
+            )
+            .bind(("new_data", data.as_str()))
+            .bind(("width", metadata.width))
+            .bind(("height", metadata.height))
+            .bind(("length", metadata.length))
+            .bind(("blocks", metadata.blocks))
+            .bind(("new_size", size))
+            .bind(("new_uploader", uploader))
+            .bind(("new_version", self.version + 1))
+            .query("COMMIT TRANSACTION")
+            .await?
+            .check()?);
+
+        self.data = data;
+        self.size = size;
+        self.metadata = metadata;
+        self.uploader = Some(uploader.to_owned());
+        self.version += 1;
+        self.updated_at = Some(Utc::now());
+
+        self.prune(connection).await
+    }
+
+    /// Deletes the oldest versions exceeding the configured retention limit
+    #[instrument(skip_all)]
+    async fn prune(&self, connection: &DatabaseConnection) -> Result<()> {
+        // the current version counts towards the limit as well
+        let keep = SCHEMATIC_VERSION_LIMIT.saturating_sub(1);
+        let versions = self.history(connection).await?;
+
+        if let Some(threshold) = versions.get(keep) {
+            sql_span!(connection
+                .query(
+                    "DELETE schematic_version WHERE schematic = $schematic AND version <= $version"
+                )
+                .bind(("schematic", self.id.to_thing()))
+                .bind(("version", threshold.version))
+                .await?
+                .check()?);
+        }
+
+        Ok(())
+    }
+
+    /// Lists the previous versions, newest first
+    #[instrument(skip_all)]
+    async fn history(&self, connection: &DatabaseConnection) -> Result<Vec<SchematicVersionEntry>> {
+        let versions = sql_span!(connection
+            .query(
+                "SELECT version, uploader, size, created_at FROM schematic_version \
+                WHERE schematic = $schematic ORDER BY version DESC"
+            )
+            .bind(("schematic", self.id.to_thing()))
+            .await?
+            .take::<Vec<SchematicVersionEntry>>(0)?);
+
+        Ok(versions)
+    }
+
+    /// Lists all versions including the current one, newest first
+    #[instrument(skip_all)]
+    pub async fn versions(
+        &self,
+        connection: &DatabaseConnection,
+    ) -> Result<Vec<SchematicVersionEntry>> {
+        let mut versions = vec![SchematicVersionEntry {
+            version: self.version,
+            uploader: self.uploader.clone().unwrap_or(self.owner.clone()),
+            size: self.size,
+            current: true,
+            created_at: self.updated_at.unwrap_or(self.created_at),
+        }];
+        versions.append(&mut self.history(connection).await?);
+
+        Ok(versions)
+    }
+
+    /// Fetch a previous version by its number
+    #[instrument(skip(self, connection))]
+    pub async fn fetch_version(
+        &self,
+        version: u32,
+        connection: &DatabaseConnection,
+    ) -> Result<Option<SchematicVersion>> {
+        let version = sql_span!(connection
+            .query("SELECT * FROM schematic_version WHERE schematic = $schematic AND version = $version")
+            .bind(("schematic", self.id.to_thing()))
+            .bind(("version", version))
+            .await?
+            .take::<Option<SchematicVersion>>(0)?);
+
+        Ok(version)
+    }
+
+    /// Get the base64 encoded data of the given version, which may be the current one
+    pub async fn version_data(
+        &self,
+        version: u32,
+        connection: &DatabaseConnection,
+    ) -> Result<String> {
+        if version == self.version {
+            return Ok(self.data.clone());
+        }
+
+        match self.fetch_version(version, connection).await? {
+            Some(version) => Ok(version.data().clone()),
+            None => Err(ApplicationError::BadRequest("version not found".to_owned())),
+        }
+    }
+
+    /// Restores the given version by uploading it as a new version
+    #[instrument(skip(self, quota, connection))]
+    pub async fn restore(
+        &mut self,
+        version: u32,
+        uploader: &str,
+        quota: &SchematicQuota,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        if version == self.version {
+            return Err(ApplicationError::BadRequest(
+                "version is already the current version".to_owned(),
+            ));
+        }
+
+        match self.fetch_version(version, connection).await? {
+            Some(version) => {
+                quota.check(*version.size(), false)?;
+                let metadata = SchematicMetadata::read(version.data())?;
+                self.replace(
+                    version.data().clone(),
+                    *version.size(),
+                    metadata,
+                    uploader,
+                    connection,
+                )
+                .await
+            }
+            None => Err(ApplicationError::BadRequest("version not found".to_owned())),
+        }
+    }
+
+    /// Deletes a previous version. The current version can't be deleted.
+    #[instrument(skip(self, connection))]
+    pub async fn delete_version(
+        &self,
+        version: u32,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        if version == self.version {
+            return Err(ApplicationError::BadRequest(
+                "the current version can't be deleted".to_owned(),
+            ));
+        }
+
+        match self.fetch_version(version, connection).await? {
+            Some(version) => {
+                let _: SchematicVersion = sql_span!(connection.delete(version.id()).await?);
+                Ok(())
+            }
+            None => Err(ApplicationError::BadRequest("version not found".to_owned())),
+        }
+    }
+
+    /// Replaces the tags of the schematic. Tags are trimmed, lowercased and deduplicated.
+    #[instrument(skip(self, connection))]
+    pub async fn set_tags(
+        &mut self,
+        tags: &[String],
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        let mut normalised: Vec<String> = Vec::new();
+        for tag in tags {
+            let tag = tag.trim().to_lowercase();
+            if tag.is_empty()
+                || tag.chars().count() > MAX_TAG_LENGTH
+                || !tag
+                    .chars()
+                    .all(|char| char.is_alphanumeric() || char == '-' || char == '_')
+            {
+                return Err(ApplicationError::BadRequest(format!(
+                    "tags have to consist of 1 to {MAX_TAG_LENGTH} alphanumeric characters, dashes \
+                    or underscores"
+                )));
+            }
+            if !normalised.contains(&tag) {
+                normalised.push(tag);
+            }
+        }
+        if normalised.len() > MAX_TAGS {
+            return Err(ApplicationError::BadRequest(format!(
+                "a schematic can have at most {MAX_TAGS} tags"
+            )));
+        }
+
+        sql_span!(connection
+            .query("UPDATE $schematic SET tags = $tags")
+            .bind(("schematic", self.id.to_thing()))
+            .bind(("tags", &normalised))
+            .await?
+            .check()?);
+
+        self.tags = normalised;
+        Ok(())
+    }
+
+    /// Lists all tags used on the schematics of the given owner, sorted alphabetically
+    #[instrument(skip(connection))]
+    pub async fn owner_tags(owner: &str, connection: &DatabaseConnection) -> Result<Vec<String>> {
+        let tags = sql_span!(connection
+            .query("SELECT VALUE tags FROM schematic WHERE owner = $owner")
+            .bind(("owner", owner))
+            .await?
+            .take::<Vec<Vec<String>>>(0)?);
+
+        let mut tags = tags.into_iter().flatten().collect::<Vec<String>>();
+        tags.sort();
+        tags.dedup();
+        Ok(tags)
+    }
+
+    /// Computes the metadata of schematics uploaded before it has been tracked
+    #[instrument(skip_all)]
+    pub async fn backfill_metadata(connection: &DatabaseConnection) -> Result<()> {
+        #[derive(Deserialize)]
+        struct Pending {
+            id: Id,
+            data: String,
+        }
+
+        let pending = sql_span!(connection
+            .query("SELECT id, data FROM schematic WHERE blocks IS NONE")
+            .await?
+            .take::<Vec<Pending>>(0)?);
+
+        for schematic in pending {
+            // unreadable files are stored with empty metadata to not retry them on every start
+            let metadata =
+                SchematicMetadata::read(schematic.data.as_str()).unwrap_or_else(|error| {
+                    warn!(
+                        "Failed to read schematic {}: {error}",
+                        schematic.id.to_string()
+                    );
+                    SchematicMetadata::default()
+                });
+
+            sql_span!(connection
+                .query("UPDATE $schematic SET width = $width, height = $height, length = $length, blocks = $blocks")
+                .bind(("schematic", schematic.id.to_thing()))
+                .bind(("width", metadata.width))
+                .bind(("height", metadata.height))
+                .bind(("length", metadata.length))
+                .bind(("blocks", metadata.blocks))
+                .await?
+                .check()?);
+        }
+
+        Ok(())
+    }
+
+    /// Deletes the schematic including all of its versions and share links
+    #[instrument(skip_all)]
+    pub async fn delete(self, connection: &DatabaseConnection) -> Result<()> {
+        sql_span!(connection
+            .query("DELETE schematic_version WHERE schematic = $schematic")
+            .query("DELETE schematic_share_access WHERE share.schematic = $schematic")
+            .query("DELETE schematic_share WHERE schematic = $schematic")
+            .query("DELETE $schematic")
+            .bind(("schematic", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::account::Account;
+use crate::prelude::*;
+
+/// The schematic limits and the current usage of an account. The limits default to the configured
+/// values, but may be raised for single accounts (e.g. trusted builders).
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchematicQuota {
+    /// the maximum amount of schematics
+    pub max_count: u64,
+    /// the maximum amount of stored bytes (including previous versions)
+    pub max_size: u64,
+    /// the current amount of schematics
+    pub count: u64,
+    /// the currently stored bytes (including previous versions)
+    pub size: u64,
+}
+
+/// The per account overrides of the default limits
+#[derive(Deserialize, Serialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct QuotaOverride {
+    /// the maximum amount of schematics, `null` falls back to the default
+    #[serde(alias = "max_count")]
+    pub max_count: Option<u64>,
+    /// the maximum amount of stored bytes, `null` falls back to the default
+    #[serde(alias = "max_size")]
+    pub max_size: Option<u64>,
+}
+
+#[derive(Deserialize, Default)]
+struct Usage {
+    #[serde(default)]
+    count: u64,
+    #[serde(default)]
+    size: u64,
+}
+
+impl SchematicQuota {
+    /// Fetch the limits and the usage of the given account
+    #[instrument(skip_all)]
+    pub async fn fetch(account: &Account, connection: &DatabaseConnection) -> Result<Self> {
+        let limits: Option<QuotaOverride> = sql_span!(
+            connection
+                .select(("schematic_quota", account.id().id.as_str()))
+                .await?
+        );
+        let limits = limits.unwrap_or_default();
+
+        let (count, size) = match account.uuid() {
+            Some(uuid) => {
+                let mut response = sql_span!(connection
+                    .query(
+                        "SELECT count() AS count, math::sum(size) AS size FROM schematic \
+                        WHERE owner = $owner GROUP ALL"
+                    )
+                    .query(
+                        "SELECT math::sum(size) AS size FROM schematic_version \
+                        WHERE schematic.owner = $owner GROUP ALL"
+                    )
+                    .bind(("owner", uuid))
+                    .await?
+                    .check()?);
+                let current = response.take::<Option<Usage>>(0)?.unwrap_or_default();
+                let history = response.take::<Option<Usage>>(1)?.unwrap_or_default();
+
+                (current.count, current.size + history.size)
+            }
+            None => (0, 0),
+        };
+
+        Ok(Self {
+            max_count: limits.max_count.unwrap_or(*SCHEMATIC_COUNT_LIMIT),
+            max_size: limits.max_size.unwrap_or(*SCHEMATIC_SIZE_LIMIT),
+            count,
+            size,
+        })
+    }
+
+    /// Overrides the limits of the given account
+    #[instrument(skip(connection))]
+    pub async fn set(
+        account: &Account,
+        limits: &QuotaOverride,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let _: Option<QuotaOverride> = sql_span!(
+            connection
+                .update(("schematic_quota", account.id().id.as_str()))
+                .content(&serde_json::json!({
+                    "max_count": limits.max_count,
+                    "max_size": limits.max_size
+                }))
+                .await?
+        );
+
+        Self::fetch(account, connection).await
+    }
+
+    /// Checks whether storing additional `size` bytes (and optionally a new schematic) is allowed
+    pub fn check(&self, size: u64, creates: bool) -> Result<()> {
+        if creates && self.count >= self.max_count {
+            return Err(ApplicationError::BadRequest(format!(
+                "schematic limit reached ({} of {} schematics)",
+                self.count, self.max_count
+            )));
+        }
+        if self.size + size > self.max_size {
+            return Err(ApplicationError::BadRequest(format!(
+                "storage limit exceeded ({} of {} bytes used, the upload requires {size} bytes)",
+                self.size, self.max_size
+            )));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::quota::SchematicQuota;
+
+    #[test]
+    fn test_check() {
+        let quota = SchematicQuota {
+            max_count: 2,
+            max_size: 100,
+            count: 2,
+            size: 60,
+        };
+
+        assert!(quota.check(40, false).is_ok());
+        assert!(quota.check(41, false).is_err());
+        assert!(quota.check(10, true).is_err());
+    }
+}
@@ -0,0 +1,173 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// The property schematics are sorted by
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SchematicSort {
+    Name,
+    /// the date of the first upload
+    #[default]
+    Date,
+    Size,
+    /// the amount of non-air blocks
+    Blocks,
+}
+
+impl SchematicSort {
+    fn order(&self) -> &'static str {
+        match self {
+            SchematicSort::Name => "name COLLATE",
+            SchematicSort::Date => "created_at",
+            SchematicSort::Size => "size",
+            SchematicSort::Blocks => "blocks",
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// The filters of the schematic library. All given filters have to match.
+#[derive(Deserialize, Debug, Clone, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchematicSearch {
+    /// a case insensitive part of the name
+    pub name: Option<String>,
+    /// a case insensitive prefix of the name
+    pub prefix: Option<String>,
+    pub tag: Option<String>,
+    pub min_width: Option<u16>,
+    pub max_width: Option<u16>,
+    pub min_height: Option<u16>,
+    pub max_height: Option<u16>,
+    pub min_length: Option<u16>,
+    pub max_length: Option<u16>,
+    /// only schematics first uploaded after the given time
+    pub uploaded_after: Option<DateTime<Utc>>,
+    /// only schematics first uploaded before the given time
+    pub uploaded_before: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort: SchematicSort,
+    #[serde(default)]
+    pub order: SortOrder,
+}
+
+impl SchematicSearch {
+    /// Builds the query selecting the matching schematics accessible by the given minecraft uuid.
+    /// User input is only passed as bindings.
+    pub fn query(&self, uuid: &str) -> (String, Vec<(&'static str, Value)>) {
+        let mut conditions = vec!["(owner = $uuid OR ->added->(account WHERE uuid = $uuid))"];
+        let mut bindings = vec![("uuid", Value::from(uuid))];
+
+        if let Some(name) = &self.name {
+            conditions.push("string::lowercase(name) CONTAINS $name");
+            bindings.push(("name", Value::from(name.to_lowercase())));
+        }
+        if let Some(prefix) = &self.prefix {
+            conditions.push("string::startsWith(string::lowercase(name), $prefix)");
+            bindings.push(("prefix", Value::from(prefix.to_lowercase())));
+        }
+        if let Some(tag) = &self.tag {
+            conditions.push("tags CONTAINS $tag");
+            bindings.push(("tag", Value::from(tag.trim().to_lowercase())));
+        }
+
+        let dimensions = [
+            ("min_width", "width >= $min_width", self.min_width),
+            ("max_width", "width <= $max_width", self.max_width),
+            ("min_height", "height >= $min_height", self.min_height),
+            ("max_height", "height <= $max_height", self.max_height),
+            ("min_length", "length >= $min_length", self.min_length),
+            ("max_length", "length <= $max_length", self.max_length),
+        ];
+        for (key, condition, value) in dimensions {
+            if let Some(value) = value {
+                conditions.push(condition);
+                bindings.push((key, Value::from(value)));
+            }
+        }
+
+        if let Some(after) = self.uploaded_after {
+            conditions.push("created_at >= <datetime> $uploaded_after");
+            bindings.push(("uploaded_after", Value::from(after.to_rfc3339())));
+        }
+        if let Some(before) = self.uploaded_before {
+            conditions.push("created_at <= <datetime> $uploaded_before");
+            bindings.push(("uploaded_before", Value::from(before.to_rfc3339())));
+        }
+
+        let order = match self.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        let query = format!(
+            "SELECT id, name, owner, tags, size, width, height, length, blocks, created_at \
+            FROM schematic WHERE {} ORDER BY {} {order}",
+            conditions.join(" AND "),
+            self.sort.order()
+        );
+
+        (query, bindings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::search::{SchematicSearch, SchematicSort, SortOrder};
+
+    #[test]
+    fn test_query() {
+        let (query, bindings) = SchematicSearch::default().query("uuid");
+        assert!(query.ends_with("ORDER BY created_at DESC"));
+        assert_eq!(1, bindings.len());
+
+        let search = SchematicSearch {
+            name: Some("House".to_owned()),
+            tag: Some("medieval".to_owned()),
+            min_width: Some(16),
+            sort: SchematicSort::Name,
+            order: SortOrder::Asc,
+            ..Default::default()
+        };
+        let (query, bindings) = search.query("uuid");
+        assert!(query.contains("string::lowercase(name) CONTAINS $name"));
+        assert!(query.contains("tags CONTAINS $tag"));
+        assert!(query.contains("width >= $min_width"));
+        assert!(!query.contains("$max_width"));
+        assert!(query.ends_with("ORDER BY name COLLATE ASC"));
+        assert!(bindings.contains(&("name", serde_json::Value::from("house"))));
+        assert_eq!(4, bindings.len());
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::session::ALPHABET;
+use crate::data::schematic::Schematic;
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::sign::Signer;
+
+/// A public share link of a schematic, which can be used without an account
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Getters)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct SchematicShare {
+    id: Id,
+    /// the shared schematic
+    schematic: Id,
+    /// the time after which the link can no longer be used
+    #[serde(alias = "expires_at")]
+    expires_at: Option<DateTime<Utc>>,
+    /// the maximum amount of downloads
+    #[serde(alias = "max_downloads")]
+    max_downloads: Option<u64>,
+    /// the amount of downloads so far
+    #[serde(default)]
+    downloads: u64,
+    #[serde(default)]
+    revoked: bool,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+/// A single access of a share link
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchematicShareAccess {
+    /// the address of the client, if known
+    pub address: Option<String>,
+    #[serde(alias = "user_agent")]
+    pub user_agent: Option<String>,
+    /// whether the download has been served
+    pub granted: bool,
+    #[serde(alias = "created_at")]
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSchematicShare {
+    pub expires_at: Option<DateTime<Utc>>,
+    pub max_downloads: Option<u64>,
+}
+
+/// Signs the given share id with the configured secret
+fn sign(id: &str) -> Result<String> {
+    let key = PKey::hmac(SCHEMATIC_SHARE_SECRET.as_bytes())?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &key)?;
+    let signature = signer.sign_oneshot_to_vec(id.as_bytes())?;
+
+    Ok(signature
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect::<String>())
+}
+
+impl SchematicShare {
+    /// Creates a new share link for the given schematic
+    #[instrument(skip(schematic, connection))]
+    pub async fn create(
+        schematic: &Schematic,
+        data: &CreateSchematicShare,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        if data
+            .expires_at
+            .is_some_and(|expires_at| expires_at <= Utc::now())
+        {
+            return Err(ApplicationError::BadRequest(
+                "expiry has to be in the future".to_owned(),
+            ));
+        }
+        if data.max_downloads.is_some_and(|max| max == 0) {
+            return Err(ApplicationError::BadRequest(
+                "maximum downloads have to be positive".to_owned(),
+            ));
+        }
+
+        let share = sql_span!(connection
+            .query(
+                "CREATE $share SET schematic = $schematic, expires_at = $expires_at, \
+                max_downloads = $max_downloads"
+            )
+            .bind((
+                "share",
+                Id::new(("schematic_share", nanoid::nanoid!(32, &ALPHABET).as_str())).to_thing()
+            ))
+            .bind(("schematic", schematic.id().to_thing()))
+            .bind(("expires_at", data.expires_at))
+            .bind(("max_downloads", data.max_downloads))
+            .await?
+            .take::<Option<SchematicShare>>(0)?);
+
+        share.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Fetch a share link of the given schematic by its id
+    #[instrument(skip(schematic, connection))]
+    pub async fn from_id(
+        schematic: &Schematic,
+        id: &str,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        let share: Option<SchematicShare> = sql_span!(
+            connection
+                .select(&Id::try_from(("schematic_share", id))?)
+                .await?
+        );
+
+        share
+            .filter(|share| share.schematic.eq(schematic.id()))
+            .ok_or(ApplicationError::BadRequest("share not found".to_owned()))
+    }
+
+    /// Fetch the share link the given token has been issued for. Fails on invalid signatures.
+    #[instrument(skip_all)]
+    pub async fn from_token(token: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let invalid = || ApplicationError::BadRequest("invalid share link".to_owned());
+        let (id, signature) = token.split_once('.').ok_or_else(invalid)?;
+
+        let expected = sign(id)?;
+        if expected.len() != signature.len()
+            || !openssl::memcmp::eq(expected.as_bytes(), signature.as_bytes())
+        {
+            return Err(invalid());
+        }
+
+        let share: Option<SchematicShare> =
+            sql_span!(connection.select(("schematic_share", id)).await?);
+        share.ok_or_else(invalid)
+    }
+
+    /// Lists all share links of the given schematic, newest first
+    #[instrument(skip_all)]
+    pub async fn list(schematic: &Schematic, connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        let shares = sql_span!(connection
+            .query("SELECT * FROM schematic_share WHERE schematic = $schematic ORDER BY created_at DESC")
+            .bind(("schematic", schematic.id().to_thing()))
+            .await?
+            .take::<Vec<SchematicShare>>(0)?);
+
+        Ok(shares)
+    }
+
+    /// The signed token of this link, which is required to download the schematic
+    pub fn token(&self) -> Result<String> {
+        Ok(format!("{}.{}", self.id.id, sign(self.id.id.as_str())?))
+    }
+
+    /// Revokes the link. The access log is retained.
+    #[instrument(skip_all)]
+    pub async fn revoke(&mut self, connection: &DatabaseConnection) -> Result<()> {
+        sql_span!(connection
+            .query("UPDATE $share SET revoked = true")
+            .bind(("share", self.id.to_thing()))
+            .await?
+            .check()?);
+
+        self.revoked = true;
+        Ok(())
+    }
+
+    /// Counts a download if the link is still usable and records the access. The check and the
+    /// increment happen in a single statement, so concurrent downloads can't exceed the limit.
+    #[instrument(skip(self, connection))]
+    pub async fn access(
+        &mut self,
+        address: Option<String>,
+        user_agent: Option<String>,
+        connection: &DatabaseConnection,
+    ) -> Result<bool> {
+        let updated = sql_span!(connection
+            .query(
+                "UPDATE $share SET downloads += 1 WHERE revoked = false \
+                AND (expires_at IS NONE OR expires_at > time::now()) \
+                AND (max_downloads IS NONE OR downloads < max_downloads)"
+            )
+            .bind(("share", self.id.to_thing()))
+            .await?
+            .take::<Option<SchematicShare>>(0)?);
+        let granted = updated.is_some();
+
+        sql_span!(connection
+            .query(
+                "CREATE schematic_share_access SET share = $share, address = $address, \
+                user_agent = $user_agent, granted = $granted"
+            )
+            .bind(("share", self.id.to_thing()))
+            .bind(("address", address))
+            .bind(("user_agent", user_agent))
+            .bind(("granted", granted))
+            .await?
+            .check()?);
+
+        if let Some(updated) = updated {
+            *self = updated;
+        }
+        Ok(granted)
+    }
+
+    /// Lists the access log of this link, newest first
+    #[instrument(skip_all)]
+    pub async fn log(&self, connection: &DatabaseConnection) -> Result<Vec<SchematicShareAccess>> {
+        let log = sql_span!(connection
+            .query(
+                "SELECT address, user_agent, granted, created_at FROM schematic_share_access \
+                WHERE share = $share ORDER BY created_at DESC"
+            )
+            .bind(("share", self.id.to_thing()))
+            .await?
+            .take::<Vec<SchematicShareAccess>>(0)?);
+
+        Ok(log)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::sign;
+
+    #[test]
+    fn test_sign() {
+        let signature = sign("share").unwrap();
+
+        assert_eq!(64, signature.len());
+        assert_eq!(signature, sign("share").unwrap());
+        assert_ne!(signature, sign("other").unwrap());
+    }
+}
@@ -0,0 +1,250 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::schematic::format::{
+    invalid, volume, BlockEntity, Compound, CompoundExt, Structure,
+};
+use crate::prelude::*;
+use fastnbt::{ByteArray, IntArray, Value};
+
+/// The sponge schematic version we write
+const VERSION: i32 = 2;
+
+/// Reads a sponge schematic of version 1, 2 or 3
+pub fn read(root: Compound) -> Result<Structure> {
+    // version 3 nests everything in a `Schematic` compound
+    let root = match root.get("Schematic") {
+        Some(Value::Compound(inner)) => inner.clone(),
+        _ => root,
+    };
+    let version = root.int("Version").unwrap_or(1);
+
+    let width = root.dimension("Width")?;
+    let height = root.dimension("Height")?;
+    let length = root.dimension("Length")?;
+    let ([width, height, length], volume) = volume(width, height, length)?;
+    // version 1 did not include a data version, so we assume 1.13.2
+    let data_version = root.int("DataVersion").unwrap_or(1631) as i32;
+
+    let (palette, data, block_entities) = if version >= 3 {
+        let blocks = root.compound("Blocks")?;
+        (
+            blocks.compound("Palette")?,
+            blocks.value("Data")?,
+            blocks.get("BlockEntities"),
+        )
+    } else {
+        (
+            root.compound("Palette")?,
+            root.value("BlockData")?,
+            root.get("BlockEntities").or(root.get("TileEntities")),
+        )
+    };
+
+    // invert the palette
+    let mut states = vec![String::new(); palette.len()];
+    for (state, index) in palette.iter() {
+        let index = index
+            .as_i64()
+            .filter(|index| (0..palette.len() as i64).contains(index))
+            .ok_or_else(|| invalid("palette index out of range"))?;
+        states[index as usize] = state.clone();
+    }
+
+    let data = match data {
+        Value::ByteArray(data) => data,
+        _ => return Err(invalid("block data is not a byte array")),
+    };
+    let blocks = read_varints(data, volume)?;
+    if blocks.iter().any(|block| *block as usize >= states.len()) {
+        return Err(invalid("block data references unknown palette entries"));
+    }
+
+    let block_entities = match block_entities {
+        Some(Value::List(entries)) => entries
+            .iter()
+            .map(|entry| read_block_entity(entry, version))
+            .collect::<Result<Vec<BlockEntity>>>()?,
+        _ => Vec::new(),
+    };
+
+    Ok(Structure::new(
+        [width, height, length],
+        data_version,
+        states,
+        blocks,
+        block_entities,
+    ))
+}
+
+/// Writes the given structure as sponge schematic of version 2
+pub fn write(structure: &Structure, name: &str) -> Result<Compound> {
+    let mut root = Compound::new();
+    root.insert("Version".to_owned(), Value::Int(VERSION));
+    root.insert("DataVersion".to_owned(), Value::Int(structure.data_version));
+    root.insert("Width".to_owned(), Value::Short(structure.width as i16));
+    root.insert("Height".to_owned(), Value::Short(structure.height as i16));
+    root.insert("Length".to_owned(), Value::Short(structure.length as i16));
+    root.insert(
+        "Offset".to_owned(),
+        Value::IntArray(IntArray::new(vec![0, 0, 0])),
+    );
+    root.insert(
+        "Metadata".to_owned(),
+        Value::Compound(Compound::from([(
+            "Name".to_owned(),
+            Value::String(name.to_owned()),
+        )])),
+    );
+
+    root.insert(
+        "PaletteMax".to_owned(),
+        Value::Int(structure.palette.len() as i32),
+    );
+    root.insert(
+        "Palette".to_owned(),
+        Value::Compound(
+            structure
+                .palette
+                .iter()
+                .enumerate()
+                .map(|(index, state)| (state.clone(), Value::Int(index as i32)))
+                .collect(),
+        ),
+    );
+    root.insert(
+        "BlockData".to_owned(),
+        Value::ByteArray(write_varints(structure.blocks.as_slice())),
+    );
+    root.insert(
+        "BlockEntities".to_owned(),
+        Value::List(
+            structure
+                .block_entities
+                .iter()
+                .map(|entity| {
+                    let mut compound = entity.data.clone();
+                    compound.insert(
+                        "Pos".to_owned(),
+                        Value::IntArray(IntArray::new(entity.position.to_vec())),
+                    );
+                    compound.insert("Id".to_owned(), Value::String(entity.id.clone()));
+                    Value::Compound(compound)
+                })
+                .collect(),
+        ),
+    );
+
+    Ok(root)
+}
+
+fn read_block_entity(entry: &Value, version: i64) -> Result<BlockEntity> {
+    let mut compound = match entry {
+        Value::Compound(compound) => compound.clone(),
+        _ => return Err(invalid("block entity is not a compound")),
+    };
+
+    let position = match compound.remove("Pos") {
+        Some(Value::IntArray(position)) if position.len() == 3 => {
+            [position[0], position[1], position[2]]
+        }
+        _ => return Err(invalid("block entity without a valid position")),
+    };
+    let id = compound
+        .remove("Id")
+        .and_then(|id| id.as_str().map(str::to_owned))
+        .ok_or_else(|| invalid("block entity without an id"))?;
+    // version 3 moved the actual data into a nested compound
+    let data = match (version, compound.remove("Data")) {
+        (3.., Some(Value::Compound(data))) => data,
+        (_, Some(data)) => {
+            compound.insert("Data".to_owned(), data);
+            compound
+        }
+        (_, None) => compound,
+    };
+
+    Ok(BlockEntity { position, id, data })
+}
+
+fn read_varints(data: &[i8], count: usize) -> Result<Vec<u32>> {
+    let mut result = Vec::with_capacity(count);
+    let mut value: u32 = 0;
+    let mut shift = 0;
+
+    for byte in data.iter().map(|byte| *byte as u8) {
+        value |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            result.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+            if shift > 28 {
+                return Err(invalid("block data contains an oversized varint"));
+            }
+        }
+    }
+
+    if result.len() != count {
+        return Err(invalid(
+            format!(
+                "block data contains {} blocks, but {count} were expected",
+                result.len()
+            )
+            .as_str(),
+        ));
+    }
+
+    Ok(result)
+}
+
+fn write_varints(values: &[u32]) -> ByteArray {
+    let mut data = Vec::with_capacity(values.len());
+    for value in values {
+        let mut value = *value;
+        while value >= 0x80 {
+            data.push(((value & 0x7f) | 0x80) as u8 as i8);
+            value >>= 7;
+        }
+        data.push(value as u8 as i8);
+    }
+
+    ByteArray::new(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::sponge::{read_varints, write_varints};
+
+    #[test]
+    fn test_varints() {
+        let values = vec![0, 1, 127, 128, 300, 70000];
+        let encoded = write_varints(values.as_slice());
+        assert_eq!(values, read_varints(&encoded, values.len()).unwrap());
+        assert!(read_varints(&encoded, values.len() + 1).is_err());
+    }
+}
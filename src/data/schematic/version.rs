@@ -27,17 +27,38 @@
 use crate::prelude::*;
 use chrono::{DateTime, Utc};
 
+/// A previous version of a schematic. The current version is stored on the schematic itself.
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema, Getters)]
 #[get = "pub"]
-pub struct Schematic {
+pub struct SchematicVersion {
     id: Id,
-    /// the name of the schematic
-    name: String,
-    /// the base64 encoded schematic file
+    /// the schematic this version belongs to
+    schematic: Id,
+    /// the incrementing version number
+    version: u32,
+    /// the base64 encoded schematic file (sponge format)
     data: String,
-    /// the minecraft uuid
-    owner: String,
-    /// TODO: may add updated_at and other logging functions for downloads etc
+    /// the minecraft uuid of the uploader
+    uploader: String,
+    /// the size of the decoded file in bytes
+    size: u64,
+    /// the time this version has been uploaded
     #[serde(alias = "created_at")]
     created_at: DateTime<Utc>,
 }
+
+/// The publicly listed metadata of a schematic version
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SchematicVersionEntry {
+    pub version: u32,
+    /// the minecraft uuid of the uploader
+    pub uploader: String,
+    /// the size of the decoded file in bytes
+    pub size: u64,
+    /// whether this is the current version of the schematic
+    #[serde(default)]
+    pub current: bool,
+    #[serde(alias = "created_at")]
+    pub created_at: DateTime<Utc>,
+}
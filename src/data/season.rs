@@ -0,0 +1,140 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::prelude::*;
+use chrono::{DateTime, Utc};
+
+/// A competitive season. Statistics and ratings can be restricted to the matches played
+/// within the time of a season, the current season has started and hasn't ended yet.
+#[derive(Getters, Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+#[get = "pub"]
+pub struct Season {
+    id: Id,
+    name: String,
+    start: DateTime<Utc>,
+    /// the end of a closed season
+    end: Option<DateTime<Utc>>,
+    #[serde(alias = "created_at")]
+    created_at: DateTime<Utc>,
+}
+
+impl Season {
+    /// Schedules a new season. The open season is closed at the given start, so it stays the
+    /// current season until the new one begins.
+    #[instrument(skip(connection))]
+    pub async fn begin(
+        name: &str,
+        start: DateTime<Utc>,
+        connection: &DatabaseConnection,
+    ) -> Result<Self> {
+        if name.trim().is_empty() {
+            return Err(ApplicationError::BadRequest(
+                "the name can't be empty".to_owned(),
+            ));
+        }
+        if let Some(latest) = Self::all(connection).await?.first() {
+            if start <= latest.start || latest.end.is_some_and(|end| start < end) {
+                return Err(ApplicationError::BadRequest(
+                    "the season has to start after the latest season".to_owned(),
+                ));
+            }
+        }
+
+        let season = sql_span!(connection
+            .query("BEGIN TRANSACTION")
+            .query("UPDATE season SET end = <datetime> $start WHERE end IS NONE")
+            .query("CREATE season SET name = $name, start = <datetime> $start")
+            .query("COMMIT TRANSACTION")
+            .bind(("name", name.trim()))
+            .bind(("start", start.to_rfc3339()))
+            .await?
+            .check()?
+            .take::<Option<Season>>(1)?);
+
+        season.ok_or(ApplicationError::InternalServerError)
+    }
+
+    /// Closes the season at the given end. Matches played afterwards are rated outside of
+    /// any season until the next season starts.
+    #[instrument(skip(connection))]
+    pub async fn close(
+        &mut self,
+        end: DateTime<Utc>,
+        connection: &DatabaseConnection,
+    ) -> Result<()> {
+        if self.end.is_some() {
+            return Err(ApplicationError::Conflict(
+                "the season has already been closed".to_owned(),
+            ));
+        }
+        if end <= self.start {
+            return Err(ApplicationError::BadRequest(
+                "the season has to end after its start".to_owned(),
+            ));
+        }
+
+        let season = sql_span!(connection
+            .query("UPDATE $season SET end = <datetime> $end")
+            .bind(("season", self.id.to_thing()))
+            .bind(("end", end.to_rfc3339()))
+            .await?
+            .take::<Option<Season>>(0)?);
+
+        *self = season.ok_or(ApplicationError::InternalServerError)?;
+        Ok(())
+    }
+
+    /// Fetch a season by its id
+    #[instrument(skip(connection))]
+    pub async fn from_id(id: &str, connection: &DatabaseConnection) -> Result<Self> {
+        let season: Option<Season> =
+            sql_span!(connection.select(&Id::try_from(("season", id))?).await?);
+
+        season.ok_or(ApplicationError::BadRequest("season not found".to_owned()))
+    }
+
+    /// Fetch the season which has started and hasn't ended yet
+    #[instrument(skip_all)]
+    pub async fn current(connection: &DatabaseConnection) -> Result<Option<Self>> {
+        Ok(sql_span!(connection
+            .query(
+                "SELECT * FROM season WHERE start <= time::now() \
+                AND (end IS NONE OR end > time::now()) ORDER BY start DESC LIMIT 1"
+            )
+            .await?
+            .take::<Option<Season>>(0)?))
+    }
+
+    /// Select all seasons, the latest first
+    #[instrument(skip_all)]
+    pub async fn all(connection: &DatabaseConnection) -> Result<Vec<Self>> {
+        Ok(sql_span!(connection
+            .query("SELECT * FROM season ORDER BY start DESC")
+            .await?
+            .take::<Vec<Season>>(0)?))
+    }
+}
@@ -39,6 +39,8 @@ const SURREALDB_USERNAME: &str = "SURREALDB_USERNAME";
 const SURREALDB_PASSWORD: &str = "SURREALDB_PASSWORD";
 
 pub type DatabaseConnection = Surreal<Client>;
+/// A query under construction, used to compose statements of several records in one transaction
+pub type DatabaseQuery<'r> = surrealdb::method::Query<'r, Client>;
 
 #[derive(Deserialize)]
 pub struct DatabaseResult<T> {
@@ -104,6 +106,8 @@ pub async fn connect() -> Result<DatabaseConnection> {
     migrate(&client, env!("CARGO_PKG_VERSION"), Vec::new()).await?;
     // init the permissions
     init_permissions(&client).await?;
+    // compute the metadata of schematics uploaded before it has been tracked
+    crate::data::schematic::Schematic::backfill_metadata(&client).await?;
 
     Ok(client)
 }
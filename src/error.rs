@@ -36,6 +36,8 @@ pub enum ApplicationError {
     BadRequest(String),
     #[error("{0}")]
     Forbidden(String),
+    #[error("{0}")]
+    Conflict(String),
     #[error(transparent)]
     HashError(#[from] argon2::password_hash::errors::Error),
     #[error(transparent)]
@@ -46,6 +48,8 @@ pub enum ApplicationError {
     InternalServerError,
     #[error(transparent)]
     IoError(#[from] std::io::Error),
+    #[error(transparent)]
+    OpensslError(#[from] openssl::error::ErrorStack),
 }
 
 #[derive(Serialize, Debug, JsonSchema)]
@@ -85,6 +89,10 @@ impl IntoResponse for ApplicationError {
                 log_test_error!(error);
                 (StatusCode::FORBIDDEN, Json(json!({ "error": error })))
             }
+            ApplicationError::Conflict(error) => {
+                log_test_error!(error);
+                (StatusCode::CONFLICT, Json(json!({ "error": error })))
+            }
             _ => {
                 error!("Err: {}", self.to_string());
 
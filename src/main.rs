@@ -43,16 +43,14 @@ extern crate serde_json;
 #[macro_use]
 extern crate axum_macros;
 
-use crate::prelude::{ApplicationState, DatabaseConnection};
+use crate::prelude::{ApplicationState, ROOT};
 use aide::axum::ApiRouter;
 use aide::openapi::OpenApi;
 use axum::http::{header, Method};
-use axum::routing::get_service;
 use axum::{BoxError, Extension, Router};
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
-use tower_http::services::ServeDir;
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
@@ -66,22 +64,17 @@ mod state;
 #[cfg(test)]
 mod tests;
 
-pub async fn router(connection: DatabaseConnection) -> Result<Router, BoxError> {
-    // connect to the database
-    let state = ApplicationState::from(connection);
-
+pub async fn router(state: ApplicationState) -> Result<Router, BoxError> {
     aide::gen::extract_schemas(true);
-    let cdn = ServeDir::new(".");
     let mut api = OpenApi::default();
 
     Ok(ApiRouter::new()
         .nest_api_service("/docs", routes::docs::router(state.clone()))
         .nest_api_service("/", routes::router(state))
         .finish_api_with(&mut api, routes::docs::transform_api)
-        .route("/cdn/*name", get_service(cdn))
         .layer(
             CorsLayer::new()
-                .allow_origin([std::env::var("ROOT").unwrap().parse().unwrap()])
+                .allow_origin([ROOT.parse().unwrap()])
                 .allow_methods(vec![
                     Method::GET,
                     Method::POST,
@@ -103,19 +96,24 @@ pub async fn router(connection: DatabaseConnection) -> Result<Router, BoxError>
 #[tokio::main]
 async fn main() -> Result<(), BoxError> {
     let _ = std::env::var("HCAPTCHA_SECRET").expect("HCAPTCHA_SECRET NOT FOUND");
+    let _ = std::env::var("SCHEMATIC_SHARE_SECRET").expect("SCHEMATIC_SHARE_SECRET NOT FOUND");
 
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::from_default_env())
         .with(tracing_subscriber::fmt::layer())
         .init();
 
-    let connection = database::connect().await?;
-    let router = router(connection).await?;
+    // connect to the database
+    let state = ApplicationState::from(database::connect().await?);
+    let router = router(state.clone()).await?;
+    // the background jobs only run in the server, not in the tests building the router
+    tokio::spawn(data::event::check_in::close_check_ins(state.clone()));
+    tokio::spawn(data::event::series::materialize_series(state));
 
     // start the axum server
     let address = SocketAddr::from(([0, 0, 0, 0], 8000));
     axum::Server::bind(&address)
-        .serve(router.into_make_service())
+        .serve(router.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
 
@@ -126,7 +124,7 @@ pub mod prelude {
     pub use crate::auth::authz::permission::*;
     pub use crate::database::id::Id;
     pub use crate::database::page::{Page, PagingRequest};
-    pub use crate::database::DatabaseConnection;
+    pub use crate::database::{DatabaseConnection, DatabaseQuery};
     pub use crate::error::*;
     pub use crate::routes::extractor::Json;
     pub use crate::routes::{CreationResponse, DeletionResponse};
@@ -135,5 +133,57 @@ pub mod prelude {
 
     lazy_static::lazy_static! {
         pub static ref HCAPTCHA_SECRET: String = std::env::var("HCAPTCHA_SECRET").expect("HCAPTCHA_SECRET NOT FOUND");
+        /// the public url of the frontend, used to build absolute links
+        pub static ref ROOT: String = std::env::var("ROOT").expect("ROOT NOT FOUND").trim_end_matches('/').to_owned();
+        /// the public url of the api, used to build absolute links
+        pub static ref API_ROOT: String = env_or("API_ROOT", format!("{}/api", *ROOT)).trim_end_matches('/').to_owned();
+        /// the title of the published feeds
+        pub static ref FEED_TITLE: String = env_or("FEED_TITLE", "MyPlayPlanet".to_owned());
+        /// the languages content can be translated to, the first one is the default language
+        pub static ref LANGUAGES: Vec<String> = std::env::var("LANGUAGES")
+            .unwrap_or_else(|_| "de,en".to_owned())
+            .split(',')
+            .map(|language| language.trim().to_lowercase())
+            .filter(|language| !language.is_empty())
+            .collect();
+        /// the language untranslated content is written in
+        pub static ref DEFAULT_LANGUAGE: String = LANGUAGES.first().cloned().unwrap_or_else(|| "de".to_owned());
+        /// the directory assets are stored in
+        pub static ref ASSET_ROOT: std::path::PathBuf = env_or("ASSET_ROOT", "assets".into());
+        /// the maximum size of an uploaded image in bytes
+        pub static ref IMAGE_UPLOAD_LIMIT: u64 = env_or("IMAGE_UPLOAD_LIMIT", 10 * 1024 * 1024);
+        /// the maximum amount of pixels of an uploaded image
+        pub static ref IMAGE_PIXEL_LIMIT: u64 = env_or("IMAGE_PIXEL_LIMIT", 40_000_000);
+        /// the maximum size of an uploaded event attachment in bytes
+        pub static ref ATTACHMENT_UPLOAD_LIMIT: u64 = env_or("ATTACHMENT_UPLOAD_LIMIT", 16 * 1024 * 1024);
+        /// the maximum amount of retained versions per schematic (including the current one)
+        pub static ref SCHEMATIC_VERSION_LIMIT: usize = env_or("SCHEMATIC_VERSION_LIMIT", 10);
+        /// the default maximum amount of schematics per account
+        pub static ref SCHEMATIC_COUNT_LIMIT: u64 = env_or("SCHEMATIC_COUNT_LIMIT", 50);
+        /// the default maximum amount of stored schematic bytes per account (including versions)
+        pub static ref SCHEMATIC_SIZE_LIMIT: u64 = env_or("SCHEMATIC_SIZE_LIMIT", 100 * 1024 * 1024);
+        /// the maximum size of a single uploaded schematic file in bytes
+        pub static ref SCHEMATIC_UPLOAD_LIMIT: u64 = env_or("SCHEMATIC_UPLOAD_LIMIT", 8 * 1024 * 1024);
+        /// the addresses of the reverse proxies whose `X-Forwarded-For` header is trusted
+        pub static ref TRUSTED_PROXIES: Vec<std::net::IpAddr> = std::env::var("TRUSTED_PROXIES")
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|address| address.trim().parse().ok())
+            .collect();
+        /// the secret share links are signed with. It is required on startup, only the tests fall
+        /// back to a random one, which would invalidate all issued links on restart.
+        pub static ref SCHEMATIC_SHARE_SECRET: String = std::env::var("SCHEMATIC_SHARE_SECRET")
+            .unwrap_or_else(|_| {
+                warn!("SCHEMATIC_SHARE_SECRET not found, share links are signed with a random secret");
+                nanoid::nanoid!(64)
+            });
+    }
+
+    /// Reads and parses the given env variable, falling back to the default if absent or invalid
+    fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+        std::env::var(key)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
     }
 }
@@ -37,7 +37,7 @@ use axum::extract::{Path, State};
 use axum::http::StatusCode;
 use axum::Extension;
 
-mod schematic;
+pub mod schematic;
 
 pub fn router(state: ApplicationState) -> ApiRouter {
     ApiRouter::new()
@@ -24,11 +24,17 @@
  *
  */
 
+use crate::auth::authz::Authorizable;
 use crate::data::account::Account;
-use crate::data::schematic::Schematic;
-use crate::database::DatabaseResult;
+use crate::data::schematic::format::{SchematicFormat, Structure};
+use crate::data::schematic::quota::{QuotaOverride, SchematicQuota};
+use crate::data::schematic::search::SchematicSearch;
+use crate::data::schematic::share::{CreateSchematicShare, SchematicShare, SchematicShareAccess};
+use crate::data::schematic::version::SchematicVersionEntry;
+use crate::data::schematic::{format, Schematic, SchematicMetadata};
 use crate::prelude::*;
-use aide::axum::routing::{get_with, post_with};
+use crate::routes::read_body;
+use aide::axum::routing::{delete_with, get_with, post_with, put_with};
 use aide::axum::ApiRouter;
 use aide::transform::TransformOperation;
 use axum::body::StreamBody;
@@ -36,10 +42,8 @@ use axum::extract::{BodyStream, Path, Query, State};
 use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
 use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::Extension;
-use futures::TryStreamExt;
 use std::io::Cursor;
-use tokio::io::AsyncReadExt;
-use tokio_util::io::{ReaderStream, StreamReader};
+use tokio_util::io::ReaderStream;
 
 pub fn router(state: ApplicationState) -> ApiRouter {
     ApiRouter::new()
@@ -48,6 +52,19 @@ pub fn router(state: ApplicationState) -> ApiRouter {
             get_with(get_schematic_entry_page, get_schematic_entry_page_docs)
                 .layer(require_session!(state, DEFAULT)),
         )
+        .api_route(
+            "/tags",
+            get_with(get_tags, get_tags_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/quota",
+            get_with(get_quota, get_quota_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/quota",
+            put_with(update_quota, update_quota_docs)
+                .layer(require_session!(state, SCHEMATIC_QUOTA_UPDATE)),
+        )
         .api_route(
             "/upload/:schematic_name",
             post_with(upload, upload_docs).layer(require_session!(state, DEFAULT)),
@@ -58,14 +75,57 @@ pub fn router(state: ApplicationState) -> ApiRouter {
                 .delete_with(delete, delete_docs)
                 .layer(require_session!(state, DEFAULT)),
         )
+        .api_route(
+            "/:schematic_id/tags",
+            put_with(update_tags, update_tags_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/versions",
+            get_with(get_versions, get_versions_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/versions/:version",
+            get_with(download_version, download_version_docs)
+                .delete_with(delete_version, delete_version_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/versions/:version/restore",
+            post_with(restore_version, restore_version_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/shares",
+            get_with(get_shares, get_shares_docs)
+                .post_with(create_share, create_share_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/shares/:share_id",
+            delete_with(revoke_share, revoke_share_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:schematic_id/shares/:share_id/log",
+            get_with(get_share_log, get_share_log_docs).layer(require_session!(state, DEFAULT)),
+        )
         .with_state(state)
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct SchematicEntry {
-    id: Id,
-    name: String,
+    pub id: Id,
+    pub name: String,
     owner: String,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// the size of the decoded file in bytes
+    #[serde(default)]
+    size: u64,
+    #[serde(flatten)]
+    metadata: SchematicMetadata,
+    #[serde(alias = "created_at")]
+    created_at: chrono::DateTime<chrono::Utc>,
 }
 
 /// GET /account/:account_id/schematic
@@ -73,18 +133,16 @@ async fn get_schematic_entry_page(
     State(state): State<ApplicationState>,
     Extension(account): Extension<Account>,
     Query(request): Query<PagingRequest>,
+    Query(search): Query<SchematicSearch>,
 ) -> Result<Json<Page<SchematicEntry>>> {
     let connection = state.connection();
 
     match account.uuid() {
         Some(uuid) => {
-            // select a page of all accessible entries
+            // select a page of all accessible and matching entries
+            let (query, bindings) = search.query(uuid);
             let entries = request
-                .execute::<SchematicEntry, _>(
-                    "SELECT id, name, owner FROM schematic WHERE owner = $uuid OR ->added->(account WHERE uuid = $uuid)",
-                    Some(&[("uuid", uuid)]),
-                    connection,
-                )
+                .execute::<SchematicEntry, _>(query.as_str(), Some(bindings.as_slice()), connection)
                 .await?;
 
             Ok(Json(entries))
@@ -94,12 +152,15 @@ async fn get_schematic_entry_page(
 }
 
 fn get_schematic_entry_page_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get a page of schematic entries")
-        .response::<200, Json<Page<SchematicEntry>>>()
-        .response::<401, Json<ApplicationErrorResponse>>()
+    op.description(
+        "Get a page of schematic entries. The entries can be filtered by name, tag, dimensions \
+    and upload date and sorted by name, date, size or block count (newest first by default).",
+    )
+    .response::<200, Json<Page<SchematicEntry>>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
 }
 
-/// POST /account/:account_id/schematic/:schematic_name
+/// POST /account/:account_id/schematic/upload/:schematic_name
 async fn upload(
     State(state): State<ApplicationState>,
     Path((_account_id, schematic_name)): Path<(String, String)>,
@@ -111,27 +172,32 @@ async fn upload(
     // enforce a linked minecraft uuid
     match account.uuid() {
         Some(uuid) => {
-            // read the async stream
-            let mut data: Vec<u8> = Vec::new();
-            let stream_reader = StreamReader::new(
-                body.map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error)),
-            );
-            futures::pin_mut!(stream_reader);
-            stream_reader.read_to_end(&mut data).await?;
-            // encode the data as bas64
-            let encoded = openssl::base64::encode_block(data.as_slice());
-
-            // save into the database
-            let _: Schematic = sql_span!(
-                connection
-                    .create("schematic")
-                    .content(&serde_json::json! ({
-                        "owner": uuid,
-                        "data": encoded,
-                        "name": schematic_name
-                    }))
-                    .await?
-            );
+            // read the async stream, but stop right after exceeding the upload limit
+            let data = read_body(body, *SCHEMATIC_UPLOAD_LIMIT).await?;
+            // normalise the file into the sponge format
+            let schematic_name = SchematicFormat::strip_extension(schematic_name.as_str());
+            let name = schematic_name.to_owned();
+            let (metadata, data) = format::blocking(move || {
+                let (structure, _) = Structure::read(data.as_slice())?;
+                let metadata = SchematicMetadata::from(&structure);
+                Ok((
+                    metadata,
+                    structure.write(SchematicFormat::Schem, name.as_str())?,
+                ))
+            })
+            .await?;
+
+            // save into the database, which creates a new version for already existing schematics
+            let quota = SchematicQuota::fetch(&account, connection).await?;
+            Schematic::upload(
+                uuid,
+                schematic_name,
+                data.as_slice(),
+                metadata,
+                &quota,
+                connection,
+            )
+            .await?;
 
             Ok((StatusCode::CREATED, Json(CreationResponse::from(true))))
         }
@@ -140,61 +206,34 @@ async fn upload(
 }
 
 fn upload_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Upload a new schematic")
-        .response::<201, Json<CreationResponse>>()
-        .response::<400, Json<ApplicationErrorResponse>>()
-        .security_requirement("Session")
+    op.description(
+        "Upload a new schematic. Accepts sponge (.schem), legacy MCEdit (.schematic) and \
+    litematica (.litematic) files, which are converted into the sponge format. Uploading to an \
+    already existing name creates a new version of the schematic. Fails if the upload exceeds the \
+    maximum upload size or the schematic quota of the account.",
+    )
+    .response::<201, Json<CreationResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
 }
 
-/// GET /account/:account_id/schematic/download/:schematic_id
-async fn download(
-    State(state): State<ApplicationState>,
-    Path((_account_id, schematic_id)): Path<(String, String)>,
-    Extension(account): Extension<Account>,
-) -> Result<(HeaderMap, StreamBody<ReaderStream<Cursor<Vec<u8>>>>)> {
-    let connection = state.connection();
-
-    // fetch the schematic
-    let schematic: Option<Schematic> = sql_span!(
-        connection
-            .select(&Id::try_from(("schematic", schematic_id.as_str()))?)
-            .await?
-    );
-    match schematic {
+/// Fetch the given schematic and make sure the account is its owner
+async fn fetch_owned(
+    schematic_id: &str,
+    account: &Account,
+    connection: &DatabaseConnection,
+) -> Result<Schematic> {
+    match Schematic::from_id(schematic_id, connection).await? {
         Some(schematic) => {
-            // only allow the owner to download it
-            if !schematic.owner().eq(&account
+            if schematic.owner().eq(&account
                 .uuid()
                 .clone()
                 .ok_or(ApplicationError::Unauthorized)?)
             {
-                return Err(ApplicationError::Unauthorized);
+                Ok(schematic)
+            } else {
+                Err(ApplicationError::Unauthorized)
             }
-
-            // decode the file
-            let decoded = openssl::base64::decode_block(schematic.data().as_str())
-                .map_err(|_| ApplicationError::InternalServerError)?;
-            // turn into queue
-            let cursor = Cursor::new(decoded);
-            // read it as stream
-            let stream = ReaderStream::new(cursor);
-            // convert it to a body
-            let body = StreamBody::new(stream);
-
-            let mut headers = HeaderMap::new();
-            headers.insert(
-                CONTENT_TYPE,
-                HeaderValue::from_str("text/schem; charset=utf-8").unwrap(),
-            );
-            headers.insert(
-                CONTENT_DISPOSITION,
-                HeaderValue::from_str(
-                    format!("attachment; filename=\"{}\"", schematic.name()).as_str(),
-                )
-                .unwrap(),
-            );
-
-            Ok((headers, body))
         }
         None => Err(ApplicationError::BadRequest(
             "schematic not found".to_owned(),
@@ -202,12 +241,77 @@ async fn download(
     }
 }
 
+pub type FileResponse = (HeaderMap, StreamBody<ReaderStream<Cursor<Vec<u8>>>>);
+
+/// Converts the given base64 encoded schematic into the requested format and builds the response
+pub async fn file_response(
+    data: &str,
+    name: &str,
+    format: SchematicFormat,
+) -> Result<FileResponse> {
+    // decode the file
+    let decoded =
+        openssl::base64::decode_block(data).map_err(|_| ApplicationError::InternalServerError)?;
+    // convert it into the requested format
+    let name = SchematicFormat::strip_extension(name);
+    let target = name.to_owned();
+    let decoded =
+        format::blocking(move || format::convert(decoded.as_slice(), target.as_str(), format))
+            .await?;
+    // turn into queue
+    let cursor = Cursor::new(decoded);
+    // read it as stream
+    let stream = ReaderStream::new(cursor);
+    // convert it to a body
+    let body = StreamBody::new(stream);
+
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(format.content_type()),
+    );
+    headers.insert(
+        CONTENT_DISPOSITION,
+        HeaderValue::from_str(
+            format!("attachment; filename=\"{name}.{}\"", format.extension()).as_str(),
+        )
+        .map_err(|_| ApplicationError::BadRequest("invalid schematic name".to_owned()))?,
+    );
+
+    Ok((headers, body))
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct DownloadRequest {
+    /// the format to export the schematic in
+    #[serde(default)]
+    pub format: SchematicFormat,
+}
+
+/// GET /account/:account_id/schematic/:schematic_id
+async fn download(
+    State(state): State<ApplicationState>,
+    Path((_account_id, schematic_id)): Path<(String, String)>,
+    Extension(account): Extension<Account>,
+    Query(request): Query<DownloadRequest>,
+) -> Result<FileResponse> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    file_response(schematic.data(), schematic.name(), request.format).await
+}
+
 fn download_docs(op: TransformOperation) -> TransformOperation {
-    op.description("download a schematic")
-        .response::<401, Json<ApplicationErrorResponse>>()
-        .security_requirement("Session")
+    op.description(
+        "download a schematic in the requested format (defaults to sponge). Fails if the \
+    schematic can't be represented in the requested format.",
+    )
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
 }
 
+/// DELETE /account/:account_id/schematic/:schematic_id
 pub async fn delete(
     State(state): State<ApplicationState>,
     Extension(account): Extension<Account>,
@@ -215,32 +319,575 @@ pub async fn delete(
 ) -> Result<Json<DeletionResponse>> {
     let connection = state.connection();
 
-    // TODO: execute in single db query
-    // fetch the owner of the schematic
-    let id = Id::try_from(("schematic", schematic_id.as_str()))?;
-    let owner = sql_span!(connection
-        .query("SELECT owner as result FROM $schematic")
-        .bind(("schematic", id.to_thing()))
-        .await?
-        .take::<Option<DatabaseResult<String>>>(0)?
-        .ok_or(ApplicationError::Unauthorized)?);
-    if owner.result.eq(&account
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    schematic.delete(connection).await?;
+
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Delete a schematic including all of its versions")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /account/:account_id/schematic/tags
+async fn get_tags(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<Vec<String>>> {
+    let connection = state.connection();
+
+    let uuid = account
         .uuid()
         .clone()
-        .ok_or(ApplicationError::Unauthorized)?)
-    {
-        let _: Schematic = sql_span!(connection.delete(&id).await?);
+        .ok_or(ApplicationError::Unauthorized)?;
+    Ok(Json(
+        Schematic::owner_tags(uuid.as_str(), connection).await?,
+    ))
+}
 
-        Ok(Json(DeletionResponse::from(true)))
-    } else {
-        Err(ApplicationError::Unauthorized)
-    }
+fn get_tags_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all tags used on the own schematics")
+        .response::<200, Json<Vec<String>>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
 }
 
-fn delete_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Delete a schematic")
+/// PUT /account/:account_id/schematic/:schematic_id/tags
+async fn update_tags(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id)): Path<(String, String)>,
+    Json(tags): Json<Vec<String>>,
+) -> Result<Json<Vec<String>>> {
+    let connection = state.connection();
+
+    let mut schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    schematic.set_tags(tags.as_slice(), connection).await?;
+
+    Ok(Json(schematic.tags().clone()))
+}
+
+fn update_tags_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Replace the tags of a schematic. Tags are lowercased and may consist of alphanumeric \
+    characters, dashes and underscores.",
+    )
+    .response::<200, Json<Vec<String>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /account/:account_id/schematic/:schematic_id/versions
+async fn get_versions(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id)): Path<(String, String)>,
+) -> Result<Json<Vec<SchematicVersionEntry>>> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    Ok(Json(schematic.versions(connection).await?))
+}
+
+fn get_versions_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all versions of a schematic, newest first")
+        .response::<200, Json<Vec<SchematicVersionEntry>>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// GET /account/:account_id/schematic/:schematic_id/versions/:version
+async fn download_version(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id, version)): Path<(String, String, u32)>,
+    Query(request): Query<DownloadRequest>,
+) -> Result<FileResponse> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let data = schematic.version_data(version, connection).await?;
+    file_response(data.as_str(), schematic.name(), request.format).await
+}
+
+fn download_version_docs(op: TransformOperation) -> TransformOperation {
+    op.description("download a specific version of a schematic in the requested format")
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// DELETE /account/:account_id/schematic/:schematic_id/versions/:version
+async fn delete_version(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id, version)): Path<(String, String, u32)>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    schematic.delete_version(version, connection).await?;
+
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_version_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Delete a previous version of a schematic")
         .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// POST /account/:account_id/schematic/:schematic_id/versions/:version/restore
+async fn restore_version(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id, version)): Path<(String, String, u32)>,
+) -> Result<Json<Vec<SchematicVersionEntry>>> {
+    let connection = state.connection();
+
+    let mut schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let uploader = schematic.owner().clone();
+    let quota = SchematicQuota::fetch(&account, connection).await?;
+    schematic
+        .restore(version, uploader.as_str(), &quota, connection)
+        .await?;
+
+    Ok(Json(schematic.versions(connection).await?))
+}
+
+fn restore_version_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Restore a previous version of a schematic. The restored data is saved as new version.",
+    )
+    .response::<200, Json<Vec<SchematicVersionEntry>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// A share link including its signed token
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct SchematicShareLink {
+    #[serde(flatten)]
+    pub share: SchematicShare,
+    /// the token to download the schematic with at `/share/:token`
+    pub token: String,
+}
+
+impl TryFrom<SchematicShare> for SchematicShareLink {
+    type Error = ApplicationError;
+
+    fn try_from(share: SchematicShare) -> Result<Self> {
+        Ok(Self {
+            token: share.token()?,
+            share,
+        })
+    }
+}
+
+/// GET /account/:account_id/schematic/:schematic_id/shares
+async fn get_shares(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id)): Path<(String, String)>,
+) -> Result<Json<Vec<SchematicShareLink>>> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let shares = SchematicShare::list(&schematic, connection)
+        .await?
+        .into_iter()
+        .map(SchematicShareLink::try_from)
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Json(shares))
+}
+
+fn get_shares_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all share links of a schematic, newest first")
+        .response::<200, Json<Vec<SchematicShareLink>>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// POST /account/:account_id/schematic/:schematic_id/shares
+async fn create_share(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id)): Path<(String, String)>,
+    Json(data): Json<CreateSchematicShare>,
+) -> Result<(StatusCode, Json<SchematicShareLink>)> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let share = SchematicShare::create(&schematic, &data, connection).await?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SchematicShareLink::try_from(share)?),
+    ))
+}
+
+fn create_share_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Create a public share link for a schematic with an optional expiry and maximum amount \
+    of downloads. The schematic can be downloaded without an account at `/share/:token`.",
+    )
+    .response::<201, Json<SchematicShareLink>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// DELETE /account/:account_id/schematic/:schematic_id/shares/:share_id
+async fn revoke_share(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id, share_id)): Path<(String, String, String)>,
+) -> Result<Json<SchematicShareLink>> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let mut share = SchematicShare::from_id(&schematic, share_id.as_str(), connection).await?;
+    share.revoke(connection).await?;
+
+    Ok(Json(SchematicShareLink::try_from(share)?))
+}
+
+fn revoke_share_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Revoke a share link. Its access log is retained.")
+        .response::<200, Json<SchematicShareLink>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
         .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// GET /account/:account_id/schematic/:schematic_id/shares/:share_id/log
+async fn get_share_log(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((_account_id, schematic_id, share_id)): Path<(String, String, String)>,
+) -> Result<Json<Vec<SchematicShareAccess>>> {
+    let connection = state.connection();
+
+    let schematic = fetch_owned(schematic_id.as_str(), &account, connection).await?;
+    let share = SchematicShare::from_id(&schematic, share_id.as_str(), connection).await?;
+
+    Ok(Json(share.log(connection).await?))
 }
 
-// TODO: unittests
+fn get_share_log_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all accesses of a share link, newest first")
+        .response::<200, Json<Vec<SchematicShareAccess>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// Fetch the account the quota is requested for. Accounts may always access their own quota, others
+/// require the permission to update quotas.
+async fn quota_target(
+    requester: Account,
+    account_id: &str,
+    connection: &DatabaseConnection,
+) -> Result<Account> {
+    if requester.id().to_string().eq(account_id) {
+        return Ok(requester);
+    }
+
+    requester
+        .has_permission(&SCHEMATIC_QUOTA_UPDATE, connection)
+        .await?;
+    Account::from_id(account_id, connection)
+        .await?
+        .ok_or(ApplicationError::BadRequest("account not found".to_owned()))
+}
+
+/// GET /account/:account_id/schematic/quota
+async fn get_quota(
+    State(state): State<ApplicationState>,
+    Extension(requester): Extension<Account>,
+    Path(account_id): Path<String>,
+) -> Result<Json<SchematicQuota>> {
+    let connection = state.connection();
+
+    let account = quota_target(requester, account_id.as_str(), connection).await?;
+    Ok(Json(SchematicQuota::fetch(&account, connection).await?))
+}
+
+fn get_quota_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get the schematic limits and the current usage of the account. Requires the permission \
+    'schematic.quota.update' for other accounts.",
+    )
+    .response::<200, Json<SchematicQuota>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// PUT /account/:account_id/schematic/quota
+async fn update_quota(
+    State(state): State<ApplicationState>,
+    Extension(requester): Extension<Account>,
+    Path(account_id): Path<String>,
+    Json(data): Json<QuotaOverride>,
+) -> Result<Json<SchematicQuota>> {
+    let connection = state.connection();
+
+    let account = quota_target(requester, account_id.as_str(), connection).await?;
+    Ok(Json(
+        SchematicQuota::set(&account, &data, connection).await?,
+    ))
+}
+
+fn update_quota_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Override the schematic limits of the account")
+        .response::<200, Json<SchematicQuota>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![SCHEMATIC_QUOTA_UPDATE.id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::format::{SchematicFormat, Structure};
+    use crate::data::schematic::quota::SchematicQuota;
+    use crate::data::schematic::version::SchematicVersionEntry;
+    use crate::prelude::Page;
+    use crate::routes::account::schematic::SchematicEntry;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use axum_test_helper::{TestClient, TestResponse};
+
+    async fn upload(
+        connector: &TestClient,
+        session: &str,
+        account: &str,
+        name: &str,
+        structure: &Structure,
+        format: SchematicFormat,
+    ) -> TestResponse {
+        connector
+            .post(format!("/account/{account}/schematic/upload/{name}").as_str())
+            .header(AUTHORIZATION, session)
+            .body(structure.write(format, name).unwrap())
+            .send()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_versions() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+        let mut account = suite.account().clone();
+        account.link("uuid", suite.connection()).await?;
+        let id = account.id().to_string();
+
+        let session = suite.authenticate("username", "password", None).await;
+        let mut structure = Structure::empty(2, 2, 2, 3465)?;
+        let response = upload(
+            suite.connector(),
+            session.as_str(),
+            id.as_str(),
+            "house.litematic",
+            &structure,
+            SchematicFormat::Litematic,
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        structure.blocks[0] = structure.palette_index("minecraft:stone");
+        let response = upload(
+            suite.connector(),
+            session.as_str(),
+            id.as_str(),
+            "house.schem",
+            &structure,
+            SchematicFormat::Schem,
+        )
+        .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        let page = suite
+            .connector()
+            .get(format!("/account/{id}/schematic").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<SchematicEntry>>()
+            .await;
+        assert_eq!(1, page.total);
+        let schematic = page.data[0].id.to_string();
+
+        let response = suite
+            .connector()
+            .post(format!("/account/{id}/schematic/{schematic}/versions/1/restore").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let versions = response.json::<Vec<SchematicVersionEntry>>().await;
+        assert_eq!(
+            vec![3, 2, 1],
+            versions
+                .iter()
+                .map(|version| version.version)
+                .collect::<Vec<u32>>()
+        );
+
+        let response = suite
+            .connector()
+            .get(
+                format!("/account/{id}/schematic/{schematic}/versions/2?format=schematic").as_str(),
+            )
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = suite
+            .connector()
+            .delete(format!("/account/{id}/schematic/{schematic}/versions/3").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .delete(format!("/account/{id}/schematic/{schematic}/versions/1").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_search() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+        let mut account = suite.account().clone();
+        account.link("uuid", suite.connection()).await?;
+        let id = account.id().to_string();
+
+        let session = suite.authenticate("username", "password", None).await;
+        for (name, width) in [("Castle", 4), ("cabin", 2), ("tower", 3)] {
+            let structure = Structure::empty(width, 2, 2, 3465)?;
+            let response = upload(
+                suite.connector(),
+                session.as_str(),
+                id.as_str(),
+                name,
+                &structure,
+                SchematicFormat::Schem,
+            )
+            .await;
+            assert_eq!(StatusCode::CREATED, response.status());
+        }
+
+        let search = |query: &'static str| {
+            let session = session.clone();
+            let id = id.clone();
+            let connector = suite.connector();
+            async move {
+                connector
+                    .get(format!("/account/{id}/schematic?{query}").as_str())
+                    .header(AUTHORIZATION, session.as_str())
+                    .send()
+                    .await
+                    .json::<Page<SchematicEntry>>()
+                    .await
+                    .data
+                    .into_iter()
+                    .map(|entry| entry.name)
+                    .collect::<Vec<String>>()
+            }
+        };
+
+        assert_eq!(
+            vec!["cabin", "Castle"],
+            search("prefix=ca&sort=name&order=asc").await
+        );
+        assert_eq!(
+            vec!["Castle", "tower"],
+            search("minWidth=3&sort=name&order=asc").await
+        );
+
+        let page = suite
+            .connector()
+            .get(format!("/account/{id}/schematic?name=tower").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<SchematicEntry>>()
+            .await;
+        let tower = page.data[0].id.to_string();
+        let response = suite
+            .connector()
+            .put(format!("/account/{id}/schematic/{tower}/tags").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!([" Medieval ", "medieval", "stone"]))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            vec!["medieval", "stone"],
+            response.json::<Vec<String>>().await
+        );
+
+        assert_eq!(vec!["tower"], search("tag=medieval").await);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_quota() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+        let mut account = suite.account().clone();
+        account.link("uuid", suite.connection()).await?;
+        let id = account.id().to_string();
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = suite
+            .connector()
+            .put(format!("/account/{id}/schematic/quota").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "maxCount": 1 }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let structure = Structure::empty(2, 2, 2, 3465)?;
+        for (name, status) in [("a", StatusCode::CREATED), ("b", StatusCode::BAD_REQUEST)] {
+            let response = upload(
+                suite.connector(),
+                session.as_str(),
+                id.as_str(),
+                name,
+                &structure,
+                SchematicFormat::Schem,
+            )
+            .await;
+            assert_eq!(status, response.status());
+        }
+
+        let quota = suite
+            .connector()
+            .get(format!("/account/{id}/schematic/quota").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<SchematicQuota>()
+            .await;
+        assert_eq!(1, quota.count);
+        assert_eq!(1, quota.max_count);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::asset::Asset;
+use crate::prelude::*;
+use aide::axum::routing::get_with;
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::body::StreamBody;
+use axum::extract::{Path, State};
+use axum::http::header::{CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use tokio_util::io::ReaderStream;
+
+/// asset urls change with their content, so they can be cached forever
+const CACHE: &str = "public, max-age=31536000, immutable";
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/:asset_id", get_with(get, get_docs))
+        .with_state(state)
+}
+
+/// GET /asset/:asset_id
+async fn get(
+    State(state): State<ApplicationState>,
+    Path(asset_id): Path<String>,
+    request_headers: HeaderMap,
+) -> Result<Response> {
+    let connection = state.connection();
+
+    let asset = Asset::from_id(asset_id.as_str(), connection)
+        .await?
+        .ok_or(ApplicationError::BadRequest("asset not found".to_owned()))?;
+
+    let mut headers = HeaderMap::new();
+    let etag = HeaderValue::from_str(format!("\"{}\"", asset.hash()).as_str())
+        .map_err(|_| ApplicationError::InternalServerError)?;
+    headers.insert(CACHE_CONTROL, HeaderValue::from_static(CACHE));
+    headers.insert(ETAG, etag.clone());
+
+    // the client already has the current content
+    let matches = request_headers
+        .get_all(IF_NONE_MATCH)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|value| value.trim() == "*" || value.trim().as_bytes() == etag.as_bytes());
+    if matches {
+        return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+    }
+
+    let file = tokio::fs::File::open(asset.path()).await?;
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_str(asset.mime()).map_err(|_| ApplicationError::InternalServerError)?,
+    );
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(*asset.size()));
+
+    Ok((headers, StreamBody::new(ReaderStream::new(file))).into_response())
+}
+
+fn get_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get the content of an asset. Responds with 304 if the `If-None-Match` header matches \
+    the ETag of the asset.",
+    )
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
@@ -0,0 +1,146 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::audit::{AuditEntry, AuditSearch};
+use crate::prelude::*;
+use aide::axum::routing::get_with;
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Query, State};
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/",
+            get_with(get_all, get_all_docs).layer(require_session!(state, AUDIT_READ)),
+        )
+        .with_state(state)
+}
+
+/// GET /audit
+async fn get_all(
+    State(state): State<ApplicationState>,
+    Query(data): Query<PagingRequest>,
+    Query(search): Query<AuditSearch>,
+) -> Result<Json<Page<AuditEntry>>> {
+    let connection = state.connection();
+
+    let (query, bindings) = search.query();
+    Ok(Json(
+        data.execute::<AuditEntry, _>(query.as_str(), Some(bindings.as_slice()), connection)
+            .await?,
+    ))
+}
+
+fn get_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get a page of the audit log, the latest entry first. It records every successful \
+    mutating request with its actor, session and client address. Changes of news, events and \
+    accounts name the target and the changed fields.",
+    )
+    .response::<200, Json<Page<AuditEntry>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![AUDIT_READ.id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::audit::AuditEntry;
+    use crate::data::event::Event;
+    use crate::prelude::Page;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_audit() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let event = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "name",
+                "description": "description",
+                "start": Utc::now() + Duration::hours(1),
+                "end": Utc::now() + Duration::hours(2),
+            }))
+            .send()
+            .await
+            .json::<Event>()
+            .await;
+        let response = suite
+            .connector()
+            .patch(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .header("x-forwarded-for", "198.51.100.1")
+            .json(&serde_json::json!({ "name": "other" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let page = suite
+            .connector()
+            .get(format!("/audit?target={}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<AuditEntry>>()
+            .await;
+        assert_eq!(2, page.total);
+        let update = &page.data[0];
+        assert_eq!("event.update", update.action());
+        assert_eq!(Some(suite.account().id().clone()), *update.actor());
+        assert!(update.session().is_some());
+        // the forwarded address of an untrusted peer isn't recorded
+        assert_ne!(Some("198.51.100.1"), update.address().as_deref());
+        assert!(update
+            .changes()
+            .iter()
+            .any(|change| change.field == "name" && change.after.as_deref() == Some("\"other\"")));
+        assert_eq!("event.create", page.data[1].action());
+
+        // reads aren't recorded
+        let page = suite
+            .connector()
+            .get(format!("/audit?actor={}", suite.account().id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<AuditEntry>>()
+            .await;
+        assert!(page
+            .data
+            .iter()
+            .all(|entry| !entry.action().starts_with("GET")));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::machine::{MachineClient, MachineCredentials};
+use crate::auth::session::Session;
+use crate::prelude::*;
+use aide::axum::routing::post_with;
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::State;
+use axum::http::StatusCode;
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/",
+            post_with(create, create_docs).layer(require_session!(state, MACHINE_CLIENT_CREATE)),
+        )
+        .api_route("/login", post_with(login, login_docs))
+        .with_state(state)
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+pub struct CreateMachineRequest {
+    /// a name describing the client, e.g. the server it runs on
+    name: String,
+}
+
+/// POST /auth/machine
+async fn create(
+    State(state): State<ApplicationState>,
+    Json(data): Json<CreateMachineRequest>,
+) -> Result<(StatusCode, Json<MachineCredentials>)> {
+    let connection = state.connection();
+
+    let credentials = MachineClient::create(data.name.as_str(), connection).await?;
+    Ok((StatusCode::CREATED, Json(credentials)))
+}
+
+fn create_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Create a new machine client like a game server plugin. The secret is only returned once.",
+    )
+    .response::<201, Json<MachineCredentials>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![MACHINE_CLIENT_CREATE.id.to_string()])
+}
+
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct MachineLoginRequest {
+    client_id: String,
+    secret: String,
+}
+
+/// POST /auth/machine/login
+async fn login(
+    State(state): State<ApplicationState>,
+    Json(data): Json<MachineLoginRequest>,
+) -> Result<Json<Session>> {
+    let connection = state.connection();
+
+    match MachineClient::from_id(data.client_id.as_str(), connection).await? {
+        Some(client) => Ok(Json(client.login(data.secret.as_str(), connection).await?)),
+        None => Err(ApplicationError::Unauthorized),
+    }
+}
+
+fn login_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Start a new session for a machine client")
+        .response::<200, Json<Session>>()
+        .response::<401, Json<ApplicationErrorResponse>>()
+}
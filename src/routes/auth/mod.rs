@@ -27,7 +27,10 @@
 use crate::auth::session::Session;
 use crate::auth::Authenticateable;
 use crate::data::account::Account;
+use crate::data::audit::Audit;
+use crate::data::notification::{notify, NotificationKind};
 use crate::prelude::*;
+use crate::routes::extractor::ClientAddress;
 use aide::axum::routing::post_with;
 use aide::axum::ApiRouter;
 use aide::transform::TransformOperation;
@@ -37,6 +40,7 @@ use axum::Extension;
 #[cfg(not(debug_assertions))]
 use hcaptcha::{HcaptchaCaptcha, HcaptchaClient, HcaptchaRequest};
 
+mod machine;
 mod password;
 mod totp;
 
@@ -48,6 +52,7 @@ pub fn router(state: ApplicationState) -> ApiRouter {
             post_with(logout, logout_docs).layer(require_session!(state, DEFAULT)),
         )
         .api_route("/refresh", post_with(refresh, refresh_docs))
+        .nest_api_service("/machine", machine::router(state.clone()))
         .nest_api_service("/password", password::router(state.clone()))
         .nest_api_service("/totp", totp::router(state.clone()))
         .with_state(state)
@@ -68,6 +73,8 @@ pub struct LoginRequest {
 /// POST /auth/login
 async fn login(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    ClientAddress(address): ClientAddress,
     Json(data): Json<LoginRequest>,
 ) -> Result<Json<Session>> {
     let connection = state.connection();
@@ -98,6 +105,20 @@ async fn login(
 
             // start a new session for the account
             let session = account.start_session(connection).await?;
+            audit.session(&session);
+            audit.action("account.login");
+            let message = match address {
+                Some(address) => format!("New login to your account from {address}"),
+                None => "New login to your account".to_owned(),
+            };
+            notify(
+                &state,
+                account.id(),
+                NotificationKind::Security,
+                message.as_str(),
+                None,
+            )
+            .await;
             Ok(Json(session))
         }
         None => Err(ApplicationError::Unauthorized),
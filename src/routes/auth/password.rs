@@ -26,6 +26,8 @@
 
 use crate::auth::Authenticateable;
 use crate::data::account::Account;
+use crate::data::audit::Audit;
+use crate::data::notification::{notify, NotificationKind};
 use crate::prelude::*;
 use aide::axum::routing::put_with;
 use aide::axum::ApiRouter;
@@ -52,6 +54,7 @@ pub struct ChangePasswordRequest {
 
 async fn change_password(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Extension(mut account): Extension<Account>,
     Json(data): Json<ChangePasswordRequest>,
 ) -> Result<Json<CreationResponse>> {
@@ -66,6 +69,16 @@ async fn change_password(
             connection,
         )
         .await?;
+    audit.action("account.password");
+    audit.target(account.id());
+    notify(
+        &state,
+        account.id(),
+        NotificationKind::Security,
+        "Your password has been changed",
+        None,
+    )
+    .await;
     Ok(Json(CreationResponse::from(true)))
 }
 
@@ -27,6 +27,8 @@
 use crate::auth::Authenticateable;
 use crate::data::account::protected::ProtectedAccount;
 use crate::data::account::Account;
+use crate::data::audit::Audit;
+use crate::data::notification::{notify, NotificationKind};
 use crate::prelude::*;
 use aide::axum::routing::put_with;
 use aide::axum::ApiRouter;
@@ -93,11 +95,13 @@ pub struct TotpToggleRequest {
 
 async fn toggle(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Extension(mut account): Extension<Account>,
     Json(data): Json<TotpToggleRequest>,
 ) -> Result<Json<ProtectedAccount>> {
     let connection = state.connection();
     let new_state = !account.totp;
+    let before = ProtectedAccount::from(account.clone());
 
     // verify the request with forced totp activation
     account.set_totp(true);
@@ -109,7 +113,21 @@ async fn toggle(
     account.set_totp(new_state);
     // save into the database
     let _: Account = sql_span!(connection.update(account.id()).content(&account).await?);
-    Ok(Json(ProtectedAccount::from(account)))
+    let account = ProtectedAccount::from(account);
+    audit.record("account.totp", &account.id, Some(&before), Some(&account));
+    let message = match account.totp {
+        true => "Two-factor authentication has been enabled",
+        false => "Two-factor authentication has been disabled",
+    };
+    notify(
+        &state,
+        &account.id,
+        NotificationKind::Security,
+        message,
+        None,
+    )
+    .await;
+    Ok(Json(account))
 }
 
 fn toggle_docs(op: TransformOperation) -> TransformOperation {
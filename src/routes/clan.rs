@@ -0,0 +1,622 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::auth::authz::Authorizable;
+use crate::data::account::Account;
+use crate::data::clan::history::ClanHistory;
+use crate::data::clan::request::ClanRequest;
+use crate::data::clan::{Clan, ClanMember, ClanRole};
+use crate::data::notification::{notify, NotificationKind};
+use crate::prelude::*;
+use aide::axum::routing::{delete_with, get_with, post_with};
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, Query, State};
+use axum::http::StatusCode;
+use axum::Extension;
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/", get_with(get_all, get_all_docs))
+        .api_route(
+            "/",
+            post_with(create, create_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/requests",
+            get_with(get_own_requests, get_own_requests_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/requests/:request_id",
+            delete_with(decline, decline_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/requests/:request_id/accept",
+            post_with(accept, accept_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route("/:clan_id", get_with(get, get_docs))
+        .api_route(
+            "/:clan_id",
+            delete_with(delete, delete_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route("/:clan_id/members", get_with(get_members, get_members_docs))
+        .api_route("/:clan_id/history", get_with(get_history, get_history_docs))
+        .api_route(
+            "/:clan_id/members/:account_id",
+            delete_with(kick, kick_docs)
+                .put_with(set_role, set_role_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:clan_id/leave",
+            post_with(leave, leave_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:clan_id/invitations",
+            post_with(invite, invite_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:clan_id/requests",
+            get_with(get_requests, get_requests_docs)
+                .post_with(request_join, request_join_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .with_state(state)
+}
+
+/// GET /clan
+async fn get_all(
+    State(state): State<ApplicationState>,
+    Query(request): Query<PagingRequest>,
+) -> Result<Json<Page<Clan>>> {
+    let connection = state.connection();
+
+    Ok(Json(Clan::all(request, connection).await?))
+}
+
+fn get_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get a page of all clans, ordered by their name")
+        .response::<200, Json<Page<Clan>>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct CreateClanRequest {
+    /// the name of the clan, 3 to 32 characters
+    name: String,
+    /// the tag of the clan, 2 to 6 letters or digits
+    tag: String,
+    #[serde(default)]
+    description: String,
+}
+
+/// POST /clan
+async fn create(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Json(data): Json<CreateClanRequest>,
+) -> Result<(StatusCode, Json<Clan>)> {
+    let connection = state.connection();
+
+    let clan = Clan::create(
+        data.name.as_str(),
+        data.tag.as_str(),
+        data.description.as_str(),
+        &account,
+        connection,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(clan)))
+}
+
+fn create_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Create a clan owned by the account. Accounts can only be member of a single clan, the \
+    name and the tag have to be unique.",
+    )
+    .response::<201, Json<Clan>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /clan/:clan_id
+async fn get(
+    State(state): State<ApplicationState>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<Clan>> {
+    let connection = state.connection();
+
+    Ok(Json(Clan::from_id(clan_id.as_str(), connection).await?))
+}
+
+fn get_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the given clan")
+        .response::<200, Json<Clan>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// DELETE /clan/:clan_id
+async fn delete(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    if clan.owner() != account.id() {
+        account.has_permission(&CLAN_DELETE, connection).await?;
+    }
+    clan.delete(connection).await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Disband the clan with its memberships, requests and history. Only the owner or accounts \
+    with the permission 'clan.delete' may delete a clan.",
+    )
+    .response::<200, Json<DeletionResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /clan/:clan_id/members
+async fn get_members(
+    State(state): State<ApplicationState>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<Vec<ClanMember>>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    Ok(Json(clan.members(connection).await?))
+}
+
+fn get_members_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the members of the clan, the earliest first")
+        .response::<200, Json<Vec<ClanMember>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /clan/:clan_id/history
+async fn get_history(
+    State(state): State<ApplicationState>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<Vec<ClanHistory>>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    Ok(Json(clan.history(connection).await?))
+}
+
+fn get_history_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the membership changes of the clan, the latest first")
+        .response::<200, Json<Vec<ClanHistory>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// DELETE /clan/:clan_id/members/:account_id
+async fn kick(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((clan_id, account_id)): Path<(String, String)>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    let actor = clan
+        .authorize(account.id(), ClanRole::Officer, connection)
+        .await?;
+    clan.kick(
+        &actor,
+        &Id::try_from(("account", account_id.as_str()))?,
+        connection,
+    )
+    .await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn kick_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Remove a member from the clan, which requires a higher role than the member")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<403, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct SetRoleRequest {
+    /// the new role, making a member the owner transfers the ownership
+    role: ClanRole,
+}
+
+/// PUT /clan/:clan_id/members/:account_id
+async fn set_role(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((clan_id, account_id)): Path<(String, String)>,
+    Json(data): Json<SetRoleRequest>,
+) -> Result<Json<ClanMember>> {
+    let connection = state.connection();
+
+    let mut clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    let actor = clan
+        .authorize(account.id(), ClanRole::Owner, connection)
+        .await?;
+    let member = clan
+        .set_role(
+            &actor,
+            &Id::try_from(("account", account_id.as_str()))?,
+            data.role,
+            connection,
+        )
+        .await?;
+    Ok(Json(member))
+}
+
+fn set_role_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Change the role of a member, only available for the owner. Making another member the \
+    owner transfers the ownership, the previous owner stays as an officer.",
+    )
+    .response::<200, Json<ClanMember>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// POST /clan/:clan_id/leave
+async fn leave(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    let member = clan
+        .authorize(account.id(), ClanRole::Member, connection)
+        .await?;
+    clan.leave(&member, connection).await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn leave_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Leave the clan, the owner has to transfer the ownership first")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<403, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct InviteRequest {
+    /// the id of the invited account
+    account: String,
+}
+
+/// POST /clan/:clan_id/invitations
+async fn invite(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(clan_id): Path<String>,
+    Json(data): Json<InviteRequest>,
+) -> Result<(StatusCode, Json<ClanRequest>)> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    let actor = clan
+        .authorize(account.id(), ClanRole::Officer, connection)
+        .await?;
+    let request = clan
+        .invite(&actor, data.account.as_str(), connection)
+        .await?;
+    notify(
+        &state,
+        request.account(),
+        NotificationKind::ClanInvitation,
+        format!(
+            "You have been invited to join {} [{}]",
+            clan.name(),
+            clan.tag()
+        )
+        .as_str(),
+        Some(request.id()),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+fn invite_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Invite an account to the clan, only available for officers")
+        .response::<201, Json<ClanRequest>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<403, Json<ApplicationErrorResponse>>()
+        .response::<409, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// GET /clan/:clan_id/requests
+async fn get_requests(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(clan_id): Path<String>,
+) -> Result<Json<Vec<ClanRequest>>> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    clan.authorize(account.id(), ClanRole::Officer, connection)
+        .await?;
+    Ok(Json(clan.requests(connection).await?))
+}
+
+fn get_requests_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "List the pending invitations and join requests of the clan, only available for officers",
+    )
+    .response::<200, Json<Vec<ClanRequest>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// POST /clan/:clan_id/requests
+async fn request_join(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(clan_id): Path<String>,
+) -> Result<(StatusCode, Json<ClanRequest>)> {
+    let connection = state.connection();
+
+    let clan = Clan::from_id(clan_id.as_str(), connection).await?;
+    let request = clan.request_join(account.id(), connection).await?;
+    Ok((StatusCode::CREATED, Json(request)))
+}
+
+fn request_join_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Ask to join the clan, an officer has to accept the request")
+        .response::<201, Json<ClanRequest>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<409, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// GET /clan/requests
+async fn get_own_requests(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<Vec<ClanRequest>>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        ClanRequest::of_account(account.id(), connection).await?,
+    ))
+}
+
+fn get_own_requests_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the pending invitations and join requests of the account")
+        .response::<200, Json<Vec<ClanRequest>>>()
+        .security_requirement("Session")
+}
+
+/// POST /clan/requests/:request_id/accept
+async fn accept(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(request_id): Path<String>,
+) -> Result<Json<ClanMember>> {
+    let connection = state.connection();
+
+    let request = ClanRequest::from_id(request_id.as_str(), connection).await?;
+    Ok(Json(request.accept(account.id(), connection).await?))
+}
+
+fn accept_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Accept an invitation as the invited account or a join request as an officer of the \
+    clan. The account joins the clan and its other pending requests are dropped.",
+    )
+    .response::<200, Json<ClanMember>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// DELETE /clan/requests/:request_id
+async fn decline(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(request_id): Path<String>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let request = ClanRequest::from_id(request_id.as_str(), connection).await?;
+    request.decline(account.id(), connection).await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn decline_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Decline or withdraw an invitation or join request, either as the account or as an \
+    officer of the clan",
+    )
+    .response::<200, Json<DeletionResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::account::Account;
+    use crate::data::clan::history::{ClanAction, ClanHistory};
+    use crate::data::clan::request::ClanRequest;
+    use crate::data::clan::{Clan, ClanMember, ClanRole};
+    use crate::prelude::Id;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+
+    #[tokio::test]
+    async fn test_membership() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = suite
+            .connector()
+            .post("/clan")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Iron Legion", "tag": "iron" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let clan = response.json::<Clan>().await;
+        assert_eq!("IRON", clan.tag());
+        let path = format!("/clan/{}", clan.id().to_string());
+
+        // accounts are member of a single clan
+        let response = suite
+            .connector()
+            .post("/clan")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Other", "tag": "OTHR" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let mut other = suite.account().clone();
+        other.set_id(Id::new(("account", "other")));
+        other.set_username("other".to_owned());
+        suite
+            .connection()
+            .create::<Option<Account>>(("account", "other"))
+            .content(&other)
+            .await?;
+
+        let response = suite
+            .connector()
+            .post(format!("{path}/invitations").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "account": "other" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let invitation = response.json::<ClanRequest>().await;
+
+        // only the invited account accepts invitations
+        let response = suite
+            .connector()
+            .post(format!("/clan/requests/{}/accept", invitation.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+        let member = invitation.accept(other.id(), suite.connection()).await?;
+        assert_eq!(ClanRole::Member, *member.role());
+
+        let response = suite
+            .connector()
+            .put(format!("{path}/members/other").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "role": "owner" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        // the previous owner stays as an officer, who can't remove the new owner
+        let response = suite
+            .connector()
+            .delete(format!("{path}/members/other").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        let members = suite
+            .connector()
+            .get(format!("{path}/members").as_str())
+            .send()
+            .await
+            .json::<Vec<ClanMember>>()
+            .await;
+        assert_eq!(
+            vec![ClanRole::Officer, ClanRole::Owner],
+            members
+                .iter()
+                .map(|member| *member.role())
+                .collect::<Vec<ClanRole>>()
+        );
+        assert_eq!(
+            other.id(),
+            Clan::from_id(clan.id().to_string().as_str(), suite.connection())
+                .await?
+                .owner()
+        );
+
+        let response = suite
+            .connector()
+            .post(format!("{path}/leave").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let history = suite
+            .connector()
+            .get(format!("{path}/history").as_str())
+            .send()
+            .await
+            .json::<Vec<ClanHistory>>()
+            .await;
+        assert_eq!(
+            vec![
+                ClanAction::Left,
+                ClanAction::Transferred,
+                ClanAction::Joined,
+                ClanAction::Created
+            ],
+            history
+                .iter()
+                .map(|entry| *entry.action())
+                .collect::<Vec<ClanAction>>()
+        );
+
+        // the permission allows to disband clans of other owners
+        let response = suite
+            .connector()
+            .delete(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        Ok(())
+    }
+}
@@ -24,14 +24,43 @@
  *
  */
 
-use crate::data::event::Event;
+use crate::auth::authz::Authorizable;
+use crate::auth::session::{Session, SessionType};
+use crate::data::account::Account;
+use crate::data::asset::Asset;
+use crate::data::audit::Audit;
+use crate::data::clan::Clan;
+use crate::data::event::calendar::{calendar, CalendarToken};
+use crate::data::event::check_in::CheckInDashboard;
+use crate::data::event::fight::EventFight;
+use crate::data::event::live::{EventAnnouncement, LiveMessage};
+use crate::data::event::registration::{EventRegistration, RegistrationStatus};
+use crate::data::event::result::{MatchReport, MatchResult};
+use crate::data::event::search::EventSearch;
+use crate::data::event::seeding::{matchmake, SeededPlayer};
+use crate::data::event::series::EventSeries;
+use crate::data::event::team::EventTeam;
+use crate::data::event::{
+    validate_registration, validate_schedule, Event, EventPatch, EventStatus,
+};
+use crate::data::feed::{api_link, page_link, Feed, FeedFormat, FEED_LIMIT};
+use crate::data::notification::{notify, NotificationKind};
 use crate::prelude::*;
+use crate::routes::{feed_response, read_body};
 use aide::axum::routing::{delete_with, get_with, post_with, put_with};
 use aide::axum::ApiRouter;
 use aide::transform::TransformOperation;
-use axum::extract::{Path, Query, State};
+use axum::extract::{BodyStream, Path, Query, State};
+use axum::http::header::CONTENT_TYPE;
 use axum::http::StatusCode;
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::Extension;
 use chrono::{DateTime, Utc};
+use futures::StreamExt;
+
+mod series;
 
 pub fn router(state: ApplicationState) -> ApiRouter {
     ApiRouter::new()
@@ -40,6 +69,112 @@ pub fn router(state: ApplicationState) -> ApiRouter {
             post_with(create, create_docs).layer(require_session!(state, EVENT_CREATE)),
         )
         .api_route("/", get_with(get_all, get_all_docs))
+        .api_route(
+            "/all",
+            get_with(get_all_drafts, get_all_drafts_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/status",
+            post_with(set_status, set_status_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route("/feed.rss", get_with(get_rss, get_rss_docs))
+        .api_route("/feed.atom", get_with(get_atom, get_atom_docs))
+        .api_route("/calendar.ics", get_with(get_calendar, get_calendar_docs))
+        .api_route(
+            "/calendar/personal",
+            get_with(get_personal_calendar, get_personal_calendar_docs)
+                .post_with(renew_personal_calendar, renew_personal_calendar_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/calendar/personal/:token",
+            get_with(get_personal_ics, get_personal_ics_docs),
+        )
+        .api_route("/:event_id/ics", get_with(get_ics, get_ics_docs))
+        .api_route(
+            "/:event_id/registration",
+            post_with(register, register_docs)
+                .delete_with(unregister, unregister_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:event_id/check-in",
+            post_with(check_in, check_in_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:event_id/check-in",
+            get_with(get_check_in, get_check_in_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/check-in/:uuid",
+            post_with(check_in_player, check_in_player_docs)
+                .layer(require_session!(state, DEFAULT, machine)),
+        )
+        .api_route(
+            "/:event_id/registrations",
+            get_with(get_registrations, get_registrations_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/seeding",
+            get_with(get_seeding, get_seeding_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/matchmaking",
+            get_with(get_matchmaking, get_matchmaking_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route("/:event_id/teams", get_with(get_teams, get_teams_docs))
+        .api_route(
+            "/:event_id/teams",
+            post_with(join_as_clan, join_as_clan_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:event_id/teams/:team_id",
+            delete_with(withdraw_team, withdraw_team_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route("/:event_id/live", get_with(get_live, get_live_docs))
+        .api_route("/:event_id/fights", get_with(get_fights, get_fights_docs))
+        .api_route(
+            "/:event_id/fights",
+            post_with(create_fight, create_fight_docs)
+                .layer(require_session!(state, EVENT_FIGHT_CREATE)),
+        )
+        .api_route(
+            "/:event_id/fights/:fight_id",
+            delete_with(delete_fight, delete_fight_docs)
+                .layer(require_session!(state, EVENT_FIGHT_DELETE)),
+        )
+        .api_route(
+            "/:event_id/results",
+            get_with(get_results, get_results_docs),
+        )
+        .api_route(
+            "/:event_id/results",
+            post_with(report, report_docs).layer(require_session!(state, DEFAULT, machine)),
+        )
+        .api_route(
+            "/:event_id/announcements",
+            get_with(get_announcements, get_announcements_docs),
+        )
+        .api_route(
+            "/:event_id/announcements",
+            post_with(announce, announce_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/image",
+            post_with(upload_image, upload_image_docs)
+                .delete_with(remove_image, remove_image_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:event_id/attachments/:name",
+            post_with(attach, attach_docs)
+                .delete_with(detach, detach_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .nest_api_service("/series", series::router(state.clone()))
         .api_route("/:event_id", get_with(get, get_docs))
         .api_route(
             "/:event_id",
@@ -47,7 +182,9 @@ pub fn router(state: ApplicationState) -> ApiRouter {
         )
         .api_route(
             "/:event_id",
-            put_with(update, update_docs).layer(require_session!(state, EVENT_UPDATE)),
+            put_with(update, update_docs)
+                .patch_with(patch, patch_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
         )
         .with_state(state)
 }
@@ -61,23 +198,42 @@ pub struct CreateEventRequest {
     start: DateTime<Utc>,
     /// the scheduled end of the event
     end: DateTime<Utc>,
+    /// either draft or published (default)
+    #[serde(default)]
+    status: EventStatus,
+    /// the maximum amount of registered players, further registrations are waitlisted
+    capacity: Option<u32>,
+    /// the minutes before the start in which registered players have to check in
+    #[serde(alias = "checkIn")]
+    check_in: Option<u32>,
 }
 
 /// POST /event
 async fn create(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Json(data): Json<CreateEventRequest>,
 ) -> Result<(StatusCode, Json<Event>)> {
     let connection = state.connection();
 
+    validate_schedule(&data.start, &data.end)?;
+    validate_registration(data.capacity, data.check_in)?;
+    if !matches!(data.status, EventStatus::Draft | EventStatus::Published) {
+        return Err(ApplicationError::BadRequest(
+            "events have to be created as draft or published".to_owned(),
+        ));
+    }
+
     // create the event
     let event: Event = sql_span!(connection.create("event").content(&data).await?);
+    audit.record("event.create", event.id(), None, Some(&event));
     Ok((StatusCode::CREATED, Json(event)))
 }
 
 fn create_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Create a new event")
+    op.description("Create a new event, which has to end after its start")
         .response::<201, Json<Event>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
         .security_requirement_scopes("Session", vec![EVENT_CREATE.id.to_string()])
 }
 
@@ -88,208 +244,1893 @@ async fn get(
 ) -> Result<Json<Event>> {
     let connection = state.connection();
 
-    let event: Option<Event> = connection
-        .select(&Id::try_from(("event", event_id.as_str()))?)
-        .await?;
-    Ok(Json(event.ok_or(ApplicationError::BadRequest(
-        "event not found".to_owned(),
-    ))?))
+    Ok(Json(Event::visible(event_id.as_str(), connection).await?))
 }
 
 fn get_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get the specified event")
+    op.description("Get the specified event, drafts are not found")
         .response::<200, Json<Event>>()
         .response::<400, Json<ApplicationErrorResponse>>()
 }
 
 /// GET /event
-async fn get_all<'a>(
+async fn get_all(
     State(state): State<ApplicationState>,
     Query(data): Query<PagingRequest>,
+    Query(search): Query<EventSearch>,
 ) -> Result<Json<Page<Event>>> {
     let connection = state.connection();
 
+    let (query, bindings) = search.query(false);
     Ok(Json(
-        data.execute::<Event, &str>("SELECT * FROM event", None, connection)
+        data.execute::<Event, _>(query.as_str(), Some(bindings.as_slice()), connection)
             .await?,
     ))
 }
 
 fn get_all_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get a page of events")
-        .response::<200, Json<Page<Event>>>()
+    op.description(
+        "Get a page of the matching events without drafts, ordered by their start. The time \
+    window and range filter relative to the start and end of the events.",
+    )
+    .response::<200, Json<Page<Event>>>()
 }
 
-/// DELETE /event/:event_id
-async fn delete(
+/// GET /event/all
+async fn get_all_drafts(
     State(state): State<ApplicationState>,
-    Path(event_id): Path<String>,
-) -> Result<Json<DeletionResponse>> {
+    Query(data): Query<PagingRequest>,
+    Query(search): Query<EventSearch>,
+) -> Result<Json<Page<Event>>> {
     let connection = state.connection();
 
-    sql_span!(
-        connection
-            .delete::<Option<serde_json::Value>>(&Id::try_from(("event", event_id.as_str()))?)
-            .await?
-    );
-    Ok(Json(DeletionResponse::from(true)))
+    let (query, bindings) = search.query(true);
+    Ok(Json(
+        data.execute::<Event, _>(query.as_str(), Some(bindings.as_slice()), connection)
+            .await?,
+    ))
 }
 
-fn delete_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Delete the given event")
-        .response::<200, Json<DeletionResponse>>()
-        .response::<400, Json<ApplicationErrorResponse>>()
-        .security_requirement_scopes("Session", vec![EVENT_DELETE.id.to_string()])
+fn get_all_drafts_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get a page of the matching events including drafts, ordered by their start")
+        .response::<200, Json<Page<Event>>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
 }
 
-/// PUT /event/:event_id
-async fn update(
+#[derive(Deserialize, Serialize, Debug, Clone, JsonSchema)]
+pub struct StatusRequest {
+    status: EventStatus,
+}
+
+/// POST /event/:event_id/status
+async fn set_status(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Path(event_id): Path<String>,
-    Json(data): Json<Event>,
+    Json(data): Json<StatusRequest>,
 ) -> Result<Json<Event>> {
     let connection = state.connection();
 
-    let event: Event = sql_span!(connection
-        .query("UPDATE $event CONTENT $content")
-        .bind((
-            "event",
-            Id::try_from(("event", event_id.as_str()))?.to_thing()
-        ))
-        .bind(("content", data))
-        .await?
-        .take::<Option<Event>>(0)?
-        .ok_or(ApplicationError::BadRequest("event not found".to_owned()))?);
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    event.set_status(data.status, connection).await?;
+    audit.record("event.status", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
     Ok(Json(event))
 }
 
-fn update_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Update an already existing event")
-        .response::<200, Json<Event>>()
+fn set_status_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Change the status of the event. Drafts can be published or cancelled, published events \
+    can be turned back into drafts, started or cancelled and running events can be finished or \
+    cancelled.",
+    )
+    .response::<200, Json<Event>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// Builds the feed of the upcoming events
+async fn feed(state: &ApplicationState) -> Result<Feed> {
+    let events = Event::upcoming(FEED_LIMIT, state.connection()).await?;
+
+    Ok(Feed {
+        title: format!("{} Events", *FEED_TITLE),
+        description: format!("The upcoming events of {}", *FEED_TITLE),
+        link: page_link("/"),
+        location: api_link("/event/feed"),
+        language: None,
+        entries: events.iter().map(Into::into).collect(),
+    })
+}
+
+/// GET /event/feed.rss
+async fn get_rss(State(state): State<ApplicationState>) -> Result<Response> {
+    Ok(feed_response(&feed(&state).await?, FeedFormat::Rss))
+}
+
+fn get_rss_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the upcoming events as RSS feed, the next one first")
+}
+
+/// GET /event/feed.atom
+async fn get_atom(State(state): State<ApplicationState>) -> Result<Response> {
+    Ok(feed_response(&feed(&state).await?, FeedFormat::Atom))
+}
+
+fn get_atom_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the upcoming events as Atom feed, the next one first")
+}
+
+/// Responds with the given iCalendar
+fn ics_response(calendar: String) -> Response {
+    ([(CONTENT_TYPE, "text/calendar; charset=utf-8")], calendar).into_response()
+}
+
+/// GET /event/calendar.ics
+async fn get_calendar(State(state): State<ApplicationState>) -> Result<Response> {
+    let events = Event::all(state.connection()).await?;
+
+    Ok(ics_response(calendar(
+        format!("{} Events", *FEED_TITLE).as_str(),
+        events.as_slice(),
+    )))
+}
+
+fn get_calendar_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get all events as iCalendar")
+}
+
+/// GET /event/:event_id/ics
+async fn get_ics(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Response> {
+    let event = Event::visible(event_id.as_str(), state.connection()).await?;
+
+    Ok(ics_response(calendar(
+        event.name().as_str(),
+        std::slice::from_ref(&event),
+    )))
+}
+
+fn get_ics_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the event as iCalendar")
         .response::<400, Json<ApplicationErrorResponse>>()
-        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
 }
 
-#[cfg(test)]
-mod tests {
-    use crate::data::event::Event;
-    use crate::prelude::Page;
-    use crate::tests::TestSuite;
-    use axum::http::header::AUTHORIZATION;
-    use axum::http::StatusCode;
-    use axum::BoxError;
-    use axum_test_helper::{TestClient, TestResponse};
-    use chrono::Utc;
+#[derive(Serialize, Deserialize, Debug, Clone, JsonSchema)]
+pub struct PersonalCalendarResponse {
+    /// the secret url of the personal calendar feed
+    pub url: String,
+}
 
-    async fn setup(connector: &TestClient, session: &str) -> TestResponse {
-        connector
-            .post("/event")
-            .header(AUTHORIZATION, session)
-            .json(&serde_json::json!({
-                "name": "name",
-                "description": "description",
-                "start": Utc::now(),
-                "end": Utc::now(),
-            }))
-            .send()
-            .await
+impl From<CalendarToken> for PersonalCalendarResponse {
+    fn from(token: CalendarToken) -> Self {
+        Self { url: token.url() }
     }
+}
 
-    #[tokio::test]
-    async fn test_create() -> Result<(), BoxError> {
-        let suite = TestSuite::start().await?;
+/// GET /event/calendar/personal
+async fn get_personal_calendar(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<PersonalCalendarResponse>> {
+    let token = CalendarToken::of(account.id(), state.connection()).await?;
 
-        let session = suite.authenticate("username", "password", None).await;
-        let response = setup(suite.connector(), session.as_str()).await;
-        assert_eq!(StatusCode::CREATED, response.status());
+    Ok(Json(PersonalCalendarResponse::from(token)))
+}
 
-        Ok(())
-    }
+fn get_personal_calendar_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get the secret url of the calendar feed of the events the account is registered for. \
+    The url can be subscribed to by calendar apps without authentication.",
+    )
+    .response::<200, Json<PersonalCalendarResponse>>()
+    .security_requirement("Session")
+}
 
-    #[tokio::test]
-    async fn test_get() -> Result<(), BoxError> {
-        let suite = TestSuite::start().await?;
+/// POST /event/calendar/personal
+async fn renew_personal_calendar(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<PersonalCalendarResponse>> {
+    let token = CalendarToken::renew(account.id(), state.connection()).await?;
 
-        let session = suite.authenticate("username", "password", None).await;
-        let response = setup(suite.connector(), session.as_str()).await;
-        let event = response.json::<Event>().await;
-        let response = suite
-            .connector()
-            .get(format!("/event/{}", event.id().to_string()).as_str())
-            .send()
-            .await;
-        assert_eq!(StatusCode::OK, response.status());
-        let fetched = response.json::<Event>().await;
-        assert_eq!(event, fetched);
+    Ok(Json(PersonalCalendarResponse::from(token)))
+}
 
-        Ok(())
-    }
+fn renew_personal_calendar_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Renew the secret url of the personal calendar feed, the previous url stops working",
+    )
+    .response::<200, Json<PersonalCalendarResponse>>()
+    .security_requirement("Session")
+}
 
-    #[tokio::test]
-    async fn test_get_all() -> Result<(), BoxError> {
-        let suite = TestSuite::start().await?;
+/// GET /event/calendar/personal/:token
+async fn get_personal_ics(
+    State(state): State<ApplicationState>,
+    Path(token): Path<String>,
+) -> Result<Response> {
+    let connection = state.connection();
 
-        let session = suite.authenticate("username", "password", None).await;
-        setup(suite.connector(), session.as_str()).await;
-        setup(suite.connector(), session.as_str()).await;
+    let token = CalendarToken::from_token(token.as_str(), connection)
+        .await?
+        .ok_or(ApplicationError::BadRequest(
+            "calendar not found".to_owned(),
+        ))?;
+    let events = Event::registered(token.account(), connection).await?;
 
-        let response = suite.connector().get("/event").send().await;
-        let events = response.json::<Page<Event>>().await;
-        assert_eq!(2u64, events.total);
-        assert_eq!(1u64, events.pages);
-        assert_eq!(2, events.data.len());
+    Ok(ics_response(calendar(
+        format!("{} Events", *FEED_TITLE).as_str(),
+        events.as_slice(),
+    )))
+}
 
-        Ok(())
+fn get_personal_ics_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the events the owner of the token is registered for as iCalendar")
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// POST /event/:event_id/registration
+async fn register(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Extension(account): Extension<Account>,
+) -> Result<(StatusCode, Json<EventRegistration>)> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    let registration = event.register(account.id(), connection).await?;
+    let message = match registration.status() {
+        RegistrationStatus::Waitlisted => format!("You are on the waitlist for {}", event.name()),
+        _ => format!("You are registered for {}", event.name()),
+    };
+    notify(
+        &state,
+        account.id(),
+        NotificationKind::Registration,
+        message.as_str(),
+        Some(event.id()),
+    )
+    .await;
+    Ok((StatusCode::CREATED, Json(registration)))
+}
+
+fn register_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Register the account for the event")
+        .response::<201, Json<EventRegistration>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .response::<409, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// DELETE /event/:event_id/registration
+async fn unregister(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let promoted = event.unregister(account.id(), connection).await?;
+    event.notify_promotions(&promoted, &state).await;
+    publish_registrations(&state, &event, promoted);
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn unregister_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Remove the registration of the account for the event, the freed place is given to the \
+    waitlist",
+    )
+    .response::<200, Json<DeletionResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// Publishes the changed registrations of the event to its viewers
+fn publish_registrations(
+    state: &ApplicationState,
+    event: &Event,
+    registrations: Vec<EventRegistration>,
+) {
+    for registration in registrations {
+        state
+            .live()
+            .publish(event.id(), LiveMessage::Registration(registration));
     }
+}
 
-    #[tokio::test]
-    async fn test_delete() -> Result<(), BoxError> {
-        let suite = TestSuite::start().await?;
+/// POST /event/:event_id/check-in
+async fn check_in(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<EventRegistration>> {
+    let connection = state.connection();
 
-        let session = suite.authenticate("username", "password", None).await;
-        let response = setup(suite.connector(), session.as_str()).await;
-        let event = response.json::<Event>().await;
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let registration = event.check_in_account(account.id(), connection).await?;
+    publish_registrations(&state, &event, vec![registration.clone()]);
+    Ok(Json(registration))
+}
 
-        let response = suite
-            .connector()
-            .delete(format!("/event/{}", event.id().to_string()).as_str())
-            .header(AUTHORIZATION, session)
-            .send()
-            .await;
-        assert_eq!(StatusCode::OK, response.status());
+fn check_in_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Check in the registered account, which is possible within the check-in window before \
+    the start of the event. Players who didn't check in are dropped at the start and their places \
+    are given to the waitlisted players who checked in.",
+    )
+    .response::<200, Json<EventRegistration>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
 
-        let events: Vec<Event> = suite.connection().select("event").await?;
-        assert_eq!(0, events.len());
+/// POST /event/:event_id/check-in/:uuid
+async fn check_in_player(
+    State(state): State<ApplicationState>,
+    Path((event_id, uuid)): Path<(String, String)>,
+    Extension(session): Extension<Session>,
+    account: Option<Extension<Account>>,
+) -> Result<Json<EventRegistration>> {
+    let connection = state.connection();
 
-        Ok(())
+    if !matches!(session.target(), SessionType::Machine(_)) {
+        let Some(Extension(account)) = account else {
+            return Err(ApplicationError::Unauthorized);
+        };
+        account.has_permission(&EVENT_UPDATE, connection).await?;
     }
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let registration = event.check_in_player(uuid.as_str(), connection).await?;
+    publish_registrations(&state, &event, vec![registration.clone()]);
+    Ok(Json(registration))
+}
 
-    #[tokio::test]
-    async fn test_update() -> Result<(), BoxError> {
-        let suite = TestSuite::start().await?;
+fn check_in_player_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Check in a registered player by the uuid of their minecraft account, e.g. when they join \
+    the game server. Available for machine clients and accounts with the permission \
+    'event.update'.",
+    )
+    .response::<200, Json<EventRegistration>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<401, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
 
-        let session = suite.authenticate("username", "password", None).await;
-        let response = setup(suite.connector(), session.as_str()).await;
-        let event = response.json::<Event>().await;
+/// GET /event/:event_id/check-in
+async fn get_check_in(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<CheckInDashboard>> {
+    let connection = state.connection();
 
-        let response = suite
-            .connector()
-            .put(format!("/event/{}", event.id().to_string()).as_str())
-            .header(AUTHORIZATION, session)
-            .json(&serde_json::json! ({
-                "id": event.id().to_string(),
-                "name": "other",
-                "description": "description",
-                "start": event.start().clone(),
-                "end": event.end().clone(),
-                "createdAt": event.created_at().clone()
-            }))
-            .send()
-            .await;
-        assert_eq!(StatusCode::OK, response.status());
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    Ok(Json(event.check_in_dashboard(connection).await?))
+}
 
-        let fetched = response.json::<Event>().await;
-        assert_ne!(fetched, event);
+fn get_check_in_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get the check-in progress of the registered players and teams. Check-ins, promotions \
+    from the waitlist and dropped players are pushed as registration messages of the live stream.",
+    )
+    .response::<200, Json<CheckInDashboard>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// GET /event/:event_id/registrations
+async fn get_registrations(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<EventRegistration>>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    Ok(Json(event.registrations(connection).await?))
+}
+
+fn get_registrations_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the registrations for the event, the earliest first")
+        .response::<200, Json<Vec<EventRegistration>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// GET /event/:event_id/teams
+async fn get_teams(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<EventTeam>>> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    Ok(Json(event.teams(connection).await?))
+}
+
+fn get_teams_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the teams of the event in the order they joined")
+        .response::<200, Json<Vec<EventTeam>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct JoinAsClanRequest {
+    /// the id of the clan
+    clan: String,
+    /// the ids of the accounts of the clan members forming the team
+    roster: Vec<String>,
+}
+
+/// POST /event/:event_id/teams
+async fn join_as_clan(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(event_id): Path<String>,
+    Json(data): Json<JoinAsClanRequest>,
+) -> Result<(StatusCode, Json<EventTeam>)> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let clan = Clan::from_id(data.clan.as_str(), connection).await?;
+    let team = event
+        .join_as_clan(&clan, account.id(), data.roster.as_slice(), connection)
+        .await?;
+    Ok((StatusCode::CREATED, Json(team)))
+}
+
+fn join_as_clan_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Join the event as a team of the clan, only available for officers of the clan. The \
+    selected members need a linked minecraft account and are registered for the event unless they \
+    already are.",
+    )
+    .response::<201, Json<EventTeam>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// DELETE /event/:event_id/teams/:team_id
+async fn withdraw_team(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path((event_id, team_id)): Path<(String, String)>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    event
+        .withdraw_team(team_id.as_str(), account.id(), connection)
+        .await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn withdraw_team_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Withdraw a team from the event as an officer of its clan, the registrations of the \
+    members are kept",
+    )
+    .response::<200, Json<DeletionResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /event/:event_id/seeding
+async fn get_seeding(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<SeededPlayer>>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    Ok(Json(event.seeding(connection).await?))
+}
+
+fn get_seeding_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Seed the registered players with a linked account by their rating in the current \
+    season, e.g. to fill a bracket. The highest rating gets the first seed.",
+    )
+    .response::<200, Json<Vec<SeededPlayer>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct MatchmakingRequest {
+    /// the count of players per match, 2 by default
+    #[serde(default = "default_group_size")]
+    size: usize,
+}
+
+fn default_group_size() -> usize {
+    2
+}
+
+/// GET /event/:event_id/matchmaking
+async fn get_matchmaking(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Query(request): Query<MatchmakingRequest>,
+) -> Result<Json<Vec<Vec<SeededPlayer>>>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    Ok(Json(matchmake(
+        event.seeding(connection).await?,
+        request.size,
+    )?))
+}
+
+fn get_matchmaking_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Group the registered players into matches of players with similar ratings, e.g. for \
+    casual events. The last group holds the remaining players and can be smaller.",
+    )
+    .response::<200, Json<Vec<Vec<SeededPlayer>>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// DELETE /event/:event_id
+async fn delete(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(event_id): Path<String>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let event = Id::try_from(("event", event_id.as_str()))?;
+    let previous = Event::from_id(event_id.as_str(), connection).await.ok();
+    // deleted occurrences are skipped, otherwise the series would create them again
+    if let Some(previous) = &previous {
+        if let Some(series) = previous.series() {
+            EventSeries::skip(series, previous.start(), connection).await?;
+        }
+    }
+    Asset::delete_owned(&event, connection).await?;
+    sql_span!(connection
+        .query("DELETE event_registration WHERE event = $event")
+        .query("DELETE event_team WHERE event = $event")
+        .query("DELETE event_announcement WHERE event = $event")
+        .query("DELETE match_participation WHERE event = $event")
+        .query("DELETE match_result WHERE event = $event")
+        .query("DELETE event_fight WHERE event = $event")
+        .query("DELETE $event")
+        .bind(("event", event.to_thing()))
+        .await?
+        .check()?);
+    audit.record("event.delete", &event, previous.as_ref(), None);
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Delete the given event")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_DELETE.id.to_string()])
+}
+
+/// POST /event/:event_id/image
+async fn upload_image(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(event_id): Path<String>,
+    body: BodyStream,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    // read the stream, but stop right after exceeding the upload limit
+    let buffer = read_body(body, *IMAGE_UPLOAD_LIMIT).await?;
+    event.upload_image(buffer, connection).await?;
+    audit.record("event.image", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    Ok(Json(event))
+}
+
+fn upload_image_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Upload the cover image of the event. The image is stored as webp and jpeg in the \
+    variants thumbnail, card and full. Fails if the image exceeds the size or pixel limit.",
+    )
+    .response::<200, Json<Event>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// DELETE /event/:event_id/image
+async fn remove_image(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    event.remove_image(connection).await?;
+    audit.record("event.image", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    Ok(Json(event))
+}
+
+fn remove_image_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Remove the cover image of the event")
+        .response::<200, Json<Event>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// POST /event/:event_id/attachments/:name
+async fn attach(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path((event_id, name)): Path<(String, String)>,
+    body: BodyStream,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    let buffer = read_body(body, *ATTACHMENT_UPLOAD_LIMIT).await?;
+    event.attach(name.as_str(), buffer, connection).await?;
+    audit.record("event.attachment", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    Ok(Json(event))
+}
+
+fn attach_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Attach a file to the event, an attachment with the same name is replaced. Only pdf \
+    documents and schematics are accepted.",
+    )
+    .response::<200, Json<Event>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// DELETE /event/:event_id/attachments/:name
+async fn detach(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path((event_id, name)): Path<(String, String)>,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    event.detach(name.as_str(), connection).await?;
+    audit.record("event.attachment", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    Ok(Json(event))
+}
+
+fn detach_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Remove the attachment with the given name from the event")
+        .response::<200, Json<Event>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// GET /event/:event_id/live
+async fn get_live(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Response> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    let stream = state.live().subscribe(event.id()).map(|message| {
+        SseEvent::default()
+            .event(message.name())
+            .json_data(&message)
+    });
+
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn get_live_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Stream the live updates of the event as server-sent events. Every message is named after \
+    its type and carries a LiveMessage as json. A lagged message tells that the client missed \
+    messages and has to fetch the current state again.",
+    )
+    .response_with::<200, String, _>(|res| res.description("text/event-stream of LiveMessage"))
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /event/:event_id/fights
+async fn get_fights(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<EventFight>>> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    Ok(Json(event.fights(connection).await?))
+}
+
+fn get_fights_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the fights of the event in the order they were created")
+        .response::<200, Json<Vec<EventFight>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct CreateFightRequest {
+    /// the name of the fight, e.g. "Semi-final 1"
+    name: String,
+}
+
+/// POST /event/:event_id/fights
+async fn create_fight(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Json(data): Json<CreateFightRequest>,
+) -> Result<(StatusCode, Json<EventFight>)> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let fight = event.create_fight(data.name.as_str(), connection).await?;
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Fight(fight.clone()));
+
+    Ok((StatusCode::CREATED, Json(fight)))
+}
+
+fn create_fight_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Add a fight to the event")
+        .response::<201, Json<EventFight>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_FIGHT_CREATE.id.to_string()])
+}
+
+/// DELETE /event/:event_id/fights/:fight_id
+async fn delete_fight(
+    State(state): State<ApplicationState>,
+    Path((event_id, fight_id)): Path<(String, String)>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    event.delete_fight(fight_id.as_str(), connection).await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_fight_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Remove a fight from the event, its results are kept")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_FIGHT_DELETE.id.to_string()])
+}
+
+/// GET /event/:event_id/results
+async fn get_results(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<MatchResult>>> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    Ok(Json(event.results(connection).await?))
+}
+
+fn get_results_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the reported match results of the event, the earliest first")
+        .response::<200, Json<Vec<MatchResult>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// POST /event/:event_id/results
+async fn report(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    Extension(session): Extension<Session>,
+    Json(data): Json<MatchReport>,
+) -> Result<(StatusCode, Json<MatchResult>)> {
+    let connection = state.connection();
+
+    let SessionType::Machine(reporter) = session.target() else {
+        return Err(ApplicationError::Forbidden(
+            "results are reported by machine clients".to_owned(),
+        ));
+    };
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let (result, created) = event.report(data, Some(reporter), connection).await?;
+    if !created {
+        return Ok((StatusCode::OK, Json(result)));
+    }
+
+    if let Some(fight) = result.fight() {
+        let fight = event.fight(fight.to_string().as_str(), connection).await?;
+        state.live().publish(event.id(), LiveMessage::Fight(fight));
+    }
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Result(result.clone()));
+
+    Ok((StatusCode::CREATED, Json(result)))
+}
+
+fn report_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Report the outcome of a match of the running event, only available for machine clients \
+    like the game server plugin. All participants have to be registered for the event with their \
+    linked account. Reports are idempotent on the match key: repeating a report returns the \
+    stored result, a different outcome for the same key is rejected.",
+    )
+    .response::<201, Json<MatchResult>>()
+    .response::<200, Json<MatchResult>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /event/:event_id/announcements
+async fn get_announcements(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+) -> Result<Json<Vec<EventAnnouncement>>> {
+    let connection = state.connection();
+
+    let event = Event::visible(event_id.as_str(), connection).await?;
+    Ok(Json(event.announcements(connection).await?))
+}
+
+fn get_announcements_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the announcements of the event, the latest first")
+        .response::<200, Json<Vec<EventAnnouncement>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct AnnouncementRequest {
+    message: String,
+}
+
+/// POST /event/:event_id/announcements
+async fn announce(
+    State(state): State<ApplicationState>,
+    Path(event_id): Path<String>,
+    account: Option<Extension<Account>>,
+    Json(data): Json<AnnouncementRequest>,
+) -> Result<(StatusCode, Json<EventAnnouncement>)> {
+    let connection = state.connection();
+
+    let event = Event::from_id(event_id.as_str(), connection).await?;
+    let author = account.as_ref().map(|Extension(account)| account.id());
+    let announcement = event
+        .announce(data.message.as_str(), author, connection)
+        .await?;
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Announcement(announcement.clone()));
+
+    Ok((StatusCode::CREATED, Json(announcement)))
+}
+
+fn announce_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Publish an announcement to the live stream of the event")
+        .response::<201, Json<EventAnnouncement>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// PUT /event/:event_id
+async fn update(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(event_id): Path<String>,
+    Json(data): Json<Event>,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    event.patch(&EventPatch::from(data), connection).await?;
+    audit.record("event.update", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    // a raised capacity or a removed check-in frees places for the waitlist
+    let promoted = event.fill(connection).await?;
+    event.notify_promotions(&promoted, &state).await;
+    publish_registrations(&state, &event, promoted);
+    Ok(Json(event))
+}
+
+fn update_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Update an already existing event. Finished and cancelled events can't be updated and \
+    status changes have to follow the lifecycle of events. The id has to match the updated \
+    event, the creation time is kept.",
+    )
+    .response::<200, Json<Event>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// PATCH /event/:event_id
+async fn patch(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(event_id): Path<String>,
+    Json(data): Json<EventPatch>,
+) -> Result<Json<Event>> {
+    let connection = state.connection();
+
+    let mut event = Event::from_id(event_id.as_str(), connection).await?;
+    let before = event.clone();
+    event.patch(&data, connection).await?;
+    audit.record("event.update", event.id(), Some(&before), Some(&event));
+    state
+        .live()
+        .publish(event.id(), LiveMessage::Updated(event.clone()));
+    // a raised capacity or a removed check-in frees places for the waitlist
+    let promoted = event.fill(connection).await?;
+    event.notify_promotions(&promoted, &state).await;
+    publish_registrations(&state, &event, promoted);
+    Ok(Json(event))
+}
+
+fn patch_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Partially update an already existing event, absent fields are left untouched. The id \
+    and creation time can't be changed, a given id has to match the updated event.",
+    )
+    .response::<200, Json<Event>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::account::Account;
+    use crate::data::clan::Clan;
+    use crate::data::event::fight::EventFight;
+    use crate::data::event::live::EventAnnouncement;
+    use crate::data::event::registration::{EventRegistration, RegistrationStatus};
+    use crate::data::event::result::MatchResult;
+    use crate::data::event::seeding::SeededPlayer;
+    use crate::data::event::team::EventTeam;
+    use crate::data::event::{Event, EventPatch};
+    use crate::data::rating::RatingChange;
+    use crate::prelude::{Id, Page};
+    use crate::routes::event::PersonalCalendarResponse;
+    use crate::tests::TestSuite;
+    use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use axum_test_helper::{TestClient, TestResponse};
+    use chrono::{Duration, Utc};
+    use image::ImageOutputFormat;
+    use std::io::Cursor;
+
+    async fn setup(connector: &TestClient, session: &str) -> TestResponse {
+        connector
+            .post("/event")
+            .header(AUTHORIZATION, session)
+            .json(&serde_json::json!({
+                "name": "name",
+                "description": "description",
+                "start": Utc::now(),
+                "end": Utc::now() + Duration::hours(1),
+            }))
+            .send()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_create() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_attachments() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+        let path = format!("/event/{}", event.id().to_string());
+
+        let mut image = Cursor::new(Vec::new());
+        image::RgbImage::new(640, 480).write_to(&mut image, ImageOutputFormat::Jpeg(80))?;
+        let response = suite
+            .connector()
+            .post(format!("{path}/image").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body(image.into_inner())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let images = response.json::<Event>().await.images().clone().unwrap();
+        assert_eq!((800, 450), (images.card.width, images.card.height));
+
+        let response = suite
+            .connector()
+            .post(format!("{path}/attachments/rules.pdf").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body("%PDF-1.7\n%rules")
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let event = response.json::<Event>().await;
+        assert_eq!(1, event.attachments().len());
+        let url = event.attachments()[0].url.clone();
+        let response = suite.connector().get(url.as_str()).send().await;
+        assert_eq!("application/pdf", response.headers()[CONTENT_TYPE]);
+
+        // only documents and schematics are accepted
+        let response = suite
+            .connector()
+            .post(format!("{path}/attachments/virus.exe").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body("MZ")
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .delete(format!("{path}/attachments/rules.pdf").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert!(response.json::<Event>().await.attachments().is_empty());
+        let response = suite.connector().get(url.as_str()).send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        // deleting the event removes its assets
+        suite
+            .connector()
+            .delete(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        let response = suite
+            .connector()
+            .get(images.full.webp.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+        let response = suite
+            .connector()
+            .get(format!("/event/{}", event.id().to_string()).as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let fetched = response.json::<Event>().await;
+        assert_eq!(event, fetched);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get_all() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        setup(suite.connector(), session.as_str()).await;
+        setup(suite.connector(), session.as_str()).await;
+
+        let response = suite.connector().get("/event").send().await;
+        let events = response.json::<Page<Event>>().await;
+        assert_eq!(2u64, events.total);
+        assert_eq!(1u64, events.pages);
+        assert_eq!(2, events.data.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+
+        let response = suite
+            .connector()
+            .delete(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let events: Vec<Event> = suite.connection().select("event").await?;
+        assert_eq!(0, events.len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_update() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+
+        let response = suite
+            .connector()
+            .put(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session)
+            .json(&serde_json::json! ({
+                "id": event.id().to_string(),
+                "name": "other",
+                "description": "description",
+                "start": event.start().clone(),
+                "end": event.end().clone(),
+                "createdAt": event.created_at().clone()
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let fetched = response.json::<Event>().await;
+        assert_ne!(fetched, event);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_patch() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+
+        let response = suite
+            .connector()
+            .patch(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "other",
+                "createdAt": "2000-01-01T00:00:00Z"
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let patched = response.json::<Event>().await;
+        assert_eq!("other", patched.name());
+        assert_eq!(event.description(), patched.description());
+        assert_eq!(event.created_at(), patched.created_at());
+
+        // the id within the body has to match the path
+        let response = suite
+            .connector()
+            .patch(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "id": "event:other",
+                "name": "other"
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        // the schedule is validated against the unchanged fields
+        let response = suite
+            .connector()
+            .patch(format!("/event/{}", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "end": *event.start() - Duration::hours(1)
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_check_in() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let event = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "name",
+                "description": "description",
+                "start": Utc::now() + Duration::hours(1),
+                "end": Utc::now() + Duration::hours(2),
+                "capacity": 1,
+                "check_in": 30,
+            }))
+            .send()
+            .await
+            .json::<Event>()
+            .await;
+        let path = format!("/event/{}", event.id().to_string());
+
+        suite
+            .connector()
+            .post(format!("{path}/registration").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        let mut other = suite.account().clone();
+        other.set_id(Id::new(("account", "other")));
+        other.set_username("other".to_owned());
+        suite
+            .connection()
+            .create::<Option<Account>>(("account", "other"))
+            .content(&other)
+            .await?;
+        let waitlisted = event.register(other.id(), suite.connection()).await?;
+        assert_eq!(RegistrationStatus::Waitlisted, *waitlisted.status());
+
+        // the check-in opens 30 minutes before the start
+        let response = suite
+            .connector()
+            .post(format!("{path}/check-in").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .patch(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "checkIn": 90 }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let response = suite
+            .connector()
+            .post(format!("{path}/check-in").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response
+            .json::<EventRegistration>()
+            .await
+            .checked_in_at()
+            .is_some());
+
+        let dashboard = suite
+            .connector()
+            .get(format!("{path}/check-in").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<serde_json::Value>()
+            .await;
+        assert_eq!(1, dashboard["registered"]);
+        assert_eq!(1, dashboard["checkedIn"]);
+        assert_eq!(1, dashboard["waitlisted"]);
+        assert_eq!("username", dashboard["players"][0]["username"]);
+
+        // the freed place is given to the waitlist
+        suite
+            .connector()
+            .delete(format!("{path}/registration").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        let registration = event.registration(other.id(), suite.connection()).await?;
+        assert_eq!(
+            Some(RegistrationStatus::Registered),
+            registration.map(|registration| *registration.status())
+        );
+
+        // players who didn't check in are dropped once the event started
+        let mut event = event;
+        event
+            .patch(
+                &EventPatch {
+                    start: Some(Utc::now() - Duration::minutes(1)),
+                    ..Default::default()
+                },
+                suite.connection(),
+            )
+            .await?;
+        let dropped = event.close_check_in(suite.connection()).await?;
+        assert_eq!(1, dropped.len());
+        assert_eq!(RegistrationStatus::Dropped, *dropped[0].status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_teams() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+        let path = format!("/event/{}/teams", event.id().to_string());
+
+        let clan = Clan::create(
+            "Iron Legion",
+            "IRON",
+            "",
+            suite.account(),
+            suite.connection(),
+        )
+        .await?;
+        let join = serde_json::json!({
+            "clan": clan.id().to_string(),
+            "roster": [suite.account().id().to_string()]
+        });
+
+        // members need a linked account
+        let response = suite
+            .connector()
+            .post(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&join)
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let mut account = suite.account().clone();
+        account.link("Player", suite.connection()).await?;
+        let response = suite
+            .connector()
+            .post(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&join)
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let team = response.json::<EventTeam>().await;
+        assert_eq!("IRON", team.token());
+        assert_eq!(&vec!["player".to_owned()], team.members());
+        assert!(event
+            .registration(account.id(), suite.connection())
+            .await?
+            .is_some());
+
+        let response = suite
+            .connector()
+            .post(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&join)
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let teams = suite
+            .connector()
+            .get(path.as_str())
+            .send()
+            .await
+            .json::<Vec<EventTeam>>()
+            .await;
+        assert_eq!(vec![team.clone()], teams);
+
+        let response = suite
+            .connector()
+            .delete(format!("{path}/{}", team.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(event.teams(suite.connection()).await?.is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_announcements() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+
+        let response = suite
+            .connector()
+            .post(format!("/event/{}/announcements", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "message": "  " }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .post(format!("/event/{}/announcements", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "message": "the finals start in 5 minutes" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        let response = suite
+            .connector()
+            .get(format!("/event/{}/announcements", event.id().to_string()).as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let announcements = response.json::<Vec<EventAnnouncement>>().await;
+        assert_eq!(1, announcements.len());
+        assert_eq!("the finals start in 5 minutes", announcements[0].message());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_results() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let machine = suite.authenticate_machine(session.as_str()).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let event = response.json::<Event>().await;
+        let path = format!("/event/{}", event.id().to_string());
+
+        let mut account = suite.account().clone();
+        account.link("player", suite.connection()).await?;
+        suite
+            .connector()
+            .post(format!("{path}/registration").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        suite
+            .connector()
+            .post(format!("{path}/status").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "status": "running" }))
+            .send()
+            .await;
+        let fight = suite
+            .connector()
+            .post(format!("{path}/fights").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "final" }))
+            .send()
+            .await
+            .json::<EventFight>()
+            .await;
+
+        let report = |other: &str| {
+            serde_json::json!({
+                "key": "match-1",
+                "fight": fight.id().to_string(),
+                "participants": [
+                    { "uuid": "player", "kills": 3, "deaths": 1 },
+                    { "uuid": other, "kills": 1, "deaths": 3 }
+                ],
+                "duration": 300,
+                "winner": "player"
+            })
+        };
+
+        // only machine clients report results
+        let response = suite
+            .connector()
+            .post(format!("{path}/results").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&report("player"))
+            .send()
+            .await;
+        assert_eq!(StatusCode::FORBIDDEN, response.status());
+
+        // machine clients can't use routes of accounts
+        let response = suite
+            .connector()
+            .delete(path.as_str())
+            .header(AUTHORIZATION, machine.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::UNAUTHORIZED, response.status());
+
+        // the other participant isn't registered
+        let response = suite
+            .connector()
+            .post(format!("{path}/results").as_str())
+            .header(AUTHORIZATION, machine.as_str())
+            .json(&report("stranger"))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let mut other = suite.account().clone();
+        other.set_id(Id::new(("account", "other")));
+        other.set_username("other".to_owned());
+        suite
+            .connection()
+            .create::<Option<Account>>(("account", "other"))
+            .content(&other)
+            .await?;
+        other.link("stranger", suite.connection()).await?;
+        event.register(other.id(), suite.connection()).await?;
+
+        let response = suite
+            .connector()
+            .post(format!("{path}/results").as_str())
+            .header(AUTHORIZATION, machine.as_str())
+            .json(&report("stranger"))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let result = response.json::<MatchResult>().await;
+
+        // repeated reports are idempotent
+        let response = suite
+            .connector()
+            .post(format!("{path}/results").as_str())
+            .header(AUTHORIZATION, machine.as_str())
+            .json(&report("stranger"))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(result, response.json::<MatchResult>().await);
+
+        let mut changed = report("stranger");
+        changed["winner"] = serde_json::json!("stranger");
+        let response = suite
+            .connector()
+            .post(format!("{path}/results").as_str())
+            .header(AUTHORIZATION, machine.as_str())
+            .json(&changed)
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let fights = suite
+            .connector()
+            .get(format!("{path}/fights").as_str())
+            .send()
+            .await
+            .json::<Vec<EventFight>>()
+            .await;
+        assert_eq!(Some("player".to_owned()), *fights[0].winner());
+
+        let results = suite
+            .connector()
+            .get(format!("{path}/results").as_str())
+            .send()
+            .await
+            .json::<Vec<MatchResult>>()
+            .await;
+        assert_eq!(vec![result], results);
+
+        // the winner is rated higher and seeded first
+        let seeding = suite
+            .connector()
+            .get(format!("{path}/seeding").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<SeededPlayer>>()
+            .await;
+        assert_eq!(2, seeding.len());
+        assert_eq!(
+            ("player", 1516.0),
+            (seeding[0].uuid.as_str(), seeding[0].rating)
+        );
+        assert_eq!(
+            ("stranger", 1484.0),
+            (seeding[1].uuid.as_str(), seeding[1].rating)
+        );
+
+        let groups = suite
+            .connector()
+            .get(format!("{path}/matchmaking?size=2").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<Vec<SeededPlayer>>>()
+            .await;
+        assert_eq!(vec![seeding], groups);
+
+        let history = suite
+            .connector()
+            .get("/player/stranger/ratings")
+            .send()
+            .await
+            .json::<Vec<RatingChange>>()
+            .await;
+        assert_eq!(1, history.len());
+        assert_eq!(-16.0, *history[0].change());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_feed() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        for (name, start) in [("past", -2), ("later", 2), ("next", 1)] {
+            suite
+                .connector()
+                .post("/event")
+                .header(AUTHORIZATION, session.as_str())
+                .json(&serde_json::json!({
+                    "name": name,
+                    "description": "description",
+                    "start": Utc::now() + Duration::days(start),
+                    "end": Utc::now() + Duration::days(start) + Duration::hours(1),
+                }))
+                .send()
+                .await;
+        }
+
+        let response = suite.connector().get("/event/feed.rss").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "application/rss+xml; charset=utf-8",
+            response.headers()[CONTENT_TYPE]
+        );
+        let channel = rss::Channel::read_from(response.bytes().await.as_ref())?;
+        assert_eq!(
+            vec!["next", "later"],
+            channel
+                .items()
+                .iter()
+                .filter_map(|item| item.title())
+                .collect::<Vec<&str>>()
+        );
+        assert!(channel.items()[0]
+            .link()
+            .unwrap()
+            .starts_with(crate::prelude::ROOT.as_str()));
+
+        let response = suite.connector().get("/event/feed.atom").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        let feed = atom_syndication::Feed::read_from(response.bytes().await.as_ref())?;
+        assert_eq!(2, feed.entries().len());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_calendar() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let mut events = Vec::new();
+        for name in ["registered", "other"] {
+            let response = suite
+                .connector()
+                .post("/event")
+                .header(AUTHORIZATION, session.as_str())
+                .json(&serde_json::json!({
+                    "name": name,
+                    "description": "description",
+                    "start": Utc::now() + Duration::days(1),
+                    "end": Utc::now() + Duration::days(2),
+                }))
+                .send()
+                .await;
+            events.push(response.json::<Event>().await);
+        }
+        let registration = format!("/event/{}/registration", events[0].id().to_string());
+        let response = suite
+            .connector()
+            .post(registration.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let response = suite
+            .connector()
+            .post(registration.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let response = suite.connector().get("/event/calendar.ics").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "text/calendar; charset=utf-8",
+            response.headers()[CONTENT_TYPE]
+        );
+        let calendar = response.text().await;
+        assert_eq!(2, calendar.matches("BEGIN:VEVENT").count());
+
+        let calendar = suite
+            .connector()
+            .get(format!("/event/{}/ics", events[1].id().to_string()).as_str())
+            .send()
+            .await
+            .text()
+            .await;
+        assert!(calendar.contains(format!("UID:{}@", events[1].id().id).as_str()));
+        assert!(calendar.contains("SUMMARY:other"));
+
+        let url = suite
+            .connector()
+            .get("/event/calendar/personal")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<PersonalCalendarResponse>()
+            .await
+            .url;
+        let path = &url[url.find("/event/").unwrap()..];
+        let calendar = suite.connector().get(path).send().await.text().await;
+        assert_eq!(1, calendar.matches("BEGIN:VEVENT").count());
+        assert!(calendar.contains("SUMMARY:registered"));
+
+        // renewing the url invalidates the previous one
+        suite
+            .connector()
+            .post("/event/calendar/personal")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        let response = suite.connector().get(path).send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let create = |status: &str, start: i64, end: i64| {
+            serde_json::json!({
+                "name": status,
+                "description": "description",
+                "start": Utc::now() + Duration::hours(start),
+                "end": Utc::now() + Duration::hours(end),
+                "status": status,
+            })
+        };
+
+        let response = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&create("draft", 2, 1))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let response = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&create("running", 1, 2))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let draft = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&create("draft", 1, 2))
+            .send()
+            .await
+            .json::<Event>()
+            .await;
+        suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&create("published", -2, -1))
+            .send()
+            .await;
+
+        // drafts are hidden from the public
+        let path = format!("/event/{}", draft.id().to_string());
+        let response = suite.connector().get(path.as_str()).send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let page = suite
+            .connector()
+            .get("/event")
+            .send()
+            .await
+            .json::<Page<Event>>()
+            .await;
+        assert_eq!(1, page.total);
+        let page = suite
+            .connector()
+            .get("/event/all?window=upcoming")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<Event>>()
+            .await;
+        assert_eq!(vec![draft.clone()], page.data);
+
+        let status = |status: &str| serde_json::json!({ "status": status });
+        let response = suite
+            .connector()
+            .post(format!("{}/status", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&status("running"))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        for next in ["published", "running", "finished"] {
+            let response = suite
+                .connector()
+                .post(format!("{}/status", path).as_str())
+                .header(AUTHORIZATION, session.as_str())
+                .json(&status(next))
+                .send()
+                .await;
+            assert_eq!(StatusCode::OK, response.status());
+        }
+
+        // finished events can't be changed anymore
+        let response = suite
+            .connector()
+            .put(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "id": draft.id().to_string(),
+                "name": "other",
+                "description": "description",
+                "start": draft.start().clone(),
+                "end": draft.end().clone(),
+                "status": "finished",
+                "createdAt": draft.created_at().clone()
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let page = suite
+            .connector()
+            .get("/event?window=past")
+            .send()
+            .await
+            .json::<Page<Event>>()
+            .await;
+        assert_eq!(1, page.total);
+        assert_eq!("published", page.data[0].name());
 
         Ok(())
     }
@@ -0,0 +1,313 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::audit::Audit;
+use crate::data::event::series::{CreateSeriesRequest, EventSeries, SeriesPatch};
+use crate::data::event::Event;
+use crate::prelude::*;
+use aide::axum::routing::{delete_with, get_with, post_with};
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::Extension;
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/",
+            post_with(create, create_docs).layer(require_session!(state, EVENT_CREATE)),
+        )
+        .api_route(
+            "/",
+            get_with(get_all, get_all_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:series_id",
+            get_with(get, get_docs)
+                .patch_with(patch, patch_docs)
+                .layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .api_route(
+            "/:series_id",
+            delete_with(delete, delete_docs).layer(require_session!(state, EVENT_DELETE)),
+        )
+        .api_route(
+            "/:series_id/events",
+            get_with(get_events, get_events_docs).layer(require_session!(state, EVENT_UPDATE)),
+        )
+        .with_state(state)
+}
+
+/// POST /event/series
+async fn create(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Json(data): Json<CreateSeriesRequest>,
+) -> Result<(StatusCode, Json<EventSeries>)> {
+    let connection = state.connection();
+
+    let series = EventSeries::create(data, connection).await?;
+    audit.record("event.series.create", series.id(), None, Some(&series));
+    Ok((StatusCode::CREATED, Json(series)))
+}
+
+fn create_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Create a series of recurring events. Its occurrences are created as events ahead of \
+    time, skipping the exceptions.",
+    )
+    .response::<201, Json<EventSeries>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_CREATE.id.to_string()])
+}
+
+/// GET /event/series
+async fn get_all(State(state): State<ApplicationState>) -> Result<Json<Vec<EventSeries>>> {
+    let connection = state.connection();
+
+    Ok(Json(EventSeries::all(connection).await?))
+}
+
+fn get_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all series of events")
+        .response::<200, Json<Vec<EventSeries>>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// GET /event/series/:series_id
+async fn get(
+    State(state): State<ApplicationState>,
+    Path(series_id): Path<String>,
+) -> Result<Json<EventSeries>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        EventSeries::from_id(series_id.as_str(), connection).await?,
+    ))
+}
+
+fn get_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the specified series")
+        .response::<200, Json<EventSeries>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// GET /event/series/:series_id/events
+async fn get_events(
+    State(state): State<ApplicationState>,
+    Path(series_id): Path<String>,
+) -> Result<Json<Vec<Event>>> {
+    let connection = state.connection();
+
+    let series = EventSeries::from_id(series_id.as_str(), connection).await?;
+    Ok(Json(series.events(connection).await?))
+}
+
+fn get_events_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the created occurrences of the series, drafts included")
+        .response::<200, Json<Vec<Event>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// PATCH /event/series/:series_id
+async fn patch(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(series_id): Path<String>,
+    Json(data): Json<SeriesPatch>,
+) -> Result<Json<EventSeries>> {
+    let connection = state.connection();
+
+    let series = EventSeries::from_id(series_id.as_str(), connection).await?;
+    let before = series.clone();
+    let series = series.patch(&data, connection).await?;
+    // a split series is recorded as changes of the original one
+    audit.record(
+        "event.series.update",
+        before.id(),
+        Some(&before),
+        Some(&series),
+    );
+    Ok(Json(series))
+}
+
+fn patch_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Change the series and its upcoming occurrences. Changes from a date after the first \
+    occurrence split the series, the returned one holds the changed occurrences. Occurrences \
+    edited on their own are left untouched.",
+    )
+    .response::<200, Json<EventSeries>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_UPDATE.id.to_string()])
+}
+
+/// DELETE /event/series/:series_id
+async fn delete(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(series_id): Path<String>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let series = EventSeries::from_id(series_id.as_str(), connection).await?;
+    audit.record("event.series.delete", series.id(), Some(&series), None);
+    series.delete(connection).await?;
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn delete_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Delete the series with its upcoming occurrences, the others are kept as regular events",
+    )
+    .response::<200, Json<DeletionResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![EVENT_DELETE.id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::series::EventSeries;
+    use crate::data::event::Event;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use chrono::{Duration, DurationRound, Utc};
+
+    #[tokio::test]
+    async fn test_series() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let start = Utc::now().duration_trunc(Duration::minutes(1))? + Duration::days(1);
+        let response = suite
+            .connector()
+            .post("/event/series")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "Weekly",
+                "description": "description",
+                "start": start,
+                "end": start + Duration::hours(2),
+                "rule": { "frequency": "weekly", "count": 4 },
+                "exceptions": [start + Duration::weeks(1)],
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let series = response.json::<EventSeries>().await;
+        let path = format!("/event/series/{}", series.id().to_string());
+
+        let events = suite
+            .connector()
+            .get(format!("{path}/events").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<Event>>()
+            .await;
+        assert_eq!(3, events.len());
+        assert_eq!(start + Duration::weeks(2), *events[1].start());
+
+        // a single occurrence is edited and moved on its own
+        let response = suite
+            .connector()
+            .patch(format!("/event/{}", events[0].id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "Opening",
+                "start": start + Duration::hours(1),
+                "end": start + Duration::hours(3),
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response.json::<Event>().await.detached());
+
+        // the later occurrences are split off into a new series
+        let response = suite
+            .connector()
+            .patch(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "from": start + Duration::weeks(3),
+                "name": "Finals",
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let split = response.json::<EventSeries>().await;
+        assert_ne!(series.id(), split.id());
+        assert_eq!(start + Duration::weeks(3), *split.start());
+        assert_eq!(Some(1), split.rule().count);
+
+        let events = suite
+            .connector()
+            .get(format!("{path}/events").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<Event>>()
+            .await;
+        assert_eq!(2, events.len());
+        assert_eq!("Opening", events[0].name());
+        assert_eq!("Weekly", events[1].name());
+
+        // moved and deleted occurrences aren't created again
+        let response = suite
+            .connector()
+            .delete(format!("/event/{}", events[1].id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let series =
+            EventSeries::from_id(series.id().to_string().as_str(), suite.connection()).await?;
+        assert!(series.materialize(suite.connection()).await?.is_empty());
+        assert_eq!(3, series.exceptions().len());
+
+        let response = suite
+            .connector()
+            .delete(format!("/event/series/{}", split.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let response = suite
+            .connector()
+            .get(format!("/event/series/{}", split.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+}
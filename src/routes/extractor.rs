@@ -24,10 +24,17 @@
  *
  */
 
+use crate::auth::session::{Session, SessionType};
+use crate::data::account::Account;
 use crate::prelude::*;
+use axum::extract::{ConnectInfo, FromRequestParts, Query};
+use axum::http::header::{ACCEPT_LANGUAGE, AUTHORIZATION};
+use axum::http::request::Parts;
 use axum::response::IntoResponse;
 use axum_jsonschema::JsonSchemaRejection;
 use serde::Serialize;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
 
 #[derive(FromRequest, OperationIo)]
 #[from_request(via(axum_jsonschema::Json), rejection(ApplicationError))]
@@ -60,3 +67,244 @@ impl From<JsonSchemaRejection> for ApplicationError {
         Self::BadRequest(message)
     }
 }
+
+/// The address of the requesting client. The `X-Forwarded-For` header is only honoured if the
+/// connection comes from one of the configured trusted proxies, otherwise anyone could forge it.
+#[derive(Debug, Clone)]
+pub struct ClientAddress(pub Option<String>);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for ClientAddress
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let peer = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0.ip());
+        let forwarded = parts
+            .headers
+            .get("x-forwarded-for")
+            .and_then(|value| value.to_str().ok());
+
+        Ok(Self(
+            client_address(peer, forwarded, TRUSTED_PROXIES.as_slice())
+                .map(|address| address.to_string()),
+        ))
+    }
+}
+
+/// Resolves the address of the client behind the trusted proxies. The forwarded addresses are
+/// walked from the right, as only the entries appended by trusted proxies can be relied on.
+fn client_address(
+    peer: Option<IpAddr>,
+    forwarded: Option<&str>,
+    trusted: &[IpAddr],
+) -> Option<IpAddr> {
+    let peer = peer?;
+    if !trusted.contains(&peer) {
+        return Some(peer);
+    }
+
+    let mut address = peer;
+    for entry in forwarded.unwrap_or("").rsplit(',') {
+        match entry.trim().parse::<IpAddr>() {
+            Ok(forwarded) => {
+                address = forwarded;
+                if !trusted.contains(&forwarded) {
+                    break;
+                }
+            }
+            Err(_) => break,
+        }
+    }
+    Some(address)
+}
+
+impl aide::OperationInput for ClientAddress {}
+
+/// The account of the session sent along with the request. Unlike `require_session!`, requests
+/// without a valid session of an account are let through anonymously.
+#[derive(Debug, Clone)]
+pub struct OptionalAccount(pub Option<Account>);
+
+#[async_trait]
+impl FromRequestParts<ApplicationState> for OptionalAccount {
+    type Rejection = ApplicationError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &ApplicationState,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let connection = state.connection();
+        let Some(token) = parts
+            .headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.split(' ').next_back())
+        else {
+            return Ok(Self(None));
+        };
+
+        match Session::is_session_valid(token, connection).await {
+            Ok(session) => match session.target() {
+                SessionType::Human(id) => Ok(Self(
+                    Account::from_id(id.to_string().as_str(), connection).await?,
+                )),
+                SessionType::Machine(_) => Ok(Self(None)),
+            },
+            Err(_) => Ok(Self(None)),
+        }
+    }
+}
+
+impl aide::OperationInput for OptionalAccount {}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct LanguageRequest {
+    /// the preferred language, overrides the `Accept-Language` header
+    lang: Option<String>,
+}
+
+/// The language content should be delivered in. Taken from the `lang` query parameter, the
+/// `Accept-Language` header or the default language, in that order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Language(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for Language
+where
+    S: Send + Sync,
+{
+    type Rejection = ApplicationError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let requested = Query::<LanguageRequest>::try_from_uri(&parts.uri)
+            .ok()
+            .and_then(|Query(request)| request.lang);
+        if let Some(language) = requested {
+            let language = language.to_lowercase();
+            if !LANGUAGES.contains(&language) {
+                return Err(ApplicationError::BadRequest(format!(
+                    "unsupported language {language}"
+                )));
+            }
+
+            return Ok(Self(language));
+        }
+
+        Ok(Self(
+            parts
+                .headers
+                .get(ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| negotiate(value, LANGUAGES.as_slice()))
+                .unwrap_or_else(|| DEFAULT_LANGUAGE.clone()),
+        ))
+    }
+}
+
+impl aide::OperationInput for Language {
+    fn operation_input(ctx: &mut aide::gen::GenContext, operation: &mut aide::openapi::Operation) {
+        Query::<LanguageRequest>::operation_input(ctx, operation);
+    }
+}
+
+/// Picks the supported language the client prefers most from an `Accept-Language` header.
+/// Regional variants match their primary language, e.g. `de-AT` matches `de`.
+fn negotiate(header: &str, supported: &[String]) -> Option<String> {
+    let mut preferences = header
+        .split(',')
+        .filter_map(|entry| {
+            let mut parts = entry.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .filter_map(|parameter| parameter.trim().strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+
+            Some((tag, quality))
+        })
+        .filter(|(tag, quality)| !tag.is_empty() && *quality > 0.0)
+        .collect::<Vec<(&str, f32)>>();
+    // the sort is stable, so equally weighted languages keep their order
+    preferences.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+
+    preferences.into_iter().find_map(|(tag, _)| {
+        let primary = tag.split('-').next()?.to_lowercase();
+        supported
+            .iter()
+            .find(|language| **language == primary)
+            .cloned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routes::extractor::{client_address, negotiate};
+    use std::net::IpAddr;
+
+    #[test]
+    fn test_negotiate() {
+        let supported = vec!["de".to_owned(), "en".to_owned()];
+
+        assert_eq!(
+            Some("en".to_owned()),
+            negotiate("en-US,en;q=0.9,de;q=0.8", supported.as_slice())
+        );
+        assert_eq!(
+            Some("de".to_owned()),
+            negotiate(
+                "fr-CH, fr;q=0.9, en;q=0.7, de-AT;q=0.8",
+                supported.as_slice()
+            )
+        );
+        assert_eq!(
+            Some("en".to_owned()),
+            negotiate("de;q=0, EN", supported.as_slice())
+        );
+        assert_eq!(None, negotiate("fr, *;q=0.5", supported.as_slice()));
+    }
+
+    #[test]
+    fn test_client_address() {
+        let proxy: IpAddr = "10.0.0.1".parse().unwrap();
+        let client: IpAddr = "203.0.113.7".parse().unwrap();
+        let other: IpAddr = "198.51.100.1".parse().unwrap();
+        let trusted = vec![proxy];
+
+        // the header of untrusted peers is ignored
+        assert_eq!(
+            Some(client),
+            client_address(Some(client), Some("198.51.100.1"), trusted.as_slice())
+        );
+        // forged entries in front of the address appended by the proxy are skipped
+        assert_eq!(
+            Some(client),
+            client_address(
+                Some(proxy),
+                Some("198.51.100.1, 203.0.113.7"),
+                trusted.as_slice()
+            )
+        );
+        assert_eq!(
+            Some(other),
+            client_address(Some(proxy), Some(" 198.51.100.1 "), trusted.as_slice())
+        );
+        // without the header the proxy itself is the client
+        assert_eq!(
+            Some(proxy),
+            client_address(Some(proxy), None, trusted.as_slice())
+        );
+        assert_eq!(None, client_address(None, Some("198.51.100.1"), &[]));
+    }
+}
@@ -24,9 +24,52 @@
  *
  */
 
+use crate::data::audit::Audit;
+use crate::prelude::*;
+use crate::routes::extractor::ClientAddress;
+use axum::extract::State;
+use axum::http::{Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+
+/// Records every successful mutating request in the audit log. Handlers may describe the action
+/// through the [Audit] extension. The address is resolved by [ClientAddress], which only trusts
+/// forwarded addresses of the configured proxies.
+pub async fn audit<B>(
+    State(state): State<ApplicationState>,
+    ClientAddress(address): ClientAddress,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let audit = Audit::default();
+    request.extensions_mut().insert(audit.clone());
+    let mutating = !matches!(
+        *request.method(),
+        Method::GET | Method::HEAD | Method::OPTIONS
+    );
+    let action = format!("{} {}", request.method(), request.uri().path());
+
+    let response = next.run(request).await;
+    if mutating && response.status().is_success() {
+        if let Err(error) = audit.write(action, address, state.connection()).await {
+            warn!("failed to write the audit entry: {error}");
+        }
+    }
+    response
+}
+
+/// Requires a valid session of an account with the given permission. Sessions of machine clients
+/// are rejected unless the route opts in with `require_session!(state, PERMISSION, machine)`, the
+/// handler is then responsible for checking the [SessionType](crate::auth::session::SessionType).
 #[macro_export]
 macro_rules! require_session {
-    ($state:ident, $permission:path) => {{
+    ($state:ident, $permission:path) => {
+        $crate::require_session!(@layer $state, $permission, false)
+    };
+    ($state:ident, $permission:path, machine) => {
+        $crate::require_session!(@layer $state, $permission, true)
+    };
+    (@layer $state:ident, $permission:path, $machine:literal) => {{
         use axum::extract::State;
         use axum::http::header::AUTHORIZATION;
         use axum::http::Request;
@@ -35,6 +78,7 @@ macro_rules! require_session {
         use $crate::auth::authz::Authorizable;
         use $crate::auth::session::{Session, SessionType};
         use $crate::data::account::Account;
+        use $crate::data::audit::Audit;
         use $crate::prelude::*;
 
         async fn require_session<B>(
@@ -62,6 +106,10 @@ macro_rules! require_session {
                             let guard = span.enter();
                             // borrow the mutable extensions
                             let extensions = request.extensions_mut();
+                            // attribute the audited action to the session
+                            if let Some(audit) = extensions.get::<Audit>() {
+                                audit.session(&session);
+                            }
 
                             if let SessionType::Human(id) = session.target() {
                                 let account = Account::from_id(id.to_string().as_str(), connection)
@@ -80,9 +128,13 @@ macro_rules! require_session {
                                 } else {
                                     Err(ApplicationError::Unauthorized)
                                 }
-                            } else {
+                            } else if $machine {
+                                // machine clients are trusted, but handlers may check the type
+                                extensions.insert(session);
                                 drop(guard);
                                 Ok(())
+                            } else {
+                                Err(ApplicationError::Unauthorized)
                             }
                         }
                         Err(error) => Err(error),
@@ -24,26 +24,72 @@
  *
  */
 
+use crate::data::feed::{Feed, FeedFormat};
 use crate::prelude::*;
 use aide::axum::ApiRouter;
+use axum::extract::BodyStream;
+use axum::http::header::CONTENT_TYPE;
+use axum::response::{IntoResponse, Response};
+use futures::TryStreamExt;
+use tokio::io::AsyncReadExt;
+use tokio_util::io::StreamReader;
 
 mod account;
+mod asset;
+mod audit;
 mod auth;
+mod clan;
 pub mod docs;
 mod event;
 pub mod extractor;
 mod middleware;
 mod news;
+mod notification;
+mod player;
+mod season;
+mod share;
 
 pub fn router(state: ApplicationState) -> ApiRouter {
     ApiRouter::new()
         .nest_api_service("/auth", auth::router(state.clone()))
         .nest_api_service("/account", account::router(state.clone()))
+        .nest_api_service("/asset", asset::router(state.clone()))
+        .nest_api_service("/clan", clan::router(state.clone()))
         .nest_api_service("/event", event::router(state.clone()))
         .nest_api_service("/news", news::router(state.clone()))
+        .nest_api_service("/notification", notification::router(state.clone()))
+        .nest_api_service("/player", player::router(state.clone()))
+        .nest_api_service("/season", season::router(state.clone()))
+        .nest_api_service("/share", share::router(state.clone()))
+        .nest_api_service("/audit", audit::router(state.clone()))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            middleware::audit,
+        ))
         .with_state(state)
 }
 
+/// Reads the whole request body, but stops right after exceeding the given limit of bytes
+pub async fn read_body(body: BodyStream, limit: u64) -> Result<Vec<u8>> {
+    let stream_reader = StreamReader::new(body.map_err(std::io::Error::other));
+    futures::pin_mut!(stream_reader);
+
+    let mut data = Vec::new();
+    stream_reader.take(limit + 1).read_to_end(&mut data).await?;
+    if data.len() as u64 > limit {
+        return Err(ApplicationError::BadRequest(format!(
+            "upload exceeds the maximum size of {limit} bytes"
+        )));
+    }
+
+    Ok(data)
+}
+
+/// Renders the feed in the given format
+pub fn feed_response(feed: &Feed, format: FeedFormat) -> Response {
+    ([(CONTENT_TYPE, format.mime())], feed.render(format)).into_response()
+}
+
 #[derive(Serialize, Debug, Clone, JsonSchema)]
 pub struct DeletionResponse {
     pub deleted: bool,
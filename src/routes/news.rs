@@ -24,20 +24,25 @@
  *
  */
 
-use crate::data::news::News;
+use crate::auth::authz::Authorizable;
+use crate::data::account::Account;
+use crate::data::asset::image::ImageVariants;
+use crate::data::audit::Audit;
+use crate::data::feed::{api_link, page_link, Feed, FeedFormat, FEED_LIMIT};
+use crate::data::news::content::NewsContent;
+use crate::data::news::revision::{NewsDiff, NewsRevision};
+use crate::data::news::translation::{MissingTranslations, NewsTranslation, NewsTranslations};
+use crate::data::news::{News, NewsPatch, NewsSchedule};
 use crate::prelude::*;
+use crate::routes::extractor::{Language, OptionalAccount};
+use crate::routes::{feed_response, read_body};
 use aide::axum::routing::{delete_with, get_with, post_with, put_with};
 use aide::axum::ApiRouter;
 use aide::transform::TransformOperation;
 use axum::extract::{BodyStream, Path, Query, State};
 use axum::http::StatusCode;
-use futures::TryStreamExt;
-use image::io::Reader as ImageReader;
-use std::io::Cursor;
-use tokio::io::AsyncReadExt;
-use tokio_util::io::StreamReader;
-
-const CDN_DIR: &str = "cdn";
+use axum::response::Response;
+use axum::Extension;
 
 pub fn router(state: ApplicationState) -> ApiRouter {
     ApiRouter::new()
@@ -48,10 +53,42 @@ pub fn router(state: ApplicationState) -> ApiRouter {
         .api_route(
             "/:news_id",
             put_with(update_news, update_docs)
+                .patch_with(patch_news, patch_docs)
                 .post_with(upload_image, upload_image_docs)
                 .layer(require_session!(state, NEWS_UPDATE)),
         )
+        .api_route(
+            "/:news_id/revisions",
+            get_with(get_revisions, get_revisions_docs).layer(require_session!(state, NEWS_UPDATE)),
+        )
+        .api_route(
+            "/:news_id/revisions/diff",
+            get_with(get_diff, get_diff_docs).layer(require_session!(state, NEWS_UPDATE)),
+        )
+        .api_route(
+            "/:news_id/revisions/:revision/restore",
+            post_with(restore_revision, restore_revision_docs)
+                .layer(require_session!(state, NEWS_UPDATE)),
+        )
+        .api_route(
+            "/translations/missing",
+            get_with(get_missing_translations, get_missing_translations_docs)
+                .layer(require_session!(state, NEWS_UPDATE)),
+        )
+        .api_route(
+            "/:news_id/translations",
+            get_with(get_translations, get_translations_docs)
+                .layer(require_session!(state, NEWS_UPDATE)),
+        )
+        .api_route(
+            "/:news_id/translations/:language",
+            put_with(translate, translate_docs)
+                .delete_with(remove_translation, remove_translation_docs)
+                .layer(require_session!(state, NEWS_UPDATE)),
+        )
         .api_route("/shown", get_with(get_shown, get_shown_docs))
+        .api_route("/feed.rss", get_with(get_rss, get_rss_docs))
+        .api_route("/feed.atom", get_with(get_atom, get_atom_docs))
         .api_route("/:news_id", get_with(get, get_docs))
         .api_route(
             "/",
@@ -66,33 +103,49 @@ pub fn router(state: ApplicationState) -> ApiRouter {
 
 #[derive(Deserialize, Debug, Clone, JsonSchema)]
 pub struct CreateNewsRequest {
-    /// the content of the news (html supported -> server side xss cleanup)
-    content: Option<String>,
+    /// the content of the news (html or markdown -> rendered and cleaned up server side)
+    #[serde(flatten)]
+    body: NewsContent,
     title: String,
+    #[serde(default)]
+    shown: bool,
+    #[serde(flatten)]
+    schedule: NewsSchedule,
 }
 
 /// POST /news
 async fn create(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    account: Option<Extension<Account>>,
     Json(data): Json<CreateNewsRequest>,
 ) -> Result<(StatusCode, Json<News>)> {
     let connection = state.connection();
 
-    Ok((
-        StatusCode::CREATED,
-        Json(News::new(data.title.as_str(), data.content.as_deref(), connection).await?),
-    ))
+    let news = News::new(
+        data.title.as_str(),
+        &data.body,
+        data.shown,
+        &data.schedule,
+        author(&account),
+        connection,
+    )
+    .await?;
+    audit.record("news.create", news.id(), None, Some(&news));
+    Ok((StatusCode::CREATED, Json(news)))
 }
 
 fn create_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Create news")
+    op.description("Create news. Shown news are public between publishAt and unpublishAt.")
         .response::<201, Json<News>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
         .security_requirement_scopes("Session", vec![NEWS_CREATE.id.to_string()])
 }
 
 /// POST /news/:news_id
 async fn upload_image(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Path(news_id): Path<String>,
     body: BodyStream,
 ) -> Result<Json<CreationResponse>> {
@@ -105,32 +158,20 @@ async fn upload_image(
             .await?
     );
     match news {
-        Some(_) => {
-            let stream_reader = StreamReader::new(
-                body.map_err(|error| std::io::Error::new(std::io::ErrorKind::Other, error)),
-            );
-            futures::pin_mut!(stream_reader);
-            // read the stream
-            let mut buffer = Vec::<u8>::new();
-            stream_reader.read_to_end(&mut buffer).await?;
-
-            // read the image
-            let image_reader = ImageReader::new(Cursor::new(buffer))
-                .with_guessed_format()
-                .map_err(|_| ApplicationError::BadRequest("invalid image data".to_owned()))?;
-            // decode it
-            let image = image_reader
-                .decode()
-                .map_err(|_| ApplicationError::BadRequest("invalid image data".to_owned()))?;
-
-            // create the file
-            let path = std::path::Path::new(CDN_DIR).join("news");
-            tokio::fs::create_dir_all(&path).await?;
-            let path = path.join(news_id).with_extension("png");
-            // write the new decoded image as png
-            image
-                .save(path)
-                .map_err(|_| ApplicationError::InternalServerError)?;
+        Some(news) => {
+            // read the stream, but stop right after exceeding the upload limit
+            let buffer = read_body(body, *IMAGE_UPLOAD_LIMIT).await?;
+
+            // store the variants as assets, which replaces the previous ones
+            let images = ImageVariants::store(news.id(), "image", buffer, connection).await?;
+            sql_span!(connection
+                .query("UPDATE $news SET images = $images")
+                .bind(("news", news.id().to_thing()))
+                .bind(("images", images))
+                .await?
+                .check()?);
+            audit.action("news.image");
+            audit.target(news.id());
 
             Ok(Json(CreationResponse::from(true)))
         }
@@ -139,109 +180,389 @@ async fn upload_image(
 }
 
 fn upload_image_docs(op: TransformOperation) -> TransformOperation {
-    op.description("upload an thumbnail for a news object")
-        .response::<200, Json<CreationResponse>>()
-        .response::<400, Json<ApplicationErrorResponse>>()
-        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+    op.description(
+        "upload an thumbnail for a news object. The image is stored as webp and jpeg in the \
+    variants thumbnail, card and full. Fails if the image exceeds the size or pixel limit.",
+    )
+    .response::<200, Json<CreationResponse>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
 }
 
 /// GET /news
 async fn get_all(
     State(state): State<ApplicationState>,
     Query(request): Query<PagingRequest>,
+    Language(language): Language,
 ) -> Result<Json<Page<News>>> {
     let connection = state.connection();
 
-    Ok(Json(
-        request
-            .execute::<News, &str>("SELECT * FROM news", None, connection)
-            .await?,
-    ))
+    let mut page = News::page(request, connection).await?;
+    News::localize(&mut page.data, language.as_str(), connection).await?;
+    Ok(Json(page))
 }
 
 fn get_all_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get a page of news")
-        .response::<200, Json<Page<News>>>()
-        .security_requirement_scopes("Session", vec![NEWS_GET_ALL.id.to_string()])
+    op.description(
+        "Get a page of all news, pinned and newest first. The news are translated to the \
+    requested language if possible.",
+    )
+    .response::<200, Json<Page<News>>>()
+    .security_requirement_scopes("Session", vec![NEWS_GET_ALL.id.to_string()])
 }
 
 /// GET /news/shown
-async fn get_shown(State(state): State<ApplicationState>) -> Result<Json<Vec<News>>> {
+async fn get_shown(
+    State(state): State<ApplicationState>,
+    Query(request): Query<PagingRequest>,
+    Language(language): Language,
+) -> Result<Json<Page<News>>> {
     let connection = state.connection();
 
-    let news = sql_span!(connection
-        .query("SELECT * FROM news where shown = true")
-        .await?
-        .take::<Vec<News>>(0)?);
-    Ok(Json(news))
+    let mut page = News::published(request, connection).await?;
+    News::localize(&mut page.data, language.as_str(), connection).await?;
+    Ok(Json(page))
 }
 
 fn get_shown_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get all shown / active news")
-        .response::<200, Json<Vec<News>>>()
+    op.description(
+        "Get a page of the shown news within their publishing schedule. Pinned news come first, \
+    followed by the news with the highest priority and then the newest. The news are \
+    translated to the requested language if possible.",
+    )
+    .response::<200, Json<Page<News>>>()
+}
+
+/// Builds the feed of the latest published news
+async fn feed(state: &ApplicationState, language: &str) -> Result<Feed> {
+    let connection = state.connection();
+
+    let request = PagingRequest {
+        page: 1,
+        page_size: FEED_LIMIT,
+    };
+    let mut news = News::published(request, connection).await?.data;
+    News::localize(&mut news, language, connection).await?;
+
+    Ok(Feed {
+        title: format!("{} News", *FEED_TITLE),
+        description: format!("The latest news of {}", *FEED_TITLE),
+        link: page_link("/"),
+        location: api_link("/news/feed"),
+        language: Some(language.to_owned()),
+        entries: news.iter().map(Into::into).collect(),
+    })
+}
+
+/// GET /news/feed.rss
+async fn get_rss(
+    State(state): State<ApplicationState>,
+    Language(language): Language,
+) -> Result<Response> {
+    let feed = feed(&state, language.as_str()).await?;
+    Ok(feed_response(&feed, FeedFormat::Rss))
+}
+
+fn get_rss_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the latest published news as RSS feed")
+        .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /news/feed.atom
+async fn get_atom(
+    State(state): State<ApplicationState>,
+    Language(language): Language,
+) -> Result<Response> {
+    let feed = feed(&state, language.as_str()).await?;
+    Ok(feed_response(&feed, FeedFormat::Atom))
+}
+
+fn get_atom_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the latest published news as Atom feed")
+        .response::<400, Json<ApplicationErrorResponse>>()
 }
 
 /// GET /news/:news_id
 async fn get(
     State(state): State<ApplicationState>,
     Path(news_id): Path<String>,
+    Language(language): Language,
+    OptionalAccount(account): OptionalAccount,
 ) -> Result<Json<News>> {
     let connection = state.connection();
 
-    let news: Option<News> = sql_span!(
-        connection
-            .select(&Id::try_from(("news", news_id.as_str()))?)
-            .await?
-    );
-    Ok(Json(news.ok_or(ApplicationError::BadRequest(
-        "news not found".to_owned(),
-    ))?))
+    let mut news = News::from_id(news_id.as_str(), connection).await?;
+    // unpublished news are only visible to editors
+    if !news.is_published() {
+        let editor = match account {
+            Some(account) => account
+                .has_permission(&NEWS_UPDATE, connection)
+                .await
+                .is_ok(),
+            None => false,
+        };
+        if !editor {
+            return Err(ApplicationError::BadRequest("news not found".to_owned()));
+        }
+    }
+    News::localize(
+        std::slice::from_mut(&mut news),
+        language.as_str(),
+        connection,
+    )
+    .await?;
+    Ok(Json(news))
 }
 
 fn get_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Get a specific news object")
-        .response::<200, Json<News>>()
-        .response::<400, Json<ApplicationErrorResponse>>()
+    op.description(
+        "Get a specific news object, translated to the requested language if possible. Falls \
+    back to the default language. News which aren't published are only returned to accounts with \
+    the permission 'news.update'.",
+    )
+    .response::<200, Json<News>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
 }
 
 /// PUT /news/:news_id
 async fn update_news(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Path(news_id): Path<String>,
+    account: Option<Extension<Account>>,
     Json(data): Json<News>,
 ) -> Result<Json<News>> {
     let connection = state.connection();
 
-    let news: News = sql_span!(connection
-        .query("UPDATE $news CONTENT $content")
-        .bind(("news", Id::try_from(("news", news_id.as_str()))?.to_thing()))
-        .bind(("content", data))
-        .await?
-        .take::<Option<News>>(0)?
-        .ok_or(ApplicationError::BadRequest("news not found".to_owned()))?);
+    let mut news = News::from_id(news_id.as_str(), connection).await?;
+    let before = news.clone();
+    news.update(&data, author(&account), connection).await?;
+    audit.record("news.update", news.id(), Some(&before), Some(&news));
 
     Ok(Json(news))
 }
 
 fn update_docs(op: TransformOperation) -> TransformOperation {
-    op.description("Update already existing news")
+    op.description(
+        "Update already existing news. The given revision has to be the current revision of the \
+    news, otherwise the update is rejected as the news have been changed in the meantime. The \
+    previous revision is archived. Only the default language can be updated, translations are \
+    edited separately.",
+    )
+    .response::<200, Json<News>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+/// PATCH /news/:news_id
+async fn patch_news(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path(news_id): Path<String>,
+    account: Option<Extension<Account>>,
+    Json(data): Json<NewsPatch>,
+) -> Result<Json<News>> {
+    let connection = state.connection();
+
+    let mut news = News::from_id(news_id.as_str(), connection).await?;
+    let before = news.clone();
+    news.patch(&data, author(&account), connection).await?;
+    audit.record("news.update", news.id(), Some(&before), Some(&news));
+
+    Ok(Json(news))
+}
+
+fn patch_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Partially update already existing news, absent fields are left untouched and `null` \
+    clears the publishing schedule. Like full updates, the changes have to be based on the \
+    current revision and a given id has to match the updated news.",
+    )
+    .response::<200, Json<News>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+/// The id of the requesting account, machine clients don't have one
+fn author(account: &Option<Extension<Account>>) -> Option<&Id> {
+    account.as_ref().map(|Extension(account)| account.id())
+}
+
+/// GET /news/:news_id/revisions
+async fn get_revisions(
+    State(state): State<ApplicationState>,
+    Path(news_id): Path<String>,
+) -> Result<Json<Vec<NewsRevision>>> {
+    let connection = state.connection();
+
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    Ok(Json(news.revisions(connection).await?))
+}
+
+fn get_revisions_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all revisions of news, newest first")
+        .response::<200, Json<Vec<NewsRevision>>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct DiffRequest {
+    from: u32,
+    to: u32,
+}
+
+/// GET /news/:news_id/revisions/diff
+async fn get_diff(
+    State(state): State<ApplicationState>,
+    Path(news_id): Path<String>,
+    Query(request): Query<DiffRequest>,
+) -> Result<Json<NewsDiff>> {
+    let connection = state.connection();
+
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    Ok(Json(news.diff(request.from, request.to, connection).await?))
+}
+
+fn get_diff_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the word-wise difference of title and content between two revisions")
+        .response::<200, Json<NewsDiff>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+/// POST /news/:news_id/revisions/:revision/restore
+async fn restore_revision(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path((news_id, revision)): Path<(String, u32)>,
+    account: Option<Extension<Account>>,
+) -> Result<Json<News>> {
+    let connection = state.connection();
+
+    let mut news = News::from_id(news_id.as_str(), connection).await?;
+    let before = news.clone();
+    news.restore(revision, author(&account), connection).await?;
+    audit.record("news.restore", news.id(), Some(&before), Some(&news));
+
+    Ok(Json(news))
+}
+
+fn restore_revision_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Restore the title and content of a previous revision as new revision")
         .response::<200, Json<News>>()
         .response::<400, Json<ApplicationErrorResponse>>()
         .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
 }
 
+/// GET /news/translations/missing
+async fn get_missing_translations(
+    State(state): State<ApplicationState>,
+) -> Result<Json<Vec<MissingTranslations>>> {
+    Ok(Json(News::missing_translations(state.connection()).await?))
+}
+
+fn get_missing_translations_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all news with missing translations and the languages they are missing")
+        .response::<200, Json<Vec<MissingTranslations>>>()
+        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+/// GET /news/:news_id/translations
+async fn get_translations(
+    State(state): State<ApplicationState>,
+    Path(news_id): Path<String>,
+) -> Result<Json<NewsTranslations>> {
+    let connection = state.connection();
+
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    Ok(Json(news.translations(connection).await?))
+}
+
+fn get_translations_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List the translations of news and the languages they are missing for")
+        .response::<200, Json<NewsTranslations>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct TranslateRequest {
+    title: String,
+    /// the translated content (html or markdown -> rendered and cleaned up server side)
+    #[serde(flatten)]
+    body: NewsContent,
+}
+
+/// PUT /news/:news_id/translations/:language
+async fn translate(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path((news_id, language)): Path<(String, String)>,
+    Json(data): Json<TranslateRequest>,
+) -> Result<Json<NewsTranslation>> {
+    let connection = state.connection();
+
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    let translation = news
+        .translate(
+            language.as_str(),
+            data.title.as_str(),
+            &data.body,
+            connection,
+        )
+        .await?;
+    audit.action("news.translation");
+    audit.target(news.id());
+    Ok(Json(translation))
+}
+
+fn translate_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Create or replace the translation of news to the given language. The default language \
+    is edited on the news itself.",
+    )
+    .response::<200, Json<NewsTranslation>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
+/// DELETE /news/:news_id/translations/:language
+async fn remove_translation(
+    State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
+    Path((news_id, language)): Path<(String, String)>,
+) -> Result<Json<DeletionResponse>> {
+    let connection = state.connection();
+
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    news.remove_translation(language.as_str(), connection)
+        .await?;
+    audit.action("news.translation");
+    audit.target(news.id());
+    Ok(Json(DeletionResponse::from(true)))
+}
+
+fn remove_translation_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Remove the translation of news to the given language")
+        .response::<200, Json<DeletionResponse>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![NEWS_UPDATE.id.to_string()])
+}
+
 /// DELETE /news/:news_id
 async fn delete(
     State(state): State<ApplicationState>,
+    Extension(audit): Extension<Audit>,
     Path(news_id): Path<String>,
 ) -> Result<Json<DeletionResponse>> {
     let connection = state.connection();
 
-    sql_span!(
-        connection
-            .delete::<Option<serde_json::Value>>(&Id::try_from(("news", news_id.as_str()))?)
-            .await?
-    );
+    let news = News::from_id(news_id.as_str(), connection).await?;
+    audit.record("news.delete", news.id(), Some(&news), None);
+    news.delete(connection).await?;
     Ok(Json(DeletionResponse::from(true)))
 }
 
@@ -254,13 +575,18 @@ fn delete_docs(op: TransformOperation) -> TransformOperation {
 
 #[cfg(test)]
 mod tests {
+    use crate::data::news::content::ContentFormat;
+    use crate::data::news::revision::{NewsDiff, NewsRevision};
+    use crate::data::news::translation::{MissingTranslations, NewsTranslations};
     use crate::data::news::News;
-    use crate::prelude::Page;
+    use crate::prelude::{ApplicationError, Page};
     use crate::tests::TestSuite;
-    use axum::http::header::AUTHORIZATION;
+    use axum::http::header::{ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
     use axum::http::StatusCode;
     use axum::BoxError;
     use axum_test_helper::{TestClient, TestResponse};
+    use image::ImageOutputFormat;
+    use std::io::Cursor;
 
     async fn setup(connector: &TestClient, session: &str) -> TestResponse {
         connector
@@ -276,7 +602,6 @@ mod tests {
 
     #[tokio::test]
     async fn test_create() -> Result<(), BoxError> {
-        // TODO: test file upload
         let suite = TestSuite::start().await?;
 
         let session = suite.authenticate("username", "password", None).await;
@@ -287,15 +612,142 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn test_get() -> Result<(), BoxError> {
+    async fn test_create_markdown() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = suite
+            .connector()
+            .post("/news")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "title": "title",
+                "format": "markdown",
+                "source": "**nice** content <script>alert(1)</script>",
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        let news = response.json::<News>().await;
+        assert_eq!(ContentFormat::Markdown, news.body().format);
+        assert_eq!(
+            Some("**nice** content <script>alert(1)</script>"),
+            news.body().source.as_deref()
+        );
+        assert_eq!(
+            Some("<p><strong>nice</strong> content </p>\n"),
+            news.body().content.as_deref()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_upload_image() -> Result<(), BoxError> {
         let suite = TestSuite::start().await?;
 
         let session = suite.authenticate("username", "password", None).await;
         let response = setup(suite.connector(), session.as_str()).await;
         let news = response.json::<News>().await;
+
+        let mut image = Cursor::new(Vec::new());
+        image::RgbImage::new(640, 480).write_to(&mut image, ImageOutputFormat::Jpeg(80))?;
+        let image = image.into_inner();
         let response = suite
+            .connector()
+            .post(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body(image.clone())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let replaced = suite
             .connector()
             .get(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<News>()
+            .await
+            .images()
+            .clone()
+            .unwrap();
+
+        // uploading again replaces the previous image
+        let response = suite
+            .connector()
+            .post(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body(image)
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let response = suite
+            .connector()
+            .get(replaced.card.webp.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let news = suite
+            .connector()
+            .get(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<News>()
+            .await;
+        let images = news.images().clone().unwrap();
+        assert_eq!(
+            (320, 180),
+            (images.thumbnail.width, images.thumbnail.height)
+        );
+        let asset = images.card.webp;
+
+        let response = suite.connector().get(asset.as_str()).send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!("image/webp", response.headers()[CONTENT_TYPE]);
+        let etag = response.headers()[ETAG].clone();
+
+        let response = suite
+            .connector()
+            .get(asset.as_str())
+            .header(IF_NONE_MATCH, etag)
+            .send()
+            .await;
+        assert_eq!(StatusCode::NOT_MODIFIED, response.status());
+
+        // deleting the news removes its assets
+        suite
+            .connector()
+            .delete(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        let response = suite.connector().get(asset.as_str()).send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_get() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let news = response.json::<News>().await;
+        let path = format!("/news/{}", news.id().to_string());
+
+        // unpublished news are hidden from the public
+        let response = suite.connector().get(path.as_str()).send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .get(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
             .send()
             .await;
         assert_eq!(StatusCode::OK, response.status());
@@ -305,6 +757,69 @@ mod tests {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_get_shown() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let now = chrono::Utc::now();
+        for news in [
+            serde_json::json!({ "title": "old", "shown": true }),
+            serde_json::json!({ "title": "pinned", "shown": true, "pinned": true }),
+            serde_json::json!({ "title": "new", "shown": true }),
+            serde_json::json!({ "title": "hidden" }),
+            serde_json::json!({
+                "title": "queued",
+                "shown": true,
+                "publishAt": now + chrono::Duration::days(1)
+            }),
+            serde_json::json!({
+                "title": "expired",
+                "shown": true,
+                "unpublishAt": now - chrono::Duration::days(1)
+            }),
+        ] {
+            let response = suite
+                .connector()
+                .post("/news")
+                .header(AUTHORIZATION, session.as_str())
+                .json(&news)
+                .send()
+                .await;
+            assert_eq!(StatusCode::CREATED, response.status());
+        }
+
+        let response = suite
+            .connector()
+            .post("/news")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "title": "invalid",
+                "publishAt": now,
+                "unpublishAt": now - chrono::Duration::days(1)
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let page = suite
+            .connector()
+            .get("/news/shown")
+            .send()
+            .await
+            .json::<Page<News>>()
+            .await;
+        assert_eq!(
+            vec!["pinned", "new", "old"],
+            page.data
+                .iter()
+                .map(|news| news.title().as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_get_all() -> Result<(), BoxError> {
         let suite = TestSuite::start().await?;
@@ -372,4 +887,294 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_patch() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let news = response.json::<News>().await;
+
+        let response = suite
+            .connector()
+            .patch(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "revision": 1,
+                "shown": true,
+                "createdAt": "2000-01-01T00:00:00Z"
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let patched = response.json::<News>().await;
+        assert!(patched.shown());
+        assert_eq!(news.title(), patched.title());
+        assert_eq!(news.body(), patched.body());
+        assert_eq!(news.created_at(), patched.created_at());
+
+        // the id within the body has to match the path
+        let response = suite
+            .connector()
+            .patch(format!("/news/{}", news.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "id": "news:other",
+                "revision": 2,
+                "title": "other"
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_revisions() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let news = response.json::<News>().await;
+        let path = format!("/news/{}", news.id().to_string());
+
+        let update = |content: &str, revision: u32| {
+            serde_json::json!({
+                "id": news.id().to_string(),
+                "title": "title",
+                "content": content,
+                "shown": true,
+                "revision": revision,
+                "createdAt": news.created_at().clone()
+            })
+        };
+        let response = suite
+            .connector()
+            .put(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&update("other content here", 1))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(2, *response.json::<News>().await.revision());
+
+        // updates based on an outdated revision are rejected
+        let response = suite
+            .connector()
+            .put(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&update("stale content", 1))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        let revisions = suite
+            .connector()
+            .get(format!("{}/revisions", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<NewsRevision>>()
+            .await;
+        assert_eq!(
+            vec![2, 1],
+            revisions
+                .iter()
+                .map(|revision| revision.revision)
+                .collect::<Vec<u32>>()
+        );
+
+        let diff = suite
+            .connector()
+            .get(format!("{}/revisions/diff?from=1&to=2", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<NewsDiff>()
+            .await;
+        assert_eq!(3, diff.content.len());
+
+        let response = suite
+            .connector()
+            .post(format!("{}/revisions/1/restore", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let restored = response.json::<News>().await;
+        assert_eq!(3, *restored.revision());
+        assert_eq!(news.body(), restored.body());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_update() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let id = response.json::<News>().await.id().to_string();
+
+        // both edits are based on the same revision, the second one loses the race
+        let mut first = News::from_id(id.as_str(), suite.connection()).await?;
+        let mut second = first.clone();
+        let changes = first.clone();
+        first.update(&changes, None, suite.connection()).await?;
+        let result = second.update(&changes, None, suite.connection()).await;
+        assert!(matches!(result, Err(ApplicationError::Conflict(_))));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_translations() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let response = setup(suite.connector(), session.as_str()).await;
+        let news = response.json::<News>().await;
+        let path = format!("/news/{}", news.id().to_string());
+
+        let missing = suite
+            .connector()
+            .get("/news/translations/missing")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<MissingTranslations>>()
+            .await;
+        assert_eq!(vec!["en".to_owned()], missing[0].missing);
+
+        // the default language is edited on the news itself
+        let response = suite
+            .connector()
+            .put(format!("{}/translations/de", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "title": "Titel" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .put(format!("{}/translations/en", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "title": "english title",
+                "format": "markdown",
+                "source": "*english* content"
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+
+        let translations = suite
+            .connector()
+            .get(format!("{}/translations", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<NewsTranslations>()
+            .await;
+        assert_eq!(1, translations.translations.len());
+        assert!(translations.missing.is_empty());
+
+        let english = suite
+            .connector()
+            .get(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .header(ACCEPT_LANGUAGE, "en-US,en;q=0.9")
+            .send()
+            .await
+            .json::<News>()
+            .await;
+        assert_eq!("en", english.language());
+        assert_eq!("english title", english.title());
+        assert_eq!(
+            Some("<p><em>english</em> content</p>\n"),
+            english.body().content.as_deref()
+        );
+
+        // the query parameter overrides the header
+        let german = suite
+            .connector()
+            .get(format!("{}?lang=de", path).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .header(ACCEPT_LANGUAGE, "en")
+            .send()
+            .await
+            .json::<News>()
+            .await;
+        assert_eq!("de", german.language());
+        assert_eq!("title", german.title());
+
+        // unknown languages fall back to the default language
+        let fallback = suite
+            .connector()
+            .get(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .header(ACCEPT_LANGUAGE, "fr")
+            .send()
+            .await
+            .json::<News>()
+            .await;
+        assert_eq!(news, fallback);
+
+        let response = suite
+            .connector()
+            .get(format!("{}?lang=fr", path).as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_feed() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        for news in [
+            serde_json::json!({ "title": "shown", "content": "<p>content</p>", "shown": true }),
+            serde_json::json!({ "title": "hidden" }),
+        ] {
+            suite
+                .connector()
+                .post("/news")
+                .header(AUTHORIZATION, session.as_str())
+                .json(&news)
+                .send()
+                .await;
+        }
+
+        let response = suite.connector().get("/news/feed.rss").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "application/rss+xml; charset=utf-8",
+            response.headers()[CONTENT_TYPE]
+        );
+        let channel = rss::Channel::read_from(response.bytes().await.as_ref())?;
+        assert_eq!(1, channel.items().len());
+        assert_eq!(Some("shown"), channel.items()[0].title());
+        assert_eq!(Some("<p>content</p>"), channel.items()[0].description());
+
+        let response = suite.connector().get("/news/feed.atom").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            "application/atom+xml; charset=utf-8",
+            response.headers()[CONTENT_TYPE]
+        );
+        let feed = atom_syndication::Feed::read_from(response.bytes().await.as_ref())?;
+        assert_eq!(1, feed.entries().len());
+        assert!(feed.entries()[0]
+            .id()
+            .starts_with(crate::prelude::ROOT.as_str()));
+
+        Ok(())
+    }
 }
@@ -0,0 +1,299 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::account::Account;
+use crate::data::notification::{Notification, NotificationPreferences, NotificationSearch};
+use crate::prelude::*;
+use aide::axum::routing::{get_with, post_with};
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, Query, State};
+use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::Extension;
+use futures::StreamExt;
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/",
+            get_with(get_all, get_all_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/read",
+            post_with(read_all, read_all_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/preferences",
+            get_with(get_preferences, get_preferences_docs)
+                .put_with(set_preferences, set_preferences_docs)
+                .layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/live",
+            get_with(get_live, get_live_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .api_route(
+            "/:notification_id/read",
+            post_with(read, read_docs).layer(require_session!(state, DEFAULT)),
+        )
+        .with_state(state)
+}
+
+/// GET /notification
+async fn get_all(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Query(request): Query<PagingRequest>,
+    Query(search): Query<NotificationSearch>,
+) -> Result<Json<Page<Notification>>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        Notification::list(account.id(), &search, request, connection).await?,
+    ))
+}
+
+fn get_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get a page of the notifications of the account, the latest first")
+        .response::<200, Json<Page<Notification>>>()
+        .security_requirement("Session")
+}
+
+/// POST /notification/:notification_id/read
+async fn read(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Path(notification_id): Path<String>,
+) -> Result<Json<Notification>> {
+    let connection = state.connection();
+
+    let mut notification =
+        Notification::from_id(notification_id.as_str(), account.id(), connection).await?;
+    notification.read(connection).await?;
+    Ok(Json(notification))
+}
+
+fn read_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Mark the notification as read")
+        .response::<200, Json<Notification>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement("Session")
+}
+
+/// POST /notification/read
+async fn read_all(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<CreationResponse>> {
+    let connection = state.connection();
+
+    Notification::read_all(account.id(), connection).await?;
+    Ok(Json(CreationResponse::from(true)))
+}
+
+fn read_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Mark all notifications of the account as read")
+        .response::<200, Json<CreationResponse>>()
+        .security_requirement("Session")
+}
+
+/// GET /notification/preferences
+async fn get_preferences(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Json<NotificationPreferences>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        NotificationPreferences::of(account.id(), connection).await?,
+    ))
+}
+
+fn get_preferences_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Get the notification preferences of the account")
+        .response::<200, Json<NotificationPreferences>>()
+        .security_requirement("Session")
+}
+
+/// PUT /notification/preferences
+async fn set_preferences(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+    Json(data): Json<NotificationPreferences>,
+) -> Result<Json<NotificationPreferences>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        NotificationPreferences::set(account.id(), data.muted, connection).await?,
+    ))
+}
+
+fn set_preferences_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Replace the notification preferences of the account. Notifications of muted kinds are \
+    neither stored nor pushed.",
+    )
+    .response::<200, Json<NotificationPreferences>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .security_requirement("Session")
+}
+
+/// GET /notification/live
+async fn get_live(
+    State(state): State<ApplicationState>,
+    Extension(account): Extension<Account>,
+) -> Result<Response> {
+    let stream = state.live().subscribe(account.id()).map(|message| {
+        SseEvent::default()
+            .event(message.name())
+            .json_data(&message)
+    });
+
+    Ok(Sse::new(stream)
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn get_live_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Stream new notifications of the account as server-sent events. Every message carries a \
+    LiveMessage of the type notification as json, a lagged message tells that the client missed \
+    notifications and has to fetch them again.",
+    )
+    .response_with::<200, String, _>(|res| res.description("text/event-stream of LiveMessage"))
+    .security_requirement("Session")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::event::Event;
+    use crate::data::notification::{Notification, NotificationKind, NotificationPreferences};
+    use crate::prelude::Page;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_notifications() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let event = suite
+            .connector()
+            .post("/event")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "name",
+                "description": "description",
+                "start": Utc::now() + Duration::hours(1),
+                "end": Utc::now() + Duration::hours(2),
+            }))
+            .send()
+            .await
+            .json::<Event>()
+            .await;
+        let response = suite
+            .connector()
+            .post(format!("/event/{}/registration", event.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        // the login and the registration have been notified
+        let page = suite
+            .connector()
+            .get("/notification?unread=true")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<Notification>>()
+            .await;
+        assert_eq!(2, page.total);
+        let registration = &page.data[0];
+        assert_eq!(NotificationKind::Registration, *registration.kind());
+        assert_eq!(Some(event.id().clone()), *registration.target());
+        assert_eq!(NotificationKind::Security, *page.data[1].kind());
+
+        let notification = suite
+            .connector()
+            .post(format!("/notification/{}/read", registration.id().to_string()).as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Notification>()
+            .await;
+        assert!(notification.read_at().is_some());
+        let page = suite
+            .connector()
+            .get("/notification?unread=true")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<Notification>>()
+            .await;
+        assert_eq!(1, page.total);
+
+        // muted kinds aren't stored
+        let preferences = suite
+            .connector()
+            .put("/notification/preferences")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "muted": ["security", "security"] }))
+            .send()
+            .await
+            .json::<NotificationPreferences>()
+            .await;
+        assert_eq!(vec![NotificationKind::Security], preferences.muted);
+        suite.authenticate("username", "password", None).await;
+
+        let response = suite
+            .connector()
+            .post("/notification/read")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        let page = suite
+            .connector()
+            .get("/notification")
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<Notification>>()
+            .await;
+        assert_eq!(2, page.total);
+        assert!(page
+            .data
+            .iter()
+            .all(|notification| notification.read_at().is_some()));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,205 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::player::{LeaderboardSearch, PlayerProfile, PlayerStats};
+use crate::data::rating::{Rating, RatingChange};
+use crate::prelude::*;
+use aide::axum::routing::get_with;
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, Query, State};
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route(
+            "/leaderboard",
+            get_with(get_leaderboard, get_leaderboard_docs),
+        )
+        .api_route("/ratings", get_with(get_ratings, get_ratings_docs))
+        .api_route("/:uuid", get_with(get_profile, get_profile_docs))
+        .api_route(
+            "/:uuid/ratings",
+            get_with(get_rating_history, get_rating_history_docs),
+        )
+        .with_state(state)
+}
+
+/// GET /player/leaderboard
+async fn get_leaderboard(
+    State(state): State<ApplicationState>,
+    Query(request): Query<PagingRequest>,
+    Query(search): Query<LeaderboardSearch>,
+) -> Result<Json<Page<PlayerStats>>> {
+    let connection = state.connection();
+
+    Ok(Json(search.execute(request, connection).await?))
+}
+
+fn get_leaderboard_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get a page of the players ranked by their statistics, optionally restricted to an event \
+    or the matches played within a season.",
+    )
+    .response::<200, Json<Page<PlayerStats>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /player/:uuid
+async fn get_profile(
+    State(state): State<ApplicationState>,
+    Path(uuid): Path<String>,
+) -> Result<Json<PlayerProfile>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        PlayerProfile::from_uuid(uuid.as_str(), connection).await?,
+    ))
+}
+
+fn get_profile_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get the public profile of a player by the uuid of their minecraft account, including \
+    their statistics in total and per event.",
+    )
+    .response::<200, Json<PlayerProfile>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct RatingSearch {
+    /// the season of the ratings
+    season: Option<String>,
+}
+
+/// GET /player/ratings
+async fn get_ratings(
+    State(state): State<ApplicationState>,
+    Query(request): Query<PagingRequest>,
+    Query(search): Query<RatingSearch>,
+) -> Result<Json<Page<Rating>>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        Rating::ranking(search.season.as_deref(), request, connection).await?,
+    ))
+}
+
+fn get_ratings_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Get a page of the players ranked by their rating within the given season, the current \
+    season by default. Without any season the ratings outside of seasons are ranked.",
+    )
+    .response::<200, Json<Page<Rating>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+/// GET /player/:uuid/ratings
+async fn get_rating_history(
+    State(state): State<ApplicationState>,
+    Path(uuid): Path<String>,
+    Query(search): Query<RatingSearch>,
+) -> Result<Json<Vec<RatingChange>>> {
+    let connection = state.connection();
+
+    Ok(Json(
+        RatingChange::history(uuid.as_str(), search.season.as_deref(), connection).await?,
+    ))
+}
+
+fn get_rating_history_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "List the rating changes of a player, the earliest first, optionally restricted to a \
+    season",
+    )
+    .response::<200, Json<Vec<RatingChange>>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::player::PlayerStats;
+    use crate::prelude::Page;
+    use crate::tests::TestSuite;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+
+    #[tokio::test]
+    async fn test_leaderboard() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let mut account = suite.account().clone();
+        account.link("winner", suite.connection()).await?;
+        suite
+            .connection()
+            .query(
+                "CREATE match_participation SET result = match_result:a, event = event:a, \
+                uuid = 'winner', kills = 4, deaths = 1, wins = 1, duration = 60",
+            )
+            .query(
+                "CREATE match_participation SET result = match_result:b, event = event:b, \
+                uuid = 'winner', kills = 2, deaths = 1, wins = 1, duration = 60",
+            )
+            .query(
+                "CREATE match_participation SET result = match_result:a, event = event:a, \
+                uuid = 'loser', kills = 1, deaths = 4, wins = 0, duration = 60",
+            )
+            .await?
+            .check()?;
+
+        let page = suite
+            .connector()
+            .get("/player/leaderboard")
+            .send()
+            .await
+            .json::<Page<PlayerStats>>()
+            .await;
+        assert_eq!(2, page.total);
+        assert_eq!("winner", page.data[0].uuid);
+        assert_eq!(2, page.data[0].wins);
+        assert_eq!(3.0, page.data[0].kd);
+
+        let page = suite
+            .connector()
+            .get("/player/leaderboard?event=event:b")
+            .send()
+            .await
+            .json::<Page<PlayerStats>>()
+            .await;
+        assert_eq!(1, page.total);
+
+        let response = suite.connector().get("/player/winner").send().await;
+        assert_eq!(StatusCode::OK, response.status());
+        let profile = response.json::<serde_json::Value>().await;
+        assert_eq!("username", profile["username"]);
+        assert_eq!(2, profile["total"]["matches"]);
+        assert_eq!(2, profile["events"].as_array().unwrap().len());
+
+        let response = suite.connector().get("/player/unknown").send().await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::season::Season;
+use crate::prelude::*;
+use aide::axum::routing::{get_with, post_with};
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/", get_with(get_all, get_all_docs))
+        .api_route(
+            "/",
+            post_with(create, create_docs).layer(require_session!(state, SEASON_CREATE)),
+        )
+        .api_route(
+            "/:season_id/close",
+            post_with(close, close_docs).layer(require_session!(state, SEASON_UPDATE)),
+        )
+        .with_state(state)
+}
+
+/// GET /season
+async fn get_all(State(state): State<ApplicationState>) -> Result<Json<Vec<Season>>> {
+    let connection = state.connection();
+
+    Ok(Json(Season::all(connection).await?))
+}
+
+fn get_all_docs(op: TransformOperation) -> TransformOperation {
+    op.description("List all seasons, the latest first")
+        .response::<200, Json<Vec<Season>>>()
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct CreateSeasonRequest {
+    name: String,
+    /// the start of the season, now if absent
+    start: Option<DateTime<Utc>>,
+}
+
+/// POST /season
+async fn create(
+    State(state): State<ApplicationState>,
+    Json(data): Json<CreateSeasonRequest>,
+) -> Result<(StatusCode, Json<Season>)> {
+    let connection = state.connection();
+
+    let season = Season::begin(
+        data.name.as_str(),
+        data.start.unwrap_or_else(Utc::now),
+        connection,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(season)))
+}
+
+fn create_docs(op: TransformOperation) -> TransformOperation {
+    op.description("Start a new season, the current season is closed at its start")
+        .response::<201, Json<Season>>()
+        .response::<400, Json<ApplicationErrorResponse>>()
+        .security_requirement_scopes("Session", vec![SEASON_CREATE.id.to_string()])
+}
+
+#[derive(Deserialize, Debug, Clone, JsonSchema)]
+pub struct CloseSeasonRequest {
+    /// the end of the season, now if absent
+    end: Option<DateTime<Utc>>,
+}
+
+/// POST /season/:season_id/close
+async fn close(
+    State(state): State<ApplicationState>,
+    Path(season_id): Path<String>,
+    Json(data): Json<CloseSeasonRequest>,
+) -> Result<Json<Season>> {
+    let connection = state.connection();
+
+    let mut season = Season::from_id(season_id.as_str(), connection).await?;
+    season
+        .close(data.end.unwrap_or_else(Utc::now), connection)
+        .await?;
+    Ok(Json(season))
+}
+
+fn close_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Close the season without starting a new one. Matches played until the next season \
+    starts are rated outside of any season.",
+    )
+    .response::<200, Json<Season>>()
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<409, Json<ApplicationErrorResponse>>()
+    .security_requirement_scopes("Session", vec![SEASON_UPDATE.id.to_string()])
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::season::Season;
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+    use chrono::{Duration, Utc};
+
+    #[tokio::test]
+    async fn test_create() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let start = Utc::now() - Duration::days(30);
+        let response = suite
+            .connector()
+            .post("/season")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Season 1", "start": start }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        // seasons can't start before the current one
+        let response = suite
+            .connector()
+            .post("/season")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Season 0", "start": start - Duration::days(1) }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        let response = suite
+            .connector()
+            .post("/season")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Season 2" }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let current = response.json::<Season>().await;
+
+        let seasons = suite
+            .connector()
+            .get("/season")
+            .send()
+            .await
+            .json::<Vec<Season>>()
+            .await;
+        assert_eq!(2, seasons.len());
+        assert_eq!(current, seasons[0]);
+        assert_eq!(Some(*current.start()), *seasons[1].end());
+
+        let path = format!("/season/{}/close", current.id().to_string());
+        let response = suite
+            .connector()
+            .post(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({}))
+            .send()
+            .await;
+        assert_eq!(StatusCode::OK, response.status());
+        assert!(response.json::<Season>().await.end().is_some());
+
+        let response = suite
+            .connector()
+            .post(path.as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({}))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CONFLICT, response.status());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_scheduled() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+
+        let session = suite.authenticate("username", "password", None).await;
+        let running = suite
+            .connector()
+            .post("/season")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "name": "Season 1" }))
+            .send()
+            .await
+            .json::<Season>()
+            .await;
+        let response = suite
+            .connector()
+            .post("/season")
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({
+                "name": "Season 2",
+                "start": Utc::now() + Duration::days(7),
+            }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        // the running season stays current until the scheduled one starts
+        let current = Season::current(suite.connection()).await?.unwrap();
+        assert_eq!(running.id(), current.id());
+        assert!(current.end().is_some());
+
+        Ok(())
+    }
+}
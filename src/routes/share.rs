@@ -0,0 +1,206 @@
+/*
+ *
+ * The MIT License (MIT)
+ *
+ * Copyright (c) 2023 Fritz Ochsmann
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining a copy
+ * of this software and associated documentation files (the "Software"), to deal
+ * in the Software without restriction, including without limitation the rights
+ * to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+ * copies of the Software, and to permit persons to whom the Software is
+ * furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included in all
+ * copies or substantial portions of the Software.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+ * IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+ * FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+ * AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+ * LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+ * OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+ * SOFTWARE.
+ *
+ */
+
+use crate::data::account::Account;
+use crate::data::notification::{notify, NotificationKind};
+use crate::data::schematic::share::SchematicShare;
+use crate::data::schematic::Schematic;
+use crate::prelude::*;
+use crate::routes::account::schematic::{file_response, DownloadRequest, FileResponse};
+use crate::routes::extractor::ClientAddress;
+use aide::axum::routing::get_with;
+use aide::axum::ApiRouter;
+use aide::transform::TransformOperation;
+use axum::extract::{Path, Query, State};
+use axum::http::header::USER_AGENT;
+use axum::http::HeaderMap;
+
+pub fn router(state: ApplicationState) -> ApiRouter {
+    ApiRouter::new()
+        .api_route("/:token", get_with(download, download_docs))
+        .with_state(state)
+}
+
+/// GET /share/:token
+async fn download(
+    State(state): State<ApplicationState>,
+    Path(token): Path<String>,
+    Query(request): Query<DownloadRequest>,
+    ClientAddress(address): ClientAddress,
+    headers: HeaderMap,
+) -> Result<FileResponse> {
+    let connection = state.connection();
+
+    let mut share = SchematicShare::from_token(token.as_str(), connection).await?;
+    let schematic = Schematic::from_id(share.schematic().to_string().as_str(), connection)
+        .await?
+        .ok_or(ApplicationError::BadRequest(
+            "schematic not found".to_owned(),
+        ))?;
+    // convert before counting the download, so failed conversions don't use up the link
+    let response = file_response(schematic.data(), schematic.name(), request.format).await?;
+
+    let user_agent = headers
+        .get(USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    if !share.access(address, user_agent, connection).await? {
+        return Err(ApplicationError::Forbidden(
+            "share link is no longer available".to_owned(),
+        ));
+    }
+    if let Some(owner) = Account::from_uuid(schematic.owner(), connection).await? {
+        notify(
+            &state,
+            owner.id(),
+            NotificationKind::SchematicShare,
+            format!(
+                "Your shared schematic {} has been downloaded",
+                schematic.name()
+            )
+            .as_str(),
+            Some(share.id()),
+        )
+        .await;
+    }
+    Ok(response)
+}
+
+fn download_docs(op: TransformOperation) -> TransformOperation {
+    op.description(
+        "Download a shared schematic in the requested format (defaults to sponge). Fails if the \
+    link has been revoked, is expired or exceeded its maximum amount of downloads.",
+    )
+    .response::<400, Json<ApplicationErrorResponse>>()
+    .response::<403, Json<ApplicationErrorResponse>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::data::schematic::format::{SchematicFormat, Structure};
+    use crate::data::schematic::share::SchematicShareAccess;
+    use crate::data::schematic::Schematic;
+    use crate::prelude::Page;
+    use crate::routes::account::schematic::{SchematicEntry, SchematicShareLink};
+    use crate::tests::TestSuite;
+    use axum::http::header::AUTHORIZATION;
+    use axum::http::StatusCode;
+    use axum::BoxError;
+
+    #[tokio::test]
+    async fn test_share() -> Result<(), BoxError> {
+        let suite = TestSuite::start().await?;
+        let mut account = suite.account().clone();
+        account.link("uuid", suite.connection()).await?;
+        let id = account.id().to_string();
+
+        let session = suite.authenticate("username", "password", None).await;
+        let structure = Structure::empty(2, 2, 2, 3465)?;
+        let response = suite
+            .connector()
+            .post(format!("/account/{id}/schematic/upload/house").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .body(structure.write(SchematicFormat::Schem, "house")?)
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+
+        let page = suite
+            .connector()
+            .get(format!("/account/{id}/schematic").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Page<SchematicEntry>>()
+            .await;
+        let schematic = page.data[0].id.to_string();
+
+        let response = suite
+            .connector()
+            .post(format!("/account/{id}/schematic/{schematic}/shares").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .json(&serde_json::json!({ "maxDownloads": 1 }))
+            .send()
+            .await;
+        assert_eq!(StatusCode::CREATED, response.status());
+        let link = response.json::<SchematicShareLink>().await;
+
+        // tampered tokens are rejected
+        let response = suite
+            .connector()
+            .get(format!("/share/{}0", link.token).as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+
+        // failed conversions don't count as download
+        let stored = Schematic::from_id(schematic.as_str(), suite.connection())
+            .await?
+            .unwrap();
+        let corrupt = |data: String| {
+            suite
+                .connection()
+                .query("UPDATE $schematic SET data = $data")
+                .bind(("schematic", stored.id().to_thing()))
+                .bind(("data", data))
+        };
+        corrupt(openssl::base64::encode_block(b"not a schematic")).await?;
+        let response = suite
+            .connector()
+            .get(format!("/share/{}?format=litematic", link.token).as_str())
+            .send()
+            .await;
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        corrupt(stored.data().clone()).await?;
+
+        for status in [StatusCode::OK, StatusCode::FORBIDDEN] {
+            let response = suite
+                .connector()
+                .get(format!("/share/{}", link.token).as_str())
+                .send()
+                .await;
+            assert_eq!(status, response.status());
+        }
+
+        let share = link.share.id().to_string();
+        let log = suite
+            .connector()
+            .get(format!("/account/{id}/schematic/{schematic}/shares/{share}/log").as_str())
+            .header(AUTHORIZATION, session.as_str())
+            .send()
+            .await
+            .json::<Vec<SchematicShareAccess>>()
+            .await;
+        assert_eq!(
+            vec![false, true],
+            log.iter()
+                .map(|access| access.granted)
+                .collect::<Vec<bool>>()
+        );
+
+        Ok(())
+    }
+}
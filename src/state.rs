@@ -24,16 +24,22 @@
  *
  */
 
+use crate::data::event::live::LiveBus;
 use crate::prelude::DatabaseConnection;
 
 #[derive(Clone, Debug, Getters)]
 #[get = "pub"]
 pub struct ApplicationState {
     connection: DatabaseConnection,
+    /// distributes the live updates of events
+    live: LiveBus,
 }
 
 impl From<DatabaseConnection> for ApplicationState {
     fn from(connection: DatabaseConnection) -> Self {
-        Self { connection }
+        Self {
+            connection,
+            live: LiveBus::default(),
+        }
     }
 }
@@ -30,7 +30,8 @@ use crate::data::account::create::CreateAccount;
 use crate::data::account::Account;
 use crate::database::DatabaseConnection;
 use crate::error::ApplicationError;
-use crate::prelude::PERMISSIONS;
+use crate::prelude::{ApplicationState, PERMISSIONS};
+use axum::http::header::AUTHORIZATION;
 use axum::http::StatusCode;
 use axum::BoxError;
 use axum_test_helper::{TestClient, TestResponse};
@@ -60,7 +61,9 @@ impl TestSuite {
     }
 
     async fn start_axum(connection: DatabaseConnection) -> Result<TestClient, BoxError> {
-        Ok(TestClient::new(crate::router(connection).await?))
+        Ok(TestClient::new(
+            crate::router(ApplicationState::from(connection)).await?,
+        ))
     }
 
     pub async fn start() -> Result<Self, BoxError> {
@@ -99,6 +102,32 @@ impl TestSuite {
         session.id.to_string()
     }
 
+    /// Creates a machine client with the given (human) session and starts a session for it
+    pub async fn authenticate_machine(&self, session: &str) -> String {
+        let credentials = self
+            .connector
+            .post("/auth/machine")
+            .header(AUTHORIZATION, session)
+            .json(&serde_json::json!({ "name": "game server" }))
+            .send()
+            .await
+            .json::<serde_json::Value>()
+            .await;
+
+        let response = self
+            .connector
+            .post("/auth/machine/login")
+            .json(&serde_json::json!({
+                "clientId": credentials["clientId"],
+                "secret": credentials["secret"]
+            }))
+            .send()
+            .await;
+        let session = response.json::<Session>().await;
+
+        session.id.to_string()
+    }
+
     pub async fn try_login(
         &self,
         username: &str,